@@ -5,7 +5,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
-pub use pipeline::wgpu_compute_header::{compile, compute_run, ComputeShader};
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
 
 pub use pipeline::bind::{BindGroup1, BindGroup2, BindGroup3, BufferData, Indices, Vertex};
 pub use pipeline::AbstractBind;
@@ -32,7 +32,7 @@ async fn execute_gpu() {
                 label: None,
                 features: wgpu::Features::empty(),
                 limits: wgpu::Limits {
-                    max_bind_groups: 5,
+                    max_bind_groups: 6,
                     ..Default::default()
                 },
             },
@@ -41,6 +41,8 @@ async fn execute_gpu() {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     // qualifiers
     // buffer: is a buffer?
     // in: this parameter must be bound to before the program runs
@@ -52,8 +54,15 @@ async fn execute_gpu() {
     // loop: one or more of these loop annotations are required per program. Atm, the values bound is assumed to be of equal length and this gives the number of iterations(gl_GlobalInvocationID.x)
     //      the size of any out buffers that need to be created
 
-    // TODO how to set a work group larger than 1?
+    const NUM_PARTICLES: i32 = 2;
+
     my_shader! {BOIDS = {
+        // The last workgroup has invocations past the end of the particles,
+        // they return before `main` as they are past the size of the dispatch
+        workgroup_size(64);
+
+        [[const] int] NUM_PARTICLES;
+
         [group1 [uniform in] float] deltaT;
         [group2 [uniform in] float] rule1Distance;
         [group2 [uniform in] float] rule2Distance;
@@ -82,8 +91,6 @@ async fn execute_gpu() {
                 vec2 vel;
             }; */
 
-
-
             void main() {
                 // https://github.com/austinEng/Project6-Vulkan-Flocking/blob/master/data/shaders/computeparticles/particle.comp
                 uint index = gl_GlobalInvocationID.x;
@@ -98,8 +105,7 @@ async fn execute_gpu() {
                 int cVelCount = 0;
                 vec2 pos;
                 vec2 vel;
-                // TODO The iteration of the number of particles was set by a #define value
-                for (int i = 0; i < 2; ++i) {
+                for (int i = 0; i < NUM_PARTICLES; ++i) {
                     if (i == index) { continue; }
                     pos = srcParticlePos[i].xy;
                     vel = srcParticlePos[i].xy;
@@ -139,16 +145,25 @@ async fn execute_gpu() {
     }}
 
     const S: ComputeShader = eager_compute_shader! {BOIDS!()};
-    eager_binding! {context = BOIDS!()};
+    eager_binding! {Context = BOIDS!()};
 
-    let program = compile(&S, &device, context.get_layout(&device)).await;
+    let program = ContextProgram::compile(
+        &S,
+        &device,
+        ComputeCompileArgs {
+            constants: ContextProgram::constants(NUM_PARTICLES),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
 
     let srcParticlePos = BufferData::new(vec![[0.0, 0.0, 0.0], [0.3, 0.2, 0.0]]);
     let srcParticleVel = BufferData::new(vec![[0.01, -0.02, 0.0], [-0.05, -0.03, 0.0]]);
     let srcParticle_bg = BindGroup2::new(&device, &srcParticlePos, &srcParticleVel);
 
-    let dstParticlePos = BufferData::new(vec![[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]]);
-    let dstParticleVel = BufferData::new(vec![[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]]);
+    let dstParticlePos = BufferData::new(vec![[0.0, 0.0, 0.0]; NUM_PARTICLES as usize]);
+    let dstParticleVel = BufferData::new(vec![[0.0, 0.0, 0.0]; NUM_PARTICLES as usize]);
     let dstParticle_bg = BindGroup2::new(&device, &dstParticlePos, &dstParticleVel);
 
     let deltaT = BindGroup1::new(&device, &BufferData::new(0.04));
@@ -167,41 +182,50 @@ async fn execute_gpu() {
         &BufferData::new(0.005),
     );
 
-    let mut loop_count = 0;
+    // The source particles are read only and the destination particles are written,
+    // so instead of swapping the groups each frame the new particles are copied back to the source
+    let srcParticle_buffers = srcParticle_bg.get_buffers();
+    let dstParticle_buffers = dstParticle_bg.get_buffers();
+    let particles_size = std::mem::size_of::<f32>() as u64 * 4 * NUM_PARTICLES as u64;
+
+    let size = DispatchSize::new(&device, [NUM_PARTICLES as u32, 1, 1]);
+
     loop {
-        let (srcParticle, dstParticle) = if loop_count % 2 == 0 {
-            (&srcParticle_bg, &dstParticle_bg)
-        } else {
-            (&dstParticle_bg, &srcParticle_bg)
-        };
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
-            let mut cpass =
-                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&program.pipeline);
+            let mut cpass = program.compute_pass(&mut encoder);
 
-            let context1 = (&context).set_deltaT(&mut cpass, &deltaT);
+            let context1 = program.begin(&mut cpass).set_deltaT(&mut cpass, &deltaT);
 
             let context2 =
                 context1.set_rule1Distance_rule2Distance_rule3Distance(&mut cpass, &ruleDistance);
 
             let context3 = context2.set_rule1Scale_rule2Scale_rule3Scale(&mut cpass, &ruleScale);
 
-            let context4 = context3.set_srcParticlePos_srcParticleVel(&mut cpass, &srcParticle);
+            let context4 = context3.set_srcParticlePos_srcParticleVel(&mut cpass, &srcParticle_bg);
 
-            let context5 = context4.set_dstParticlePos_dstParticleVel(&mut cpass, &dstParticle);
+            let context5 = context4.set_dstParticlePos_dstParticleVel(&mut cpass, &dstParticle_bg);
 
             {
-                context5.runnable(|| compute_run(&mut cpass, 2));
+                context5.dispatch(&mut cpass, &size);
             }
         }
-        let dstParticlePos = dstParticle.setup_read_0(
+        for (src, dst) in srcParticle_buffers.iter().zip(&dstParticle_buffers) {
+            encoder.copy_buffer_to_buffer(
+                dst.as_ref().unwrap(),
+                0,
+                src.as_ref().unwrap(),
+                0,
+                particles_size,
+            );
+        }
+        let dstParticlePos = dstParticle_bg.setup_read_0(
             &device,
             &mut encoder,
             0..std::mem::size_of::<f32>() as u64 * 8,
         );
-        let dstParticleVel = dstParticle.setup_read_1(
+        let dstParticleVel = dstParticle_bg.setup_read_1(
             &device,
             &mut encoder,
             0..std::mem::size_of::<f32>() as u64 * 8,
@@ -239,8 +263,6 @@ async fn execute_gpu() {
                 .map(|v4| [v4[0], v4[1], v4[2]])
                 .collect::<Vec<[f32; 3]>>()
         );
-
-        loop_count += 1;
     }
 }
 
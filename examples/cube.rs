@@ -6,6 +6,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -13,8 +14,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 pub use pipeline::bind::{BindGroup2, BufferData, Indices, Vertex};
@@ -52,6 +52,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! {vertex = {
         [[vertex in] vec3] a_position;
         [[vertex in] vec3] vertexColor;
@@ -84,10 +86,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let (positions, _, index_data) = load_cube();
 
@@ -157,28 +165,26 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .expect("Timeout when acquiring next swap chain texture")
                     .output;
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
                     //
                     // Handle the setup for the pipeline
                     //
 
-                    let context1 = (&context).set_a_position(&mut rpass, &vertex_position);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_a_position(&mut rpass, &vertex_position);
 
                     {
                         let context2 = (&context1).set_vertexColor(&mut rpass, &vertex_color);
@@ -186,8 +192,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             let context3 =
                                 context2.set_u_view_u_proj(&mut rpass, &bind_group_view_proj);
                             {
-                                let _ =
-                                    context3.runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                                let _ = context3.draw_indexed(&mut rpass, &indices, 1).unwrap();
                             }
                         }
                     }
@@ -5,6 +5,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -12,8 +13,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 pub use pipeline::bind::{
@@ -58,6 +58,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     let queue = std::rc::Rc::new(queue);
 
     my_shader! {BAKE_VERTEXT = {
@@ -106,7 +108,31 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         }}
     }}
 
+    shader_lib! {SHADOW = {
+        t_Shadow;
+        s_Shadow;
+        {{
+            float fetch_shadow(int light_id, vec4 homogeneous_coords) {
+                if (homogeneous_coords.w <= 0.0) {
+                    return 1.0;
+                }
+                // compensate for the Y-flip difference between the NDC and texture coordinates
+                const vec2 flip_correction = vec2(0.5, -0.5);
+                // compute texture coordinates for shadow lookup
+                vec4 light_local = vec4(
+                    homogeneous_coords.xy * flip_correction/homogeneous_coords.w + 0.5,
+                    light_id,
+                    homogeneous_coords.z / homogeneous_coords.w
+                );
+                // do the lookup, using HW PCF and comparison
+                return texture(sampler2DArrayShadow(t_Shadow, s_Shadow), light_local);
+            }
+        }}
+    }}
+
     my_shader! {FRAGMENT = {
+        use SHADOW;
+
         [[in] vec3] v_Normal;
         [[in] vec4] v_Position;
 
@@ -125,22 +151,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         [group1 [uniform in] mat4] u_World;
         [group2 [uniform in] vec4] u_Color;
         {{
-            float fetch_shadow(int light_id, vec4 homogeneous_coords) {
-                if (homogeneous_coords.w <= 0.0) {
-                    return 1.0;
-                }
-                // compensate for the Y-flip difference between the NDC and texture coordinates
-                const vec2 flip_correction = vec2(0.5, -0.5);
-                // compute texture coordinates for shadow lookup
-                vec4 light_local = vec4(
-                    homogeneous_coords.xy * flip_correction/homogeneous_coords.w + 0.5,
-                    light_id,
-                    homogeneous_coords.z / homogeneous_coords.w
-                );
-                // do the lookup, using HW PCF and comparison
-                return texture(sampler2DArrayShadow(t_Shadow, s_Shadow), light_local);
-            }
-
             void main() {
                 vec3 normal = normalize(v_Normal);
                 // accumulate color
@@ -163,11 +173,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const B_V: GraphicsShader = eager_graphics_shader! {BAKE_VERTEXT!()};
     const B_F: GraphicsShader = eager_graphics_shader! {BAKE_FRAGMENT!()};
-    eager_binding! {bake_context = BAKE_VERTEXT!(), BAKE_FRAGMENT!()};
+    eager_binding! {BakeContext = BAKE_VERTEXT!(), BAKE_FRAGMENT!()};
 
     let (stencil_program, _) = compile_valid_stencil_program!(
         device,
-        bake_context,
+        BakeContextProgram,
         B_V,
         B_F,
         GraphicsCompileArgs {
@@ -175,7 +185,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 cull_mode: wgpu::CullMode::Back,
                 ..Default::default()
             },
-            color_target_state: None,
+            color_target_states: Vec::new(),
             depth_stencil_state: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
                 depth_write_enabled: true,
@@ -189,19 +199,21 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 clamp_depth: device.features().contains(wgpu::Features::DEPTH_CLAMPING),
             }),
             multisample_state: wgpu::MultisampleState::default(),
+            ..Default::default()
         }
-    );
+    )
+    .unwrap();
 
     const S_V: GraphicsShader = eager_graphics_shader! {VERTEXT!()};
     const S_F: GraphicsShader = eager_graphics_shader! {FRAGMENT!()};
-    eager_binding! {context = VERTEXT!(), FRAGMENT!()};
+    eager_binding! {Context = VERTEXT!(), FRAGMENT!()};
 
     let args = GraphicsCompileArgs {
         primitive_state: wgpu::PrimitiveState {
             cull_mode: wgpu::CullMode::Back,
             ..Default::default()
         },
-        color_target_state: Some(wgpu::ColorTargetState {
+        color_target_states: vec![wgpu::ColorTargetState {
             // Specify the size of the color data in the buffer
             // Bgra8UnormSrgb is specifically used since it is guaranteed to work on basically all browsers (32bit)
             format: wgpu::TextureFormat::Bgra8UnormSrgb,
@@ -211,7 +223,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
             alpha_blend: wgpu::BlendState::REPLACE,
             // We can adjust the mask to only include certain colors if we want to
             write_mask: wgpu::ColorWrite::ALL,
-        }),
+        }],
         depth_stencil_state: Some(wgpu::DepthStencilState {
             format: wgpu::TextureFormat::Depth32Float,
             depth_write_enabled: true,
@@ -221,9 +233,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
             clamp_depth: false,
         }),
         multisample_state: wgpu::MultisampleState::default(),
+        ..Default::default()
     };
 
-    let (program, _) = compile_valid_graphics_program!(device, context, S_V, S_F, args);
+    let (program, _) =
+        compile_valid_graphics_program!(device, ContextProgram, S_V, S_F, args).unwrap();
 
     let view_proj_mat_init =
         generate_projection_matrix(size.width as f32 / size.height as f32) * generate_view_matrix();
@@ -352,8 +366,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     let light_proj_mat =
                         BufferData::new(generate_light_projection(light_pos[0], 60.0));
 
-                    let bind_group_light_proj_pos =
-                        BindGroup2::new(&device, &light_proj_mat, &BufferData::new(light_pos.clone()));
+                    let bind_group_light_proj_pos = BindGroup2::new(
+                        &device,
+                        &light_proj_mat,
+                        &BufferData::new(light_pos.clone()),
+                    );
 
                     //dbg!(&light_pos);
 
@@ -368,25 +385,19 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             base_array_layer: 0, // The first light is at index 0
                             array_layer_count: std::num::NonZeroU32::new(1),
                         });
-                        let mut rpass_stencil = setup_render_pass(
-                            &stencil_program,
+                        // The shadow is only drawn to the depth attachment
+                        let mut rpass_stencil = stencil_program.render_pass(
                             &mut init_encoder,
-                            wgpu::RenderPassDescriptor {
-                                label: None,
-                                // color_attachments is literally where we draw the colors to
-                                color_attachments: &[],
-                                depth_stencil_attachment: Some(
-                                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                                        attachment: &shadow_view,
-                                        depth_ops: Some(wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(1.0),
-                                            store: true,
-                                        }),
-                                        stencil_ops: None,
-                                    },
-                                ),
-                            },
+                            Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &shadow_view,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(1.0),
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            }),
                         );
+                        let bake_context = stencil_program.begin(&mut rpass_stencil);
                         {
                             let bake_context1 = (&bake_context)
                                 .set_a_position(&mut rpass_stencil, &plane_positions);
@@ -396,9 +407,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                     &bind_group_plane_world_mat,
                                 );
                                 {
-                                    bake_context_plane.runnable(|| {
-                                        graphics_run_indices(&mut rpass_stencil, &plane_index, 1)
-                                    });
+                                    bake_context_plane
+                                        .draw_indexed(&mut rpass_stencil, &plane_index, 1)
+                                        .unwrap();
                                 }
                             }
                         }
@@ -413,9 +424,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                 );
 
                                 {
-                                    bake_context_sphere.runnable(|| {
-                                        graphics_run_indices(&mut rpass_stencil, &index, 1)
-                                    });
+                                    bake_context_sphere
+                                        .draw_indexed(&mut rpass_stencil, &index, 1)
+                                        .unwrap();
                                 }
                             }
                         }
@@ -424,40 +435,34 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     {
                         let depth_view =
                             depth_texture.get_view_0(&wgpu::TextureViewDescriptor::default());
-                        let mut rpass = setup_render_pass(
-                            &program,
+                        let mut rpass = program.render_pass(
                             &mut init_encoder,
-                            wgpu::RenderPassDescriptor {
-                                label: None,
-                                // color_attachments is literally where we draw the colors to
-                                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                    attachment: &frame.view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                                            r: 0.1,
-                                            g: 0.2,
-                                            b: 0.3,
-                                            a: 1.0,
-                                        }),
-                                        store: true,
-                                    },
-                                }],
-                                depth_stencil_attachment: Some(
-                                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                                        attachment: &depth_view,
-                                        depth_ops: Some(wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(1.0),
-                                            store: true,
-                                        }),
-                                        stencil_ops: None,
-                                    },
-                                ),
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &frame.view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                                        r: 0.1,
+                                        g: 0.2,
+                                        b: 0.3,
+                                        a: 1.0,
+                                    }),
+                                    store: true,
+                                },
                             },
+                            Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_view,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(1.0),
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            }),
                         );
 
                         {
-                            let context1 = (&context)
+                            let context1 = program
+                                .begin(&mut rpass)
                                 .set_u_viewProj_u_World(&mut rpass, &bind_group_view_world);
 
                             {
@@ -487,13 +492,13 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                                         );
 
                                                     {
-                                                        context9.runnable(|| {
-                                                            graphics_run_indices(
+                                                        context9
+                                                            .draw_indexed(
                                                                 &mut rpass,
                                                                 &plane_index,
                                                                 1,
                                                             )
-                                                        });
+                                                            .unwrap();
                                                     }
                                                 }
                                             }
@@ -516,9 +521,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                                         );
 
                                                     {
-                                                        context9.runnable(|| {
-                                                            graphics_run_indices(&mut rpass, &index, 1)
-                                                        });
+                                                        context9
+                                                            .draw_indexed(&mut rpass, &index, 1)
+                                                            .unwrap();
                                                     }
                                                 }
                                             }
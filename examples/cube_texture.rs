@@ -13,8 +13,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 pub use wgpu_macros::generic_bindings;
@@ -54,6 +53,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! {vertex = {
         [[vertex in] vec3] a_Pos;
         [[vertex in] vec2] a_TexCoord;
@@ -88,10 +89,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let queue = Rc::new(queue);
 
@@ -186,24 +193,22 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .output;
 
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
-                    let context1 = (&context).set_a_Pos(&mut rpass, &vertex_position);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_a_Pos(&mut rpass, &vertex_position);
 
                     {
                         let context2 = context1.set_a_TexCoord(&mut rpass, &vertex_tex_coords);
@@ -216,8 +221,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                     context3.set_t_Color_s_Color(&mut rpass, &bind_group_t_s_map);
 
                                 {
-                                    let _ = context4
-                                        .runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                                    context4.draw_indexed(&mut rpass, &indices, 1).unwrap();
                                 }
                             }
                         }
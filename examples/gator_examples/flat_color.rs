@@ -5,6 +5,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -12,8 +13,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 use crate::pipeline::AbstractBind;
@@ -52,6 +52,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! { vertex = {
         [[vertex in] vec3] a_position;
         [group1 [uniform in] mat4] u_view;
@@ -78,10 +80,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     const S_V: GraphicsShader = eager_graphics_shader! {vertex!()};
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let (position_data, _, index_data) = load_model("src/models/teapot.obj");
 
@@ -120,24 +128,22 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
                 {
                     {
-                        let mut rpass = setup_render_pass(
-                            &program,
+                        let mut rpass = program.render_pass(
                             &mut init_encoder,
-                            wgpu::RenderPassDescriptor {
-                                label: None,
-                                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                    attachment: &frame.view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                        store: true,
-                                    },
-                                }],
-                                depth_stencil_attachment: None,
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &frame.view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                    store: true,
+                                },
                             },
+                            None,
                         );
 
-                        let context1 = (&context).set_a_position(&mut rpass, &positions);
+                        let context1 = program
+                            .begin(&mut rpass)
+                            .set_a_position(&mut rpass, &positions);
 
                         {
                             let context2 =
@@ -147,8 +153,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                 let context3 =
                                     (&context2).set_u_model(&mut rpass, &bind_group_model);
                                 {
-                                    context3
-                                        .runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                                    context3.draw_indexed(&mut rpass, &indices, 1).unwrap();
                                 }
                             }
                         }
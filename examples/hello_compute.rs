@@ -5,7 +5,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
-pub use pipeline::wgpu_compute_header::{compile, compute_run, ComputeShader};
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
 
 pub use pipeline::bind::{BindGroup1, BufferData, Indices, Vertex};
 pub use pipeline::AbstractBind;
@@ -49,6 +49,7 @@ async fn execute_gpu() {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
     let queue = Rc::new(queue);
 
     my_shader! {trivial = {
@@ -78,24 +79,26 @@ async fn execute_gpu() {
     }}
 
     const S: ComputeShader = eager_compute_shader! {trivial!()};
-    eager_binding! {context = trivial!()};
+    eager_binding! {Context = trivial!()};
 
-    let program = compile(&S, &device, context.get_layout(&device)).await;
+    let program = ContextProgram::compile(&S, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
 
     let indices = BufferData::new(vec![1, 2, 3, 4]);
 
     let bg_i = BindGroup1::new(&device, &indices);
 
     {
+        let size = DispatchSize::new(&device, [4, 1, 1]);
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
-            let mut cpass =
-                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&program.pipeline);
-            let context1 = context.set_indices(&mut cpass, &bg_i);
+            let mut cpass = program.compute_pass(&mut encoder);
+            let context1 = program.begin(&mut cpass).set_indices(&mut cpass, &bg_i);
             {
-                context1.runnable(|| compute_run(&mut cpass, 4));
+                context1.dispatch(&mut cpass, &size);
             }
         }
 
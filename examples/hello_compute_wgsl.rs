@@ -0,0 +1,133 @@
+#![recursion_limit = "1024"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
+
+pub use pipeline::bind::{BindGroup1, BufferData, Indices, Vertex};
+pub use pipeline::shared::ShaderBackend;
+pub use pipeline::AbstractBind;
+
+use std::convert::TryInto;
+use std::rc::Rc;
+
+async fn execute_gpu() {
+    // qualifiers
+    // buffer: is a buffer?
+    // in: this parameter must be bound to before the program runs
+    //     thus it can be read inside of the program scope
+    // out: if this parameter is bound, it must be rebound after each iteration
+    //      Only out variables can be mutated
+    //      Only out variables can be read as a result of the program
+    //      If out has been unassigned then an error is raised when it is read
+    // loop: one or more of these loop annotations are required per program. Atm, the values bound is assumed to be of equal length and this gives the number of iterations(gl_GlobalInvocationID.x)
+    //      the size of any out buffers that need to be created
+
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            // Request an adapter which can render to our surface
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropiate adapter");
+
+    // The device manages the connection and resources of the adapter
+    // The queue is a literal queue of tasks for the gpu
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device");
+
+    let device = Rc::new(device);
+    let queue = Rc::new(queue);
+
+    // With the WGSL backend each parameter is wrapped in a struct so it is accessed through `.data`
+    my_shader! {trivial = {
+        [group1 [buffer loop in out] uint[]] indices;
+        {{
+            fn collatz_iterations(n_base: u32) -> u32 {
+                var n: u32 = n_base;
+                var i: u32 = 0u;
+                loop {
+                    if (n <= 1u) {
+                        break;
+                    }
+                    if (n % 2u == 0u) {
+                        n = n / 2u;
+                    } else {
+                        n = 3u * n + 1u;
+                    }
+                    i = i + 1u;
+                }
+                return i;
+            }
+
+            fn main() {
+                const index: u32 = gl_GlobalInvocationID.x;
+                indices.data[index] = collatz_iterations(indices.data[index]);
+            }
+        }}
+    }}
+
+    const S: ComputeShader = eager_compute_shader! {trivial!()};
+    eager_binding! {Context = trivial!()};
+
+    let program = ContextProgram::compile(
+        &S,
+        &device,
+        ComputeCompileArgs {
+            backend: ShaderBackend::Wgsl,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let indices = BufferData::new(vec![1, 2, 3, 4]);
+
+    let bg_i = BindGroup1::new(&device, &indices);
+
+    {
+        let size = DispatchSize::new(&device, [4, 1, 1]);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut cpass = program.compute_pass(&mut encoder);
+            let context1 = program.begin(&mut cpass).set_indices(&mut cpass, &bg_i);
+            {
+                context1.dispatch(&mut cpass, &size);
+            }
+        }
+
+        let x = bg_i.setup_read_0(&device, &mut encoder, 0..16);
+
+        queue.submit(Some(encoder.finish()));
+
+        println!(
+            "{:?}",
+            x.read(&device)
+                .await
+                .unwrap()
+                .chunks_exact(4)
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .collect::<Vec<u32>>()
+        );
+    }
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
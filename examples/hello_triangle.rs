@@ -12,7 +12,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run, setup_render_pass, GraphicsCompileArgs, GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 use crate::pipeline::AbstractBind;
@@ -76,10 +76,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let positions = vec![[0.0, 0.7, 0.0], [-0.5, 0.5, 0.0], [0.5, -0.5, 0.0]];
     let brightness = vec![0.5, 0.5, 0.9];
@@ -104,28 +110,27 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .output;
 
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    // One attachment for each out of the fragment shader
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
-                    let context1 = (&context).set_a_position(&mut rpass, &vertex_position);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_a_position(&mut rpass, &vertex_position);
                     {
                         let context2 = context1.set_in_brightness(&mut rpass, &vertex_brightness);
                         {
-                            context2.runnable(|| graphics_run(&mut rpass, 3, 1));
+                            context2.draw(&mut rpass, 3, 1).unwrap();
                         }
                     }
                 }
@@ -0,0 +1,106 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
+
+pub use pipeline::bind::{BindGroup1, StorageTextureData};
+pub use pipeline::AbstractBind;
+
+use std::rc::Rc;
+
+const SIZE: u32 = 16;
+
+async fn execute_gpu() {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropiate adapter");
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device");
+
+    let device = Rc::new(device);
+    let queue = Rc::new(queue);
+
+    // Images are only read when they are `in`, only written when they are `out`
+    // and the format of the texels is given like any other qualifier
+    my_shader! {invert = {
+        workgroup_size(8, 8);
+        [group1 [in rgba8] image2D] src;
+        [group2 [out rgba8] image2D] dst;
+        [[] uvec3] gl_GlobalInvocationID;
+        {{
+            void main() {
+                ivec2 p = ivec2(gl_GlobalInvocationID.xy);
+                vec4 color = imageLoad(src, p);
+                imageStore(dst, p, vec4(1.0 - color.rgb, color.a));
+            }
+        }}
+    }}
+
+    const S: ComputeShader = eager_compute_shader! {invert!()};
+    eager_binding! {Context = invert!()};
+
+    let program = ContextProgram::compile(&S, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
+
+    let size = wgpu::Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth: 1,
+    };
+    let gradient = (0..SIZE * SIZE)
+        .map(|i| [(i % SIZE * 16) as u8, (i / SIZE * 16) as u8, 0, 255])
+        .collect();
+    let src = BindGroup1::new(
+        &device,
+        &StorageTextureData::new(gradient, size, queue.clone()),
+    );
+    let dst = BindGroup1::new(
+        &device,
+        &StorageTextureData::new_without_data(size, queue.clone()),
+    );
+
+    let size = DispatchSize::new(&device, [SIZE, SIZE, 1]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut cpass = program.compute_pass(&mut encoder);
+        let context1 = program.begin(&mut cpass).set_src(&mut cpass, &src);
+        let context2 = context1.set_dst(&mut cpass, &dst);
+        context2.dispatch(&mut cpass, &size);
+    }
+
+    // The texels are read back as the `[u8; 4]` of an `rgba8` image
+    let inverted = dst.setup_read_0(&device, &mut encoder);
+
+    queue.submit(Some(encoder.finish()));
+
+    println!(
+        "{:?}",
+        &inverted.read(&device).await.unwrap()[..SIZE as usize]
+    );
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
@@ -6,6 +6,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -13,11 +14,10 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
-pub use pipeline::bind::{BindGroup1, Indices, BufferData, SamplerData, TextureData, Vertex};
+pub use pipeline::bind::{BindGroup1, BufferData, Indices, SamplerData, TextureData, Vertex};
 pub use pipeline::AbstractBind;
 
 pub use pipeline::helper::{
@@ -47,21 +47,27 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                features: wgpu::Features::empty(),
-                limits: wgpu::Limits::default(),
+                // The model matrix is a push constant
+                features: wgpu::Features::PUSH_CONSTANTS,
+                limits: wgpu::Limits {
+                    max_push_constant_size: 64,
+                    ..Default::default()
+                },
             },
             None,
         )
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! {vertex = {
         [[vertex in] vec3] a_position;
         [[vertex in] vec3] vertexColor;
 
         [group1 [uniform in] mat4] u_view;
         [group2 [uniform in] mat4] u_proj;
-        [group3 [uniform in] mat4] u_model;
+        [[push in] mat4] u_model;
 
         [[out] vec3] fragmentColor;
         [[out] vec4] gl_Position;
@@ -88,10 +94,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let (positions, _, index_data) = load_cube();
 
@@ -135,10 +147,12 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     ]);
     let view_mat = BufferData::new(generate_view_matrix());
 
-    let proj_mat = BufferData::new(generate_projection_matrix(size.width as f32 / size.height as f32));
+    let proj_mat = BufferData::new(generate_projection_matrix(
+        size.width as f32 / size.height as f32,
+    ));
 
     let imat = generate_identity_matrix();
-    let model_mat = BufferData::new(imat); 
+    let model_mat = BufferData::new(imat);
     let model_mat2 = BufferData::new(translate(imat, 2.0, 0.0, 0.0));
 
     let vertex_position = Vertex::new(&device, &BufferData::new(positions));
@@ -148,8 +162,6 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     let bind_view_mat = BindGroup1::new(&device, &view_mat);
     let bind_proj_mat = BindGroup1::new(&device, &proj_mat);
     // let texture = BindGroup1::new(&device, &(data, descriptor, queue));
-    let bind_model_mat = BindGroup1::new(&device, &model_mat);
-    let bind_model_mat2 = BindGroup1::new(&device, &model_mat2);
 
     // A "chain" of buffers that we render on to the display
     let swap_chain = generate_swap_chain(&surface, &window, &device);
@@ -167,28 +179,26 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .expect("Timeout when acquiring next swap chain texture")
                     .output;
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
                     //
                     // Handle the setup for the pipeline
                     //
 
-                    let context1 = (&context).set_a_position(&mut rpass, &vertex_position);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_a_position(&mut rpass, &vertex_position);
                     {
                         let context2 = (&context1).set_vertexColor(&mut rpass, &vertex_color);
                         {
@@ -196,17 +206,14 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             {
                                 let context4 = (&context3).set_u_proj(&mut rpass, &bind_proj_mat);
                                 {
-                                    let context5 =
-                                        (&context4).set_u_model(&mut rpass, &bind_model_mat);
+                                    let context5 = (&context4).set_u_model(&mut rpass, &model_mat);
                                     {
-                                        let _ = context5
-                                            .runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                                        context5.draw_indexed(&mut rpass, &indices, 1).unwrap();
                                     }
                                     let context5_1 =
-                                        (&context4).set_u_model(&mut rpass, &bind_model_mat2);
+                                        (&context4).set_u_model(&mut rpass, &model_mat2);
                                     {
-                                        let _ = context5_1
-                                            .runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                                        context5_1.draw_indexed(&mut rpass, &indices, 1).unwrap();
                                     }
                                 }
                             }
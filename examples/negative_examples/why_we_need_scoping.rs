@@ -6,6 +6,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -13,8 +14,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 use crate::pipeline::AbstractBind;
@@ -52,6 +52,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! {vertex = {
         [[vertex in] vec3] a_position;
         [[vertex in] vec3] vertexColor;
@@ -84,10 +86,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let (positions, _, index_data) = load_cube();
 
@@ -170,7 +178,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let view_mat = BufferData::new(generate_view_matrix());
 
-    let proj_mat = BufferData::new(generate_projection_matrix(size.width as f32 / size.height as f32));
+    let proj_mat = BufferData::new(generate_projection_matrix(
+        size.width as f32 / size.height as f32,
+    ));
 
     let vertex_position = Vertex::new(&device, &BufferData::new(positions));
     let vertex_color = Vertex::new(&device, &color_data);
@@ -195,28 +205,26 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .expect("Timeout when acquiring next swap chain texture")
                     .output;
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
                     //
                     // Handle the setup for the pipeline
                     //
 
-                    let context1 = (&context).set_a_position(&mut rpass, &vertex_position);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_a_position(&mut rpass, &vertex_position);
 
                     let context2 = (&context1).set_vertexColor(&mut rpass, &vertex_color);
 
@@ -225,7 +233,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     // Without scoping for each context, that uses the previous context, I've inserted a call here to change the vertexColor. This should only be visible in the bad_context, as that is where it is bound, but the effect is shown when you try to run context3. Remember, at no point in the chain of context's to create context3 did we bind this bad_vertex_color and we still see the effect.
                     let bad_context = context1.set_vertexColor(&mut rpass, &bad_vertex_color);
 
-                    let _ = context3.runnable(|| graphics_run_indices(&mut rpass, &indices, 1));
+                    context3.draw_indexed(&mut rpass, &indices, 1).unwrap();
                 }
                 queue.submit(Some(init_encoder.finish()));
             }
@@ -0,0 +1,100 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
+
+pub use pipeline::bind::{BindGroup1, BufferData, Indices, Vertex};
+pub use pipeline::AbstractBind;
+
+use std::rc::Rc;
+use std::convert::TryInto;
+
+async fn execute_gpu() {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            // Request an adapter which can render to our surface
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropiate adapter");
+
+    // The device manages the connection and resources of the adapter
+    // The queue is a literal queue of tasks for the gpu
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device");
+
+    let device = Rc::new(device);
+
+    // Only out parameters can be written to, and that includes the memory an atomic function changes
+    my_shader! {compute = {
+        [group1 [buffer loop in] uint[]] indices;
+        [group2 [buffer out] uint[]] result;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                // atomicAdd writes to indices, which is only in
+                result[index] = atomicAdd(indices[index], 1);
+            }
+        }}
+    }}
+
+    const S: ComputeShader = eager_compute_shader! {compute!()};
+
+    eager_binding! {Context = compute!()};
+
+    let program = ContextProgram::compile(&S, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
+
+    let indices_data = BufferData::new(vec![1, 2, 3, 4]);
+    let indices = BindGroup1::new(&device, &indices_data);
+
+    let result_data = BufferData::new(vec![0, 0, 0, 0]);
+    let result = BindGroup1::new(&device, &result_data);
+
+    {
+        let size = DispatchSize::new(&device, [4, 1, 1]);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut cpass = program.compute_pass(&mut encoder);
+            let context1 = program.begin(&mut cpass).set_indices(&mut cpass, &indices);
+            let context2 = context1.set_result(&mut cpass, &result);
+            context2.dispatch(&mut cpass, &size);
+        }
+
+        let x = result.setup_read_0(&device, &mut encoder, 0..16);
+
+        queue.submit(Some(encoder.finish()));
+
+        println!(
+            "{:?}",
+            x.read(&device)
+                .await
+                .unwrap()
+                .chunks_exact(4)
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .collect::<Vec<u32>>()
+        );
+    }
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
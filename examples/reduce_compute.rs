@@ -0,0 +1,135 @@
+#![recursion_limit = "1024"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
+
+pub use pipeline::bind::{BindGroup1, BufferData};
+pub use pipeline::AbstractBind;
+
+use std::convert::TryInto;
+use std::rc::Rc;
+
+const TILE: u32 = 64;
+const NUM_TILES: u32 = 4;
+
+// Each workgroup sums its tile of the values in shared memory
+my_shader! {reduce = {
+    workgroup_size(TILE);
+    [[const] uint] TILE;
+    [group1 [buffer in] float[]] values;
+    [group2 [buffer in out] float[]] sums;
+    [[shared] float[TILE]] partial;
+    [[] uvec3] gl_GlobalInvocationID;
+    [[] uvec3] gl_LocalInvocationID;
+    [[] uvec3] gl_WorkGroupID;
+    {{
+        void main() {
+            uint local = gl_LocalInvocationID.x;
+            // The invocations past the values still have to reach the barriers
+            partial[local] = gl_GlobalInvocationID.x < dispatch_size.x ? values[gl_GlobalInvocationID.x] : 0.0;
+            barrier();
+            for (uint stride = TILE / 2; stride > 0; stride /= 2) {
+                if (local < stride) {
+                    partial[local] += partial[local + stride];
+                }
+                barrier();
+            }
+            if (local == 0) {
+                sums[gl_WorkGroupID.x] = partial[0];
+            }
+        }
+    }}
+}}
+
+const S: ComputeShader = eager_compute_shader! {reduce!()};
+binding_context! {pub ReduceContext = reduce!()}
+
+// The program is typed by the Context it was compiled for, which binding starts from
+async fn compile_reduce(device: &wgpu::Device) -> ReduceContextProgram {
+    ReduceContextProgram::compile(
+        &S,
+        device,
+        ComputeCompileArgs {
+            constants: ReduceContextProgram::constants(TILE),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap()
+}
+
+async fn execute_gpu() {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropiate adapter");
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device");
+
+    let device = Rc::new(device);
+    let queue = Rc::new(queue);
+
+    let program = compile_reduce(&device).await;
+
+    let values = BindGroup1::new(
+        &device,
+        &BufferData::new(
+            (0..TILE * NUM_TILES)
+                .map(|i| i as f32)
+                .collect::<Vec<f32>>(),
+        ),
+    );
+    let sums = BindGroup1::new(&device, &BufferData::new(vec![0.0; NUM_TILES as usize]));
+
+    let size = DispatchSize::new(&device, [TILE * NUM_TILES, 1, 1]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut cpass = program.compute_pass(&mut encoder);
+        let context1 = program.begin(&mut cpass).set_values(&mut cpass, &values);
+        let context2 = context1.set_sums(&mut cpass, &sums);
+        context2.dispatch(&mut cpass, &size);
+    }
+
+    let result = sums.setup_read_0(
+        &device,
+        &mut encoder,
+        0..std::mem::size_of::<f32>() as u64 * NUM_TILES as u64,
+    );
+
+    queue.submit(Some(encoder.finish()));
+
+    println!(
+        "{:?}",
+        result
+            .read(&device)
+            .await
+            .unwrap()
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+            .collect::<Vec<f32>>()
+    );
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
@@ -6,6 +6,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -13,11 +14,12 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
-pub use pipeline::bind::{BindGroup1, BindGroup2, Indices, SamplerData, TextureData, Vertex, BufferData};
+pub use pipeline::bind::{
+    BindGroup1, BindGroup2, BufferData, Indices, SamplerData, TextureData, Vertex,
+};
 pub use pipeline::AbstractBind;
 
 pub use pipeline::helper::{
@@ -56,6 +58,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     let queue = std::rc::Rc::new(queue);
 
     my_shader! {VERTEX = {
@@ -96,10 +100,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_V: GraphicsShader = eager_graphics_shader! {VERTEX!()};
     const S_F: GraphicsShader = eager_graphics_shader! {FRAGMENT!()};
-    eager_binding! {context = VERTEX!(), FRAGMENT!()};
+    eager_binding! {Context = VERTEX!(), FRAGMENT!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     my_shader! { VERTEX_CUBE = {
         [[vertex in] vec3] a_Pos;
@@ -132,15 +142,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_V_CUBE: GraphicsShader = eager_graphics_shader! {VERTEX_CUBE!()};
     const S_F_CUBE: GraphicsShader = eager_graphics_shader! {FRAGMENT_CUBE!()};
-    eager_binding! {context_cube = VERTEX_CUBE!(), FRAGMENT_CUBE!()};
+    eager_binding! {ContextCube = VERTEX_CUBE!(), FRAGMENT_CUBE!()};
 
     let (program_CUBE, _) = compile_valid_graphics_program!(
         device,
-        context_cube,
+        ContextCubeProgram,
         S_V_CUBE,
         S_F_CUBE,
         GraphicsCompileArgs::default()
-    );
+    )
+    .unwrap();
 
     let (positions_data, normals_data, indices_data) = load_model("src/models/teapot.obj");
     let positions = Vertex::new(&device, &BufferData::new(positions_data));
@@ -194,7 +205,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let proj_mat = generate_projection_matrix(size.width as f32 / size.height as f32);
 
-    let bg_view_proj = BindGroup2::new(&device, &BufferData::new(view_mat), &BufferData::new(proj_mat));
+    let bg_view_proj = BindGroup2::new(
+        &device,
+        &BufferData::new(view_mat),
+        &BufferData::new(proj_mat),
+    );
 
     let mut model_mat_data = generate_identity_matrix();
 
@@ -266,24 +281,22 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 let light_dir = BindGroup1::new(&device, &BufferData::new(light_direction.clone()));
 
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
                     {
-                        let context1 = (&context).set_u_view_u_proj(&mut rpass, &bg_view_proj);
+                        let context1 = program
+                            .begin(&mut rpass)
+                            .set_u_view_u_proj(&mut rpass, &bg_view_proj);
 
                         {
                             let context2 = context1.set_Ambient(&mut rpass, &light_ambient);
@@ -297,9 +310,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             let context6 =
                                                 context5.set_a_position(&mut rpass, &positions);
                                             {
-                                                context6.runnable(|| {
-                                                    graphics_run_indices(&mut rpass, &indices, 1)
-                                                });
+                                                context6
+                                                    .draw_indexed(&mut rpass, &indices, 1)
+                                                    .unwrap();
                                             }
                                         }
                                     }
@@ -313,9 +326,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             let context6 =
                                                 context5.set_a_position(&mut rpass, &positions2);
                                             {
-                                                context6.runnable(|| {
-                                                    graphics_run_indices(&mut rpass, &indices2, 1)
-                                                });
+                                                context6
+                                                    .draw_indexed(&mut rpass, &indices2, 1)
+                                                    .unwrap();
                                             }
                                         }
                                     }
@@ -324,25 +337,11 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                         }
                     }
 
-                    rpass.set_pipeline(&program_CUBE.pipeline);
-                    /* let mut rpass = setup_render_pass(
-                        &program_CUBE,
-                        &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
-                        },
-                    ); */
-
-                    let context2_cube = (&context_cube).set_a_Pos(&mut rpass, &positions_cube);
+                    // The cube is drawn in the same pass, with its own pipeline
+                    let mut rpass = program_CUBE.continue_pass(rpass);
+                    let context2_cube = program_CUBE
+                        .begin(&mut rpass)
+                        .set_a_Pos(&mut rpass, &positions_cube);
 
                     {
                         let context3_cube = context2_cube.set_u_Transform(&mut rpass, &trans_mat);
@@ -355,7 +354,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
                                 {
                                     context5_cube
-                                        .runnable(|| graphics_run_indices(&mut rpass, &index_cube, 1));
+                                        .draw_indexed(&mut rpass, &index_cube, 1)
+                                        .unwrap();
                                 }
                             }
                         }
@@ -13,7 +13,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run, setup_render_pass, GraphicsCompileArgs, GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 use crate::pipeline::AbstractBind;
@@ -49,12 +49,14 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! { vertex = {
         [group1 [uniform in] mat4] view;
         [group1 [uniform in] mat4] proj;
         [[out] vec3] v_Uv;
         [[out] vec4] gl_Position;
-        [[] int] gl_VertexID;
+        [[] int] gl_VertexIndex;
         {{
             void main() {
                 vec4 pos = vec4(0.0);
@@ -87,10 +89,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     const S_V: GraphicsShader = eager_graphics_shader! {vertex!()};
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let proj_mat = BufferData::new(generate_projection_matrix(
         size.width as f32 / size.height as f32,
@@ -212,29 +220,26 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                 let mut init_encoder =
                     device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
-                    let context1 =
-                        (&context).set_t_Cubemap_s_Cubemap(&mut rpass, &bind_group_t_s_cubemap);
+                    let context1 = program
+                        .begin(&mut rpass)
+                        .set_t_Cubemap_s_Cubemap(&mut rpass, &bind_group_t_s_cubemap);
                     {
                         let context2 = (&context1).set_view_proj(&mut rpass, &bind_group_view_proj);
                         {
-                            context2.runnable(|| graphics_run(&mut rpass, 3, 1));
+                            context2.draw(&mut rpass, 3, 1).unwrap();
                         }
                     }
                 }
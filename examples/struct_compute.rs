@@ -0,0 +1,120 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
+
+pub use pipeline::bind::{BindGroup1, BufferData, WgpuStruct};
+pub use pipeline::AbstractBind;
+
+use std::convert::TryInto;
+use std::rc::Rc;
+
+// The matching GLSL struct is declared for the shader so the body can use `Particle` and its fields
+#[derive(WgpuStruct)]
+pub struct Particle {
+    pos: [f32; 2],
+    vel: [f32; 2],
+}
+
+async fn execute_gpu() {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            // Request an adapter which can render to our surface
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropiate adapter");
+
+    // The device manages the connection and resources of the adapter
+    // The queue is a literal queue of tasks for the gpu
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device");
+
+    let device = Rc::new(device);
+
+    my_shader! {particles = {
+        [group1 [buffer loop in out] Particle[]] particles;
+        [group2 [uniform in] float] deltaT;
+        {{
+            void main() {
+                uint index = gl_GlobalInvocationID.x;
+                Particle p = particles[index];
+                particles[index].pos = p.pos + p.vel * deltaT;
+            }
+        }}
+    }}
+
+    const S: ComputeShader = eager_compute_shader! {particles!()};
+    eager_binding! {Context = particles!()};
+
+    let program = ContextProgram::compile(&S, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
+
+    let particles = BufferData::new(vec![
+        Particle {
+            pos: [0.0, 0.0],
+            vel: [1.0, 0.0],
+        },
+        Particle {
+            pos: [0.5, 0.5],
+            vel: [0.0, -1.0],
+        },
+    ]);
+    let bg_particles = BindGroup1::new(&device, &particles);
+    let bg_delta = BindGroup1::new(&device, &BufferData::new(0.5));
+
+    {
+        let size = DispatchSize::new(&device, [2, 1, 1]);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut cpass = program.compute_pass(&mut encoder);
+            let context1 = program
+                .begin(&mut cpass)
+                .set_particles(&mut cpass, &bg_particles);
+            let context2 = context1.set_deltaT(&mut cpass, &bg_delta);
+            {
+                context2.dispatch(&mut cpass, &size);
+            }
+        }
+
+        // Each particle is two vec2's so there is no padding between them
+        let x = bg_particles.setup_read_0(&device, &mut encoder, 0..32);
+
+        queue.submit(Some(encoder.finish()));
+
+        println!(
+            "{:?}",
+            x.read(&device)
+                .await
+                .unwrap()
+                .chunks_exact(4)
+                .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+                .collect::<Vec<f32>>()
+                .chunks_exact(4)
+                .map(|p| ([p[0], p[1]], [p[2], p[3]]))
+                .collect::<Vec<([f32; 2], [f32; 2])>>()
+        );
+    }
+}
+
+fn main() {
+    futures::executor::block_on(execute_gpu());
+}
@@ -5,6 +5,7 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
+use std::rc::Rc;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -12,8 +13,7 @@ use winit::{
 };
 
 pub use pipeline::wgpu_graphics_header::{
-    generate_swap_chain, graphics_run_indices, setup_render_pass, GraphicsCompileArgs,
-    GraphicsShader,
+    generate_swap_chain, GraphicsCompileArgs, GraphicsShader,
 };
 
 use crate::pipeline::AbstractBind;
@@ -52,6 +52,8 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     my_shader! {vertex = {
         [[vertex in] vec3] a_position;
         [[vertex in] vec3] a_normal;
@@ -60,7 +62,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         [group3 [uniform in] mat4] u_view;
         [group3 [uniform in] mat4] u_proj;
         [group4 [uniform in] mat4] u_model;
-        [[] int] gl_VertexID;
+        [[] int] gl_VertexIndex;
 
         [[out] vec3] fragmentNormal;
         [[out] vec4] gl_Position;
@@ -93,10 +95,16 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     const S_F: GraphicsShader = eager_graphics_shader! {fragment!()};
 
-    eager_binding! {context = vertex!(), fragment!()};
+    eager_binding! {Context = vertex!(), fragment!()};
 
-    let (program, _) =
-        compile_valid_graphics_program!(device, context, S_V, S_F, GraphicsCompileArgs::default());
+    let (program, _) = compile_valid_graphics_program!(
+        device,
+        ContextProgram,
+        S_V,
+        S_F,
+        GraphicsCompileArgs::default()
+    )
+    .unwrap();
 
     let (positions, normals, index_data) = load_model("src/models/teapot.obj");
 
@@ -106,7 +114,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let view_mat = BufferData::new(generate_view_matrix());
 
-    let proj_mat = BufferData::new(generate_projection_matrix(size.width as f32 / size.height as f32));
+    let proj_mat = BufferData::new(generate_projection_matrix(
+        size.width as f32 / size.height as f32,
+    ));
 
     let mut model_mat_init = generate_identity_matrix();
     model_mat_init = scale(model_mat_init, 0.7);
@@ -135,30 +145,29 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .output;
 
                 light_direction = rotate_vec3(&light_direction, 0.05);
-                let bind_group_light_dir = BindGroup1::new(&device, &BufferData::new(light_direction.clone()));
+                let bind_group_light_dir =
+                    BindGroup1::new(&device, &BufferData::new(light_direction.clone()));
 
                 model_mat_init = rotation_y(model_mat_init, 0.05);
                 let bind_group_model = BindGroup1::new(&device, &BufferData::new(model_mat_init));
                 {
-                    let mut rpass = setup_render_pass(
-                        &program,
+                    let mut rpass = program.render_pass(
                         &mut init_encoder,
-                        wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                                attachment: &frame.view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                                    store: true,
-                                },
-                            }],
-                            depth_stencil_attachment: None,
+                        wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &frame.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: true,
+                            },
                         },
+                        None,
                     );
 
                     {
-                        let context1 = (&context).set_a_position(&mut rpass, &vertex_position);
+                        let context1 = program
+                            .begin(&mut rpass)
+                            .set_a_position(&mut rpass, &vertex_position);
                         {
                             let context2 = (&context1).set_a_normal(&mut rpass, &vertex_normal);
                             {
@@ -174,9 +183,9 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                                             let context6 = (&context5)
                                                 .set_u_model(&mut rpass, &bind_group_model);
                                             {
-                                                context6.runnable(|| {
-                                                    graphics_run_indices(&mut rpass, &indices, 1)
-                                                });
+                                                context6
+                                                    .draw_indexed(&mut rpass, &indices, 1)
+                                                    .unwrap();
                                             }
                                         }
                                     }
@@ -5,12 +5,13 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
-pub use pipeline::wgpu_compute_header::{compile, compute_run, ComputeShader};
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
 
 pub use pipeline::bind::{BindGroup1, BufferData, Indices, Vertex};
 pub use pipeline::AbstractBind;
 
 use std::convert::TryInto;
+use std::rc::Rc;
 
 async fn execute_gpu() {
     let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
@@ -37,6 +38,8 @@ async fn execute_gpu() {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     // qualifiers
     // buffer: is a buffer?
     // in: this parameter must be bound to before the program runs
@@ -64,9 +67,11 @@ async fn execute_gpu() {
 
     const S: ComputeShader = eager_compute_shader! {compute!()};
 
-    eager_binding! {context = compute!()};
+    eager_binding! {Context = compute!()};
 
-    let program = compile(&S, &device, context.get_layout(&device)).await;
+    let program = ContextProgram::compile(&S, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
 
     let indices_1_data = BufferData::new(vec![1, 2, 3, 4]);
     let indices_1 = BindGroup1::new(&device, &indices_1_data);
@@ -78,25 +83,21 @@ async fn execute_gpu() {
     let indices_3 = BindGroup1::new(&device, &indices_3_data);
 
     {
+        let size = DispatchSize::new(&device, [4, 1, 1]);
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
-            let mut cpass =
-                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&program.pipeline);
-            let context1 = context.set_indices(&mut cpass, &indices_3);
-            {
-                let context2 = (&context1).set_indices2(&mut cpass, &indices_1);
-
-                context2.runnable(|| compute_run(&mut cpass, 4));
-            }
-            {
-                let context3 =
-                    context1.set_indices2(&mut cpass, &indices_2);
-                {
-                    context3.runnable(|| compute_run(&mut cpass, 4));
-                }
-            }
+            let mut cpass = program.compute_pass(&mut encoder);
+            let context1 = program
+                .begin(&mut cpass)
+                .set_indices(&mut cpass, &indices_3);
+            let context2 = context1.set_indices2(&mut cpass, &indices_1);
+            context2.dispatch(&mut cpass, &size);
+
+            // indices stays bound while indices2 is given the next buffer
+            let context3 = context2.rebind_indices2(&mut cpass, &indices_2);
+            context3.dispatch(&mut cpass, &size);
         }
 
         let x = indices_1.setup_read_0(&device, &mut encoder, 0..16);
@@ -5,12 +5,13 @@ extern crate pipeline;
 #[macro_use]
 extern crate eager;
 
-pub use pipeline::wgpu_compute_header::{compile, compute_run, ComputeShader};
+pub use pipeline::wgpu_compute_header::{ComputeCompileArgs, ComputeShader, DispatchSize};
 
 pub use pipeline::bind::{BindGroup1, BufferData, Indices, Vertex};
 pub use pipeline::AbstractBind;
 
 use std::convert::TryInto;
+use std::rc::Rc;
 
 async fn execute_gpu() {
     let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
@@ -37,6 +38,8 @@ async fn execute_gpu() {
         .await
         .expect("Failed to create device");
 
+    let device = Rc::new(device);
+
     // qualifiers
     // buffer: is a buffer?
     // in: this parameter must be bound to before the program runs
@@ -76,48 +79,44 @@ async fn execute_gpu() {
 
     const ADD_ONE: ComputeShader = eager_compute_shader! {One!()};
 
-    eager_binding! {context = One!()};
+    eager_binding! {Context = One!()};
 
     const ADD_TWO: ComputeShader = eager_compute_shader! {Two!()};
 
-    eager_binding! {next_context = Two!()};
+    eager_binding! {NextContext = Two!()};
 
-    let program1 = compile(&ADD_ONE, &device, context.get_layout(&device)).await;
+    let program1 = ContextProgram::compile(&ADD_ONE, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
 
-    let program2 = compile(&ADD_TWO, &device, context.get_layout(&device)).await;
+    let program2 = NextContextProgram::compile(&ADD_TWO, &device, ComputeCompileArgs::default())
+        .await
+        .unwrap();
 
     let indices = BindGroup1::new(&device, &BufferData::new(vec![1, 2, 3, 4]));
     let empty1 = BindGroup1::new(&device, &BufferData::new(vec![0, 0, 0, 0]));
     let empty2 = BindGroup1::new(&device, &BufferData::new(vec![0, 0, 0, 0]));
 
     {
+        let size = DispatchSize::new(&device, [4, 1, 1]);
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
-            let mut cpass =
-                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-            cpass.set_pipeline(&program1.pipeline);
-            {
-                let context1 = context.set_add_one_in(&mut cpass, &indices);
-                {
-                    let context2 = context1.set_add_two_in(&mut cpass, &empty1);
-                    {
-                        context2.runnable(|| compute_run(&mut cpass, 4));
-                    }
-                }
-            }
-            cpass.set_pipeline(&program2.pipeline);
-            {
-                let next_context1 = next_context.set_add_two_in(&mut cpass, &empty1);
-
-                {
-                    let next_context2 = next_context1.set_add_two_result(&mut cpass, &empty2);
-
-                    {
-                        next_context2.runnable(|| compute_run(&mut cpass, 4));
-                    }
-                }
-            }
+            let mut cpass = program1.compute_pass(&mut encoder);
+            let context1 = program1
+                .begin(&mut cpass)
+                .set_add_one_in(&mut cpass, &indices);
+            let context2 = context1.set_add_two_in(&mut cpass, &empty1);
+            context2.dispatch(&mut cpass, &size);
+
+            // The second program continues the pass,
+            // and the out of the first program is bound to the in of the same name
+            let mut cpass = program2.continue_pass(cpass);
+            let next_start = program2.begin(&mut cpass);
+            let next_context1 = context2.pipe_into(&mut cpass, next_start);
+            let next_context2 = next_context1.set_add_two_result(&mut cpass, &empty2);
+            next_context2.dispatch(&mut cpass, &size);
         }
         let x = empty1.setup_read_0(&device, &mut encoder, 0..16);
         let y = empty2.setup_read_0(&device, &mut encoder, 0..16);
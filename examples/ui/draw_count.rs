@@ -0,0 +1,58 @@
+#![recursion_limit = "1024"]
+#![deny(unused_must_use)]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::bind::{BufferData, Vertex};
+pub use pipeline::AbstractBind;
+
+my_shader! {vertex = {
+    [[vertex in] vec3] a_position;
+    [[out] vec4] gl_Position;
+    {{
+        void main() {
+            gl_Position = vec4(a_position, 1.0);
+        }
+    }}
+}}
+
+my_shader! {fragment = {
+    [[out] vec4] color;
+    {{
+        void main() {
+            color = vec4(1.0);
+        }
+    }}
+}}
+
+binding_context! {Context = vertex!(), fragment!()}
+
+// Drawing more vertices than the buffers have is an error that has to be handled
+fn draw(program: &ContextProgram, device: &wgpu::Device, view: &wgpu::TextureView) {
+    let positions = vec![[0.0, 0.7, 0.0], [-0.5, 0.5, 0.0], [0.5, -0.5, 0.0]];
+    let vertex_position = Vertex::new(device, &BufferData::new(positions));
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut rpass = program.render_pass(
+        &mut encoder,
+        wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: true,
+            },
+        },
+        None,
+    );
+    let context = program
+        .begin(&mut rpass)
+        .set_a_position(&mut rpass, &vertex_position);
+    context.draw(&mut rpass, 6, 1);
+}
+
+fn main() {}
@@ -0,0 +1,66 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::wgpu_compute_header::DispatchSize;
+
+pub use pipeline::bind::{BindGroup1, BufferData};
+pub use pipeline::AbstractBind;
+
+use std::rc::Rc;
+
+my_shader! {One = {
+    [group1 [buffer loop in] uint[]] add_one_in;
+    [group2 [buffer in out] uint[]] add_two_in;
+    {{
+        void main() {
+            uint index = gl_GlobalInvocationID.x;
+            add_two_in[index] = add_one_in[index] + 1;
+        }
+    }}
+}}
+
+my_shader! {Two = {
+    [group1 [buffer loop in] uint[]] add_two_in;
+    [group2 [buffer in out] uint[]] add_two_result;
+    {{
+        void main() {
+            uint index = gl_GlobalInvocationID.x;
+            add_two_result[index] = add_two_in[index] + 2;
+        }
+    }}
+}}
+
+binding_context! {Context = One!()}
+
+binding_context! {NextContext = Two!()}
+
+// Binds every parameter, runs both programs and pipes the out of the first into the second
+#[allow(dead_code)]
+fn pipe(program1: &ContextProgram, program2: &NextContextProgram, device: &Rc<wgpu::Device>) {
+    let indices = BindGroup1::new(device, &BufferData::new(vec![1, 2, 3, 4]));
+    let empty1 = BindGroup1::new(device, &BufferData::new(vec![0, 0, 0, 0]));
+    let empty2 = BindGroup1::new(device, &BufferData::new(vec![0, 0, 0, 0]));
+    let size = DispatchSize::new(device, [4, 1, 1]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut cpass = program1.compute_pass(&mut encoder);
+    let context = program1
+        .begin(&mut cpass)
+        .set_add_one_in(&mut cpass, &indices)
+        .set_add_two_in(&mut cpass, &empty1);
+    context.dispatch(&mut cpass, &size);
+
+    let mut cpass = program2.continue_pass(cpass);
+    let next_start = program2.begin(&mut cpass);
+    let next_context = context
+        .pipe_into(&mut cpass, next_start)
+        .set_add_two_result(&mut cpass, &empty2);
+    next_context.dispatch(&mut cpass, &size);
+}
+
+fn main() {}
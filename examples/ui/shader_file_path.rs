@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate pipeline;
+
+// The path is relative to the Cargo.toml of the crate, not to this file
+shader_file! {compute = "trivial.comp"}
+
+fn main() {}
@@ -0,0 +1,33 @@
+#![recursion_limit = "1024"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::AbstractBind;
+
+// Two arrays of 4096 floats take twice the workgroup memory there is
+my_shader! {compute = {
+    workgroup_size(64);
+    [group1 [buffer in out] float[]] values;
+    [[shared] float[4096]] first;
+    [[shared] float[4096]] second;
+    [[] uvec3] gl_GlobalInvocationID;
+    [[] uvec3] gl_LocalInvocationID;
+    {{
+        void main() {
+            uint local = gl_LocalInvocationID.x;
+            first[local] = gl_GlobalInvocationID.x < dispatch_size.x ? values[gl_GlobalInvocationID.x] : 0.0;
+            second[local] = first[local];
+            barrier();
+            if (gl_GlobalInvocationID.x < dispatch_size.x) {
+                values[gl_GlobalInvocationID.x] = second[local];
+            }
+        }
+    }}
+}}
+
+binding_context! {Context = compute!()}
+
+fn main() {}
@@ -0,0 +1,28 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::AbstractBind;
+
+// Each function only sees the locals it declares, the parameters of the shader and the global scope
+my_shader! {compute = {
+    [group1 [buffer loop in] uint[]] indices;
+    [group2 [buffer out] uint[]] result;
+    {{
+        uint add_index(uint n) {
+            return n + index;
+        }
+
+        void main() {
+            uint index = gl_GlobalInvocationID.x;
+            result[index] = add_index(indices[index]);
+        }
+    }}
+}}
+
+binding_context! {Context = compute!()}
+
+fn main() {}
@@ -0,0 +1,25 @@
+#![recursion_limit = "256"]
+#[macro_use]
+extern crate pipeline;
+
+#[macro_use]
+extern crate eager;
+
+pub use pipeline::AbstractBind;
+
+// Only an out parameter can be assigned to
+my_shader! {compute = {
+    [group1 [buffer loop in] uint[]] indices;
+    [group2 [buffer out] uint[]] result;
+    {{
+        void main() {
+            uint index = gl_GlobalInvocationID.x;
+            indices[index] = indices[index] + 1;
+            result[index] = indices[index];
+        }
+    }}
+}}
+
+binding_context! {Context = compute!()}
+
+fn main() {}
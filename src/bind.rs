@@ -1,9 +1,12 @@
-pub use crate::read::MyBufferView;
-pub use crate::write::MyBufferViewMut;
-use crate::shared::{GLSLTYPE, QUALIFIER};
+pub use crate::read::{MyBufferView, MyImageView};
+use crate::shared::{round_up, BlockLayout, StructDescription, GLSLTYPE, QUALIFIER};
 pub use crate::write;
+pub use crate::write::MyBufferViewMut;
+use crate::RuntimePass;
+use once_cell::unsync::OnceCell;
 use std::marker::PhantomData;
 use wgpu_macros::create_get_view_func;
+pub use wgpu_macros::WgpuStruct;
 use zerocopy::AsBytes as _;
 
 use std::rc::Rc;
@@ -28,7 +31,7 @@ pub trait WgpuType {
     fn get_qualifiers() -> Option<QUALIFIER>;
 }
 
-/// This struct is used to hold traditional array like data such as single values, vectors, and matricies. It is parameterized on a `BINDINGTYPE` which specifies whether the end buffer is suppose to be a uniform or storage buffer.
+/// This struct is used to hold traditional array like data such as single values, vectors, and matricies. It is parameterized on a `BINDINGTYPE` which specifies whether the end buffer is suppose to be a uniform or storage buffer. The Context picks whether a storage buffer is read only from the qualifiers of its parameter.
 pub struct BufferData<const BINDINGTYPE: wgpu::BufferBindingType, T> {
     pub data: T,
 }
@@ -39,192 +42,202 @@ impl<const BINDINGTYPE: wgpu::BufferBindingType, T> BufferData<BINDINGTYPE, T> {
     }
 }
 
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType for BufferData<BINDINGTYPE, f32> {
-    fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        BoundData::new_buffer(
-            device,
-            self.data.as_bytes(),
-            1 as u64,
-            Self::size_of(),
-            qual,
-            Self::create_binding_type(),
-        )
-    }
-    fn size_of() -> usize {
-        std::mem::size_of::<f32>()
-    }
-    fn create_binding_type() -> wgpu::BindingType {
-        wgpu::BindingType::Buffer {
-            ty: BINDINGTYPE,
-            has_dynamic_offset: false,
-            min_binding_size: wgpu::BufferSize::new(Self::size_of() as u64),
-        }
-    }
-    fn get_qualifiers() -> Option<QUALIFIER> {
-        match BINDINGTYPE {
-            wgpu::BufferBindingType::Uniform => Some(QUALIFIER::UNIFORM),
-            wgpu::BufferBindingType::Storage { read_only: _ } => Some(QUALIFIER::BUFFER),
-        }
-    }
+/// A type that can be the field of a struct deriving `WgpuStruct` or the data of a `BufferData`.
+pub trait WgpuField {
+    /// The GLSL type of the field
+    const GTYPE: GLSLTYPE;
+
+    /// Writes the value to the start of `out` following the rules of `layout`.
+    #[doc(hidden)]
+    fn write(&self, layout: BlockLayout, out: &mut [u8]);
 }
 
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType for BufferData<BINDINGTYPE, Vec<u32>> {
-    fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        BoundData::new_buffer(
-            device,
-            self.data.as_slice().as_bytes(),
-            self.data.len() as u64,
-            Self::size_of(),
-            qual,
-            Self::create_binding_type(),
-        )
-    }
-    fn size_of() -> usize {
-        std::mem::size_of::<[u32; 1]>()
-    }
-    fn create_binding_type() -> wgpu::BindingType {
-        wgpu::BindingType::Buffer {
-            ty: BINDINGTYPE,
-            has_dynamic_offset: false,
-            min_binding_size: wgpu::BufferSize::new(Self::size_of() as u64),
+macro_rules! impl_wgpu_scalar {
+    ($($ty:ty => $gtype:expr),*) => {$(
+        impl WgpuField for $ty {
+            const GTYPE: GLSLTYPE = $gtype;
+            fn write(&self, _: BlockLayout, out: &mut [u8]) {
+                let bytes = self.as_bytes();
+                out[..bytes.len()].copy_from_slice(bytes);
+            }
         }
+    )*};
+}
+
+impl_wgpu_scalar!(
+    f32 => GLSLTYPE::Float,
+    f64 => GLSLTYPE::Double,
+    i32 => GLSLTYPE::Int,
+    u32 => GLSLTYPE::Uint
+);
+
+// A bool in a block takes up as much space as a uint
+impl WgpuField for bool {
+    const GTYPE: GLSLTYPE = GLSLTYPE::Bool;
+    fn write(&self, layout: BlockLayout, out: &mut [u8]) {
+        (*self as u32).write(layout, out)
     }
-    fn get_qualifiers() -> Option<QUALIFIER> {
-        match BINDINGTYPE {
-            wgpu::BufferBindingType::Uniform => Some(QUALIFIER::UNIFORM),
-            wgpu::BufferBindingType::Storage { read_only: _ } => Some(QUALIFIER::BUFFER),
+}
+
+// Vectors are written component by component
+macro_rules! impl_wgpu_vector {
+    ($($scalar:ty, $n:literal => $gtype:expr),*) => {$(
+        impl WgpuField for [$scalar; $n] {
+            const GTYPE: GLSLTYPE = $gtype;
+            fn write(&self, layout: BlockLayout, out: &mut [u8]) {
+                let size = <$scalar as WgpuField>::GTYPE.align_and_size(layout).1;
+                self.iter()
+                    .enumerate()
+                    .for_each(|(i, c)| c.write(layout, &mut out[i * size..]));
+            }
         }
-    }
+    )*};
 }
 
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType for BufferData<BINDINGTYPE, Vec<f32>> {
-    fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        BoundData::new_buffer(
-            device,
-            self.data.as_slice().as_bytes(),
-            self.data.len() as u64,
-            Self::size_of(),
-            qual,
-            Self::create_binding_type(),
-        )
-    }
-    fn size_of() -> usize {
-        std::mem::size_of::<[f32; 1]>()
-    }
-    fn create_binding_type() -> wgpu::BindingType {
-        wgpu::BindingType::Buffer {
-            ty: BINDINGTYPE,
-            has_dynamic_offset: false,
-            min_binding_size: wgpu::BufferSize::new(Self::size_of() as u64),
+impl_wgpu_vector!(
+    bool, 2 => GLSLTYPE::Bvec2,
+    bool, 3 => GLSLTYPE::Bvec3,
+    bool, 4 => GLSLTYPE::Bvec4,
+    i32, 2 => GLSLTYPE::Ivec2,
+    i32, 3 => GLSLTYPE::Ivec3,
+    i32, 4 => GLSLTYPE::Ivec4,
+    u32, 2 => GLSLTYPE::Uvec2,
+    u32, 3 => GLSLTYPE::Uvec3,
+    u32, 4 => GLSLTYPE::Uvec4,
+    f32, 2 => GLSLTYPE::Vec2,
+    f32, 3 => GLSLTYPE::Vec3,
+    f32, 4 => GLSLTYPE::Vec4,
+    f64, 2 => GLSLTYPE::Dvec2,
+    f64, 3 => GLSLTYPE::Dvec3,
+    f64, 4 => GLSLTYPE::Dvec4
+);
+
+// Matrices are column major so `[[f32; ROWS]; COLS]` and each column is padded out like an array element
+macro_rules! impl_wgpu_matrix {
+    ($($scalar:ty, $cols:literal, $rows:literal => $gtype:expr),*) => {$(
+        impl WgpuField for [[$scalar; $rows]; $cols] {
+            const GTYPE: GLSLTYPE = $gtype;
+            fn write(&self, layout: BlockLayout, out: &mut [u8]) {
+                let stride = Self::GTYPE.align_and_size(layout).0;
+                self.iter()
+                    .enumerate()
+                    .for_each(|(i, c)| c.write(layout, &mut out[i * stride..]));
+            }
         }
-    }
-    fn get_qualifiers() -> Option<QUALIFIER> {
-        match BINDINGTYPE {
-            wgpu::BufferBindingType::Uniform => Some(QUALIFIER::UNIFORM),
-            wgpu::BufferBindingType::Storage { read_only: _ } => Some(QUALIFIER::BUFFER),
+    )*};
+}
+
+impl_wgpu_matrix!(
+    f32, 2, 2 => GLSLTYPE::Mat2,
+    f32, 2, 3 => GLSLTYPE::Mat2x3,
+    f32, 2, 4 => GLSLTYPE::Mat2x4,
+    f32, 3, 2 => GLSLTYPE::Mat3x2,
+    f32, 3, 3 => GLSLTYPE::Mat3,
+    f32, 3, 4 => GLSLTYPE::Mat3x4,
+    f32, 4, 2 => GLSLTYPE::Mat4x2,
+    f32, 4, 3 => GLSLTYPE::Mat4x3,
+    f32, 4, 4 => GLSLTYPE::Mat4,
+    f64, 2, 2 => GLSLTYPE::Dmat2,
+    f64, 2, 3 => GLSLTYPE::Dmat2x3,
+    f64, 2, 4 => GLSLTYPE::Dmat2x4,
+    f64, 3, 2 => GLSLTYPE::Dmat3x2,
+    f64, 3, 3 => GLSLTYPE::Dmat3,
+    f64, 3, 4 => GLSLTYPE::Dmat3x4,
+    f64, 4, 2 => GLSLTYPE::Dmat4x2,
+    f64, 4, 3 => GLSLTYPE::Dmat4x3,
+    f64, 4, 4 => GLSLTYPE::Dmat4
+);
+
+// The square matrices can also be given as cgmath matrices
+macro_rules! impl_wgpu_cgmath {
+    ($($matrix:ident, $scalar:ty, $n:literal),*) => {$(
+        impl WgpuField for cgmath::$matrix<$scalar> {
+            const GTYPE: GLSLTYPE = <[[$scalar; $n]; $n] as WgpuField>::GTYPE;
+            fn write(&self, layout: BlockLayout, out: &mut [u8]) {
+                let columns: &[[$scalar; $n]; $n] = self.as_ref();
+                columns.write(layout, out)
+            }
         }
-    }
+    )*};
 }
 
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType
-    for BufferData<BINDINGTYPE, Vec<[f32; 2]>>
-{
-    fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        let numbers: Vec<f32> = self
-            .data
-            .clone()
-            .into_iter()
-            .map(|x| x.to_vec())
-            .flatten()
-            .collect();
-        BoundData::new_buffer(
-            device,
-            numbers.as_slice().as_bytes(),
-            self.data.len() as u64,
-            Self::size_of(),
-            qual,
-            Self::create_binding_type(),
-        )
-    }
+impl_wgpu_cgmath!(
+    Matrix2, f32, 2, Matrix3, f32, 3, Matrix4, f32, 4, Matrix2, f64, 2, Matrix3, f64, 3, Matrix4,
+    f64, 4
+);
 
-    fn size_of() -> usize {
-        std::mem::size_of::<[f32; 2]>()
-    }
-    fn create_binding_type() -> wgpu::BindingType {
-        wgpu::BindingType::Buffer {
-            ty: BINDINGTYPE,
-            has_dynamic_offset: false,
-            min_binding_size: wgpu::BufferSize::new(Self::size_of() as u64),
-        }
-    }
-    fn get_qualifiers() -> Option<QUALIFIER> {
-        match BINDINGTYPE {
-            wgpu::BufferBindingType::Uniform => Some(QUALIFIER::UNIFORM),
-            wgpu::BufferBindingType::Storage { read_only: _ } => Some(QUALIFIER::BUFFER),
-        }
-    }
+/// A Rust struct that can be used as a GLSL struct parameter, this should be implemented with `#[derive(WgpuStruct)]`.
+/// Every field needs to implement `WgpuField`.
+pub trait WgpuStruct {
+    /// The name and fields of the struct that are used to declare it in the shader
+    const DESCRIPTION: StructDescription;
+
+    /// Writes each field at the offset given to it by `layout`.
+    #[doc(hidden)]
+    fn write_fields(&self, layout: BlockLayout, out: &mut [u8]);
 }
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType
-    for BufferData<BINDINGTYPE, Vec<[f32; 3]>>
-{
-    fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        let numbers: Vec<f32> = self
-            .data
-            .clone()
-            .into_iter()
-            .map(|x| {let mut y = x.to_vec(); y.push(0.0); y}) // We need to extend Vec3 -> Vec4 for alignment
-            .flatten()
-            .collect();
-        BoundData::new_buffer(
-            device,
-            numbers.as_slice().as_bytes(),
-            self.data.len() as u64,
-            Self::size_of(),
-            qual,
-            Self::create_binding_type(),
-        )
-    }
 
-    fn size_of() -> usize {
-        std::mem::size_of::<[f32; 4]>()
+impl<T: WgpuStruct> WgpuField for T {
+    const GTYPE: GLSLTYPE = GLSLTYPE::Struct(&T::DESCRIPTION);
+    fn write(&self, layout: BlockLayout, out: &mut [u8]) {
+        self.write_fields(layout, out)
     }
-    fn create_binding_type() -> wgpu::BindingType {
-        wgpu::BindingType::Buffer {
-            ty: BINDINGTYPE,
-            has_dynamic_offset: false,
-            min_binding_size: wgpu::BufferSize::new(Self::size_of() as u64),
-        }
+}
+
+// Uniforms are laid out with std140 and storage buffers with std430.
+// Vertex buffers are tightly packed other than vec3's which are padded out to a vec4 like in std430.
+fn block_layout(binding_type: wgpu::BufferBindingType, qual: Option<QUALIFIER>) -> BlockLayout {
+    match (qual, binding_type) {
+        (Some(QUALIFIER::VERTEX), _) => BlockLayout::Std430,
+        (_, wgpu::BufferBindingType::Uniform) => BlockLayout::Std140,
+        (_, wgpu::BufferBindingType::Storage { read_only: _ }) => BlockLayout::Std430,
     }
-    fn get_qualifiers() -> Option<QUALIFIER> {
-        match BINDINGTYPE {
-            wgpu::BufferBindingType::Uniform => Some(QUALIFIER::UNIFORM),
-            wgpu::BufferBindingType::Storage { read_only: _ } => Some(QUALIFIER::BUFFER),
-        }
+}
+
+impl<const BINDINGTYPE: wgpu::BufferBindingType, T: WgpuField> BufferData<BINDINGTYPE, T> {
+    /// The GLSL type of the data
+    pub const GTYPE: GLSLTYPE = T::GTYPE;
+
+    /// Sets the data as the push constant at `offset` instead of binding it to a buffer.
+    #[doc(hidden)]
+    pub fn set_push_constant<'a, P: RuntimePass<'a>>(&self, rpass: &mut P, offset: usize) {
+        // Push constants are written in multiples of 4 bytes
+        let mut bytes = vec![0; round_up(T::GTYPE.align_and_size(BlockLayout::Std430).1, 4)];
+        self.data.write(BlockLayout::Std430, &mut bytes);
+        rpass.set_push_constants(offset as u32, &bytes);
     }
 }
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType
-    for BufferData<BINDINGTYPE, Vec<[f32; 4]>>
+
+// Lay out each element one after the other with the padding they need between them
+fn array_bytes<T: WgpuField>(data: &[T], layout: BlockLayout) -> Vec<u8> {
+    let stride = T::GTYPE.array_stride(layout);
+    let mut bytes = vec![0; stride * data.len()];
+    data.iter()
+        .zip(bytes.chunks_exact_mut(stride))
+        .for_each(|(d, out)| d.write(layout, out));
+    bytes
+}
+
+impl<const BINDINGTYPE: wgpu::BufferBindingType, T: WgpuField> WgpuType
+    for BufferData<BINDINGTYPE, T>
 {
     fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        let numbers: Vec<f32> = self
-            .data
-            .clone()
-            .into_iter()
-            .map(|x| x.to_vec())
-            .flatten()
-            .collect();
+        let layout = block_layout(BINDINGTYPE, qual.clone());
+        let (align, size) = T::GTYPE.align_and_size(layout);
+        let mut bytes = vec![0; round_up(size, align)];
+        self.data.write(layout, &mut bytes);
         BoundData::new_buffer(
             device,
-            numbers.as_slice().as_bytes(),
-            self.data.len() as u64,
-            Self::size_of(),
+            &bytes,
+            1,
+            bytes.len(),
             qual,
             Self::create_binding_type(),
         )
     }
-
     fn size_of() -> usize {
-        std::mem::size_of::<[f32; 4]>()
+        let (align, size) = T::GTYPE.align_and_size(block_layout(BINDINGTYPE, None));
+        round_up(size, align)
     }
     fn create_binding_type() -> wgpu::BindingType {
         wgpu::BindingType::Buffer {
@@ -240,25 +253,24 @@ impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType
         }
     }
 }
-impl<const BINDINGTYPE: wgpu::BufferBindingType> WgpuType
-    for BufferData<BINDINGTYPE, cgmath::Matrix4<f32>>
+
+impl<const BINDINGTYPE: wgpu::BufferBindingType, T: WgpuField> WgpuType
+    for BufferData<BINDINGTYPE, Vec<T>>
 {
     fn bind(&self, device: &wgpu::Device, qual: Option<QUALIFIER>) -> BoundData {
-        let mat_slice: &[f32; 16] = self.data.as_ref();
+        let layout = block_layout(BINDINGTYPE, qual.clone());
         BoundData::new_buffer(
             device,
-            bytemuck::cast_slice(mat_slice.as_bytes()),
-            64,
-            Self::size_of(),
+            &array_bytes(self.data.as_slice(), layout),
+            self.data.len() as u64,
+            T::GTYPE.array_stride(layout),
             qual,
             Self::create_binding_type(),
         )
     }
-
     fn size_of() -> usize {
-        64
+        T::GTYPE.array_stride(block_layout(BINDINGTYPE, None))
     }
-
     fn create_binding_type() -> wgpu::BindingType {
         wgpu::BindingType::Buffer {
             ty: BINDINGTYPE,
@@ -426,6 +438,125 @@ impl<
     }
 }
 
+/// A texel of a storage image, which decides the format of the texture it is in.
+pub trait Texel: zerocopy::AsBytes + zerocopy::FromBytes + Copy {
+    const FORMAT: wgpu::TextureFormat;
+}
+
+macro_rules! impl_texel {
+    ($($ty:ty => $format:ident),*) => {$(
+        impl Texel for $ty {
+            const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::$format;
+        }
+    )*};
+}
+
+impl_texel!(
+    [u8; 4] => Rgba8Unorm,
+    [f32; 4] => Rgba32Float,
+    f32 => R32Float,
+    [u32; 4] => Rgba32Uint,
+    u32 => R32Uint
+);
+
+/// This struct is used to create storage images, which a compute shader reads and writes a texel at a time with `imageLoad` and `imageStore`.
+/// The format of the texture comes from the texel type `T`, like `[u8; 4]` for an `rgba8` image.
+/// For `ACCESS` https://docs.rs/wgpu/0.7.0/wgpu/enum.StorageTextureAccess.html
+/// For `VIEWDIMENSION` https://docs.rs/wgpu/0.7.0/wgpu/enum.TextureViewDimension.html, only `D2` and `D3` are supported
+pub struct StorageTextureData<
+    const ACCESS: wgpu::StorageTextureAccess,
+    const VIEWDIMENSION: wgpu::TextureViewDimension,
+    T: Texel,
+> {
+    pub data: Option<Vec<T>>,
+    pub size: wgpu::Extent3d,
+    queue: Rc<wgpu::Queue>,
+}
+
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+    > StorageTextureData<ACCESS, VIEWDIMENSION, T>
+{
+    pub fn new(data: Vec<T>, size: wgpu::Extent3d, queue: Rc<wgpu::Queue>) -> Self {
+        assert_eq!(
+            data.len(),
+            (size.width * size.height * size.depth) as usize,
+            "The image needs one texel for each pixel"
+        );
+        StorageTextureData {
+            data: Some(data),
+            size,
+            queue,
+        }
+    }
+    pub fn new_without_data(size: wgpu::Extent3d, queue: Rc<wgpu::Queue>) -> Self {
+        StorageTextureData {
+            data: None,
+            size,
+            queue,
+        }
+    }
+    fn desc(&self) -> wgpu::TextureDescriptor<'static> {
+        wgpu::TextureDescriptor {
+            label: None,
+            size: self.size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: match VIEWDIMENSION {
+                wgpu::TextureViewDimension::D3 => wgpu::TextureDimension::D3,
+                _ => wgpu::TextureDimension::D2,
+            },
+            format: T::FORMAT,
+            // Copied from to read the image back
+            usage: wgpu::TextureUsage::STORAGE
+                | wgpu::TextureUsage::COPY_SRC
+                | wgpu::TextureUsage::COPY_DST,
+        }
+    }
+}
+
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+    > WgpuType for StorageTextureData<ACCESS, VIEWDIMENSION, T>
+{
+    fn bind(&self, device: &wgpu::Device, _: Option<QUALIFIER>) -> BoundData {
+        let texture = match &self.data {
+            Some(data) => {
+                device.create_texture_with_data(&self.queue, &self.desc(), data.as_bytes())
+            }
+            None => device.create_texture(&self.desc()),
+        };
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(VIEWDIMENSION),
+            ..wgpu::TextureViewDescriptor::default()
+        });
+        BoundData::StorageTexture {
+            data: texture,
+            view,
+            size: self.size,
+            binding_type: Self::create_binding_type(),
+        }
+    }
+    fn size_of() -> usize {
+        std::mem::size_of::<T>()
+    }
+
+    fn create_binding_type() -> wgpu::BindingType {
+        wgpu::BindingType::StorageTexture {
+            access: ACCESS,
+            format: T::FORMAT,
+            view_dimension: VIEWDIMENSION,
+        }
+    }
+    fn get_qualifiers() -> Option<QUALIFIER> {
+        None
+    }
+}
+
 #[doc(hidden)]
 /// The result of binding WGPUType data to the gpu. These are basically all handlers to GPU data of different types.
 pub enum BoundData {
@@ -440,6 +571,12 @@ pub enum BoundData {
         view: wgpu::TextureView,
         binding_type: wgpu::BindingType,
     },
+    StorageTexture {
+        data: wgpu::Texture,
+        view: wgpu::TextureView,
+        size: wgpu::Extent3d,
+        binding_type: wgpu::BindingType,
+    },
     Sampler {
         data: wgpu::Sampler,
         binding_type: wgpu::BindingType,
@@ -500,12 +637,27 @@ impl BoundData {
             _ => unreachable!(),
         }
     }
+    pub fn get_image(&self) -> Option<(&wgpu::Texture, wgpu::Extent3d)> {
+        match self {
+            BoundData::StorageTexture { data, size, .. } => Some((data, *size)),
+            _ => None,
+        }
+    }
     pub fn get_sampler(self) -> wgpu::Sampler {
         match self {
             BoundData::Sampler { data, .. } => (data),
             _ => unreachable!(),
         }
     }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        match self {
+            BoundData::Buffer { binding_type, .. }
+            | BoundData::Texture { binding_type, .. }
+            | BoundData::StorageTexture { binding_type, .. }
+            | BoundData::Sampler { binding_type, .. } => *binding_type,
+        }
+    }
 }
 
 //todo get rid of this when there is a new way to stringify shaders
@@ -546,10 +698,27 @@ pub struct DefaultBinding {
 pub struct Indices {
     pub buffer: Rc<wgpu::Buffer>,
     pub len: u32,
+    /// The largest index, which has to be within the vertex buffers that are drawn with these indices
+    pub max_index: Option<u32>,
+    /// Whether the indices are `u16` or `u32`, which the index buffer is set with
+    pub format: wgpu::IndexFormat,
+}
+
+/// An index of an index buffer, which decides the format the buffer is read with.
+pub trait Index: zerocopy::AsBytes + Copy + Into<u32> {
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl Index for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+}
+
+impl Index for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
 }
 
 impl Indices {
-    pub fn new(device: &wgpu::Device, data: &Vec<u16>) -> Self {
+    pub fn new<I: Index>(device: &wgpu::Device, data: &Vec<I>) -> Self {
         Indices {
             buffer: Rc::new(
                 device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -559,6 +728,8 @@ impl Indices {
                 }),
             ),
             len: data.len() as u32,
+            max_index: data.iter().map(|&i| i.into()).max(),
+            format: I::FORMAT,
         }
     }
 }
@@ -567,6 +738,7 @@ impl Indices {
 pub struct Vertex<A: WgpuType + ?Sized> {
     typ: PhantomData<A>,
     buffer: Rc<wgpu::Buffer>,
+    len: u32,
 }
 
 impl<'a, A: WgpuType> Vertex<A> {
@@ -574,33 +746,175 @@ impl<'a, A: WgpuType> Vertex<A> {
         &self.buffer
     }
 
+    /// The number of elements in the buffer, which is how many vertices or instances it has data for
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn new(device: &wgpu::Device, data: &A) -> Self {
+        let (buffer, len, _) = data
+            .bind(device, Some(QUALIFIER::VERTEX))
+            .get_buffer()
+            .unwrap();
         Vertex {
             typ: PhantomData,
-            buffer: data.bind(device, Some(QUALIFIER::VERTEX)).get_buffer().unwrap().0,
+            buffer,
+            len: len as u32,
+        }
+    }
+}
+
+// A Context's layout makes each group visible to the stages whose shaders declare its parameters,
+// and a bind group can only be set on a pipeline with a layout of the same visibility.
+// So the group of a set of stages is created the first time a Context with that visibility binds it,
+// which lets one group be bound on any Context.
+type BindGroups = [OnceCell<wgpu::BindGroup>; 8];
+
+fn get_or_create_bind_group<'a>(
+    bind_groups: &'a BindGroups,
+    device: &wgpu::Device,
+    buffers: &[BoundData],
+    visibility: wgpu::ShaderStage,
+) -> &'a wgpu::BindGroup {
+    bind_groups[visibility.bits() as usize].get_or_init(|| {
+        let entries: Vec<_> = buffers.iter().map(|b| (b, b.binding_type())).collect();
+        create_bind_group(device, &entries, visibility)
+    })
+}
+
+// The next program binds a piped entry alone in a group, with any visibility and either the access of the entry or read only,
+// so the group of each of these is created the first time an entry is piped with it.
+// The first half is for the access of the entry and the second for read only, each indexed by the bits of the stages.
+type PipedBindGroups = [OnceCell<wgpu::BindGroup>; 16];
+
+/// An entry of a group which a Context keeps for each out once the group is set, to pipe it into the next program.
+#[derive(Clone, Copy)]
+pub struct PipeSource<'a> {
+    device: &'a wgpu::Device,
+    data: &'a BoundData,
+    bind_groups: &'a PipedBindGroups,
+}
+
+fn pipe_source<'a>(
+    device: &'a wgpu::Device,
+    buffers: &'a [BoundData],
+    piped: &'a [PipedBindGroups],
+    entry: usize,
+) -> PipeSource<'a> {
+    PipeSource {
+        device,
+        data: &buffers[entry],
+        bind_groups: &piped[entry],
+    }
+}
+
+/// An entry of a group that one program wrote, which the next program binds alone in a group as a `D`.
+/// It is created by the Context that bound the group, and is only read as another type that `D` can be piped into.
+pub struct Piped<'a, D> {
+    typ: PhantomData<D>,
+    source: PipeSource<'a>,
+}
+
+impl<'a, D> Clone for Piped<'a, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, D> Copy for Piped<'a, D> {}
+
+impl<'a, D> Piped<'a, D> {
+    // Only a Context creates one, with the type of the out it kept the entry for
+    #[doc(hidden)]
+    pub fn from_source(source: PipeSource<'a>) -> Self {
+        Piped {
+            typ: PhantomData,
+            source,
         }
     }
+
+    #[doc(hidden)]
+    pub fn source(self) -> PipeSource<'a> {
+        self.source
+    }
+
+    /// The same entry, read as the parameter of the next program
+    pub fn pipe_as<E>(self) -> Piped<'a, E>
+    where
+        D: PipeInto<E>,
+    {
+        Piped::from_source(self.source)
+    }
+}
+
+impl<'a, D: WgpuType> Piped<'a, D> {
+    pub fn get_bind_group(&self, visibility: wgpu::ShaderStage) -> &'a wgpu::BindGroup {
+        let PipeSource {
+            device,
+            data,
+            bind_groups,
+        } = self.source;
+        // `D` is the entry's own type or what it pipes into read only
+        let binding_type = D::create_binding_type();
+        let read_only = binding_type != data.binding_type();
+        bind_groups[visibility.bits() as usize + if read_only { 8 } else { 0 }]
+            .get_or_init(|| create_bind_group(device, &[(data, binding_type)], visibility))
+    }
+}
+
+/// Data written by one program that the next program can bind as a `D`.
+/// That is the same type with the same access, or the same type read only, so an `out` or `in out` pipes into an `in`.
+pub trait PipeInto<D> {}
+
+impl<T> PipeInto<BufferData<{ wgpu::BufferBindingType::Storage { read_only: false } }, T>>
+    for BufferData<{ wgpu::BufferBindingType::Storage { read_only: false } }, T>
+{
+}
+
+impl<T> PipeInto<BufferData<{ wgpu::BufferBindingType::Storage { read_only: true } }, T>>
+    for BufferData<{ wgpu::BufferBindingType::Storage { read_only: false } }, T>
+{
+}
+
+impl<const VIEWDIMENSION: wgpu::TextureViewDimension, T: Texel>
+    PipeInto<StorageTextureData<{ wgpu::StorageTextureAccess::WriteOnly }, VIEWDIMENSION, T>>
+    for StorageTextureData<{ wgpu::StorageTextureAccess::WriteOnly }, VIEWDIMENSION, T>
+{
 }
 
-fn create_bind_group(device: &wgpu::Device, buffers: &Vec<BoundData>) -> wgpu::BindGroup {
+impl<const VIEWDIMENSION: wgpu::TextureViewDimension, T: Texel>
+    PipeInto<StorageTextureData<{ wgpu::StorageTextureAccess::ReadWrite }, VIEWDIMENSION, T>>
+    for StorageTextureData<{ wgpu::StorageTextureAccess::ReadWrite }, VIEWDIMENSION, T>
+{
+}
+
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+    > PipeInto<StorageTextureData<{ wgpu::StorageTextureAccess::ReadOnly }, VIEWDIMENSION, T>>
+    for StorageTextureData<ACCESS, VIEWDIMENSION, T>
+{
+}
+
+// Each entry is bound with the binding type it is given, which is its own unless it is piped
+fn create_bind_group(
+    device: &wgpu::Device,
+    buffers: &[(&BoundData, wgpu::BindingType)],
+    visibility: wgpu::ShaderStage,
+) -> wgpu::BindGroup {
     let bind_entry: Vec<_> = buffers
         .iter()
         .enumerate()
-        .map(|(i, buf)| {
-            wgpu::BindGroupLayoutEntry {
-                binding: i as u32,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // can check which stage
-                ty: match buf {
-                    BoundData::Buffer { binding_type, .. } => *binding_type,
-                    //todo check that these are good
-                    BoundData::Texture { binding_type, .. } => *binding_type,
-                    BoundData::Sampler { binding_type, .. } => *binding_type,
-                },
-
-                count: None,
-            }
+        .map(|(i, (_, binding_type))| wgpu::BindGroupLayoutEntry {
+            binding: i as u32,
+            visibility,
+            ty: *binding_type,
+            count: None,
         })
         .collect();
 
@@ -612,11 +926,13 @@ fn create_bind_group(device: &wgpu::Device, buffers: &Vec<BoundData>) -> wgpu::B
     let bind_group_bindings: Vec<_> = buffers
         .iter()
         .enumerate()
-        .map(|(i, buf)| wgpu::BindGroupEntry {
+        .map(|(i, (buf, _))| wgpu::BindGroupEntry {
             binding: i as u32,
             resource: match buf {
                 BoundData::Buffer { data, .. } => data.as_entire_binding(),
-                BoundData::Texture { view, .. } => wgpu::BindingResource::TextureView(&view),
+                BoundData::Texture { view, .. } | BoundData::StorageTexture { view, .. } => {
+                    wgpu::BindingResource::TextureView(&view)
+                }
                 BoundData::Sampler { data, .. } => wgpu::BindingResource::Sampler(data),
             },
         })
@@ -632,23 +948,33 @@ fn create_bind_group(device: &wgpu::Device, buffers: &Vec<BoundData>) -> wgpu::B
 // todo generate with a macro
 pub struct BindGroup1<B: WgpuType> {
     typ1: PhantomData<B>,
+    device: Rc<wgpu::Device>,
     data: Vec<BoundData>,
 
-    bind_group: wgpu::BindGroup,
+    // One for each set of stages the group can be visible to
+    bind_groups: BindGroups,
+    piped: Vec<PipedBindGroups>,
 }
 
 //todo also do impl's with macro
 impl<'a, B: WgpuType> BindGroup1<B> {
-    pub fn get_bind_group(&'a self) -> &'a wgpu::BindGroup {
-        &self.bind_group
+    pub fn get_bind_group(&'a self, visibility: wgpu::ShaderStage) -> &'a wgpu::BindGroup {
+        get_or_create_bind_group(&self.bind_groups, &self.device, &self.data, visibility)
     }
 
-    pub fn get_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    // Each entry on its own, which the Context keeps for the outs it pipes into the next program
+    pub fn piped_0(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 0)
+    }
+
+    // The layout of the group for a Context that makes it visible to `visibility`
+    pub fn get_layout(
+        device: &wgpu::Device,
+        visibility: wgpu::ShaderStage,
+    ) -> wgpu::BindGroupLayout {
         let bind_entry_vec = vec![wgpu::BindGroupLayoutEntry {
             binding: 0,
-            visibility: wgpu::ShaderStage::VERTEX
-                | wgpu::ShaderStage::FRAGMENT
-                | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+            visibility,
             ty: B::create_binding_type(),
             count: None,
         }];
@@ -662,45 +988,56 @@ impl<'a, B: WgpuType> BindGroup1<B> {
         })
     }
 
-    pub fn new(device: &wgpu::Device, data0: &B) -> Self {
+    pub fn new(device: &Rc<wgpu::Device>, data0: &B) -> Self {
         let data = vec![data0.bind(device, B::get_qualifiers())];
 
-        let bind_group = create_bind_group(device, &data);
+        let piped = data.iter().map(|_| Default::default()).collect();
 
         BindGroup1 {
             typ1: PhantomData,
+            device: device.clone(),
             data,
-            bind_group,
+            bind_groups: Default::default(),
+            piped,
         }
     }
 }
 pub struct BindGroup2<B: WgpuType, C: WgpuType> {
     typ1: PhantomData<B>,
     typ2: PhantomData<C>,
+    device: Rc<wgpu::Device>,
     data: Vec<BoundData>,
-    bind_group: wgpu::BindGroup,
+    bind_groups: BindGroups,
+    piped: Vec<PipedBindGroups>,
 }
 
 impl<'a, B: WgpuType, C: WgpuType> BindGroup2<B, C> {
-    pub fn get_bind_group(&'a self) -> &'a wgpu::BindGroup {
-        &self.bind_group
+    pub fn get_bind_group(&'a self, visibility: wgpu::ShaderStage) -> &'a wgpu::BindGroup {
+        get_or_create_bind_group(&self.bind_groups, &self.device, &self.data, visibility)
+    }
+
+    // Each entry on its own, which the Context keeps for the outs it pipes into the next program
+    pub fn piped_0(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 0)
+    }
+    pub fn piped_1(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 1)
     }
 
-    pub fn get_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    pub fn get_layout(
+        device: &wgpu::Device,
+        visibility: wgpu::ShaderStage,
+    ) -> wgpu::BindGroupLayout {
         let bind_entry_vec = vec![
             wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+                visibility,
                 ty: B::create_binding_type(),
                 count: None,
             },
             wgpu::BindGroupLayoutEntry {
                 binding: 1,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+                visibility,
                 ty: C::create_binding_type(),
                 count: None,
             },
@@ -715,19 +1052,21 @@ impl<'a, B: WgpuType, C: WgpuType> BindGroup2<B, C> {
         })
     }
 
-    pub fn new(device: &wgpu::Device, data0: &B, data1: &C) -> Self {
+    pub fn new(device: &Rc<wgpu::Device>, data0: &B, data1: &C) -> Self {
         let data = vec![
             data0.bind(device, B::get_qualifiers()),
-            data1.bind(device, B::get_qualifiers()),
+            data1.bind(device, C::get_qualifiers()),
         ];
 
-        let bind_group = create_bind_group(device, &data);
+        let piped = data.iter().map(|_| Default::default()).collect();
 
         BindGroup2 {
             typ1: PhantomData,
             typ2: PhantomData,
+            device: device.clone(),
             data,
-            bind_group,
+            bind_groups: Default::default(),
+            piped,
         }
     }
     pub fn get_buffers(&self) -> Vec<Option<Rc<wgpu::Buffer>>> {
@@ -742,38 +1081,48 @@ pub struct BindGroup3<B: WgpuType, C: WgpuType, D: WgpuType> {
     typ1: PhantomData<B>,
     typ2: PhantomData<C>,
     typ3: PhantomData<D>,
+    device: Rc<wgpu::Device>,
     data: Vec<BoundData>,
-    bind_group: wgpu::BindGroup,
+    bind_groups: BindGroups,
+    piped: Vec<PipedBindGroups>,
 }
 
 impl<'a, B: WgpuType, C: WgpuType, D: WgpuType> BindGroup3<B, C, D> {
-    pub fn get_bind_group(&'a self) -> &'a wgpu::BindGroup {
-        &self.bind_group
+    pub fn get_bind_group(&'a self, visibility: wgpu::ShaderStage) -> &'a wgpu::BindGroup {
+        get_or_create_bind_group(&self.bind_groups, &self.device, &self.data, visibility)
+    }
+
+    // Each entry on its own, which the Context keeps for the outs it pipes into the next program
+    pub fn piped_0(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 0)
+    }
+    pub fn piped_1(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 1)
+    }
+    pub fn piped_2(&'a self) -> PipeSource<'a> {
+        pipe_source(&self.device, &self.data, &self.piped, 2)
     }
 
-    pub fn get_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    pub fn get_layout(
+        device: &wgpu::Device,
+        visibility: wgpu::ShaderStage,
+    ) -> wgpu::BindGroupLayout {
         let bind_entry_vec = vec![
             wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+                visibility,
                 ty: B::create_binding_type(),
                 count: None,
             },
             wgpu::BindGroupLayoutEntry {
                 binding: 1,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+                visibility,
                 ty: C::create_binding_type(),
                 count: None,
             },
             wgpu::BindGroupLayoutEntry {
                 binding: 2,
-                visibility: wgpu::ShaderStage::VERTEX
-                    | wgpu::ShaderStage::FRAGMENT
-                    | wgpu::ShaderStage::COMPUTE, // TODO I've made uniforms visible to both stages even if they are only used in one. Find out if this is a bad thing
+                visibility,
                 ty: D::create_binding_type(),
                 count: None,
             },
@@ -789,21 +1138,23 @@ impl<'a, B: WgpuType, C: WgpuType, D: WgpuType> BindGroup3<B, C, D> {
     }
 
     /// Initializes data on the device and returns it as a group
-    pub fn new(device: &wgpu::Device, data0: &B, data1: &C, data2: &D) -> Self {
+    pub fn new(device: &Rc<wgpu::Device>, data0: &B, data1: &C, data2: &D) -> Self {
         let data = vec![
             data0.bind(device, B::get_qualifiers()),
-            data1.bind(device, B::get_qualifiers()),
-            data2.bind(device, B::get_qualifiers()),
+            data1.bind(device, C::get_qualifiers()),
+            data2.bind(device, D::get_qualifiers()),
         ];
 
-        let bind_group = create_bind_group(device, &data);
+        let piped = data.iter().map(|_| Default::default()).collect();
 
         BindGroup3 {
             typ1: PhantomData,
             typ2: PhantomData,
             typ3: PhantomData,
+            device: device.clone(),
             data,
-            bind_group,
+            bind_groups: Default::default(),
+            piped,
         }
     }
     pub fn get_buffers(&self) -> Vec<Option<Rc<wgpu::Buffer>>> {
@@ -899,7 +1250,6 @@ where
     }
 }
 
-
 impl<'a, const BINDINGTYPE: wgpu::BufferBindingType, T, R> BindGroup2<R, BufferData<BINDINGTYPE, T>>
 where
     BufferData<BINDINGTYPE, T>: WgpuType,
@@ -931,3 +1281,54 @@ where
         )
     }
 }
+
+// Storage images are read back a texel at a time as the texel type of the image
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+    > BindGroup1<StorageTextureData<ACCESS, VIEWDIMENSION, T>>
+{
+    pub fn setup_read_0(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> MyImageView<T> {
+        let (texture, size) = self.data.get(0).unwrap().get_image().unwrap();
+        MyImageView::new(device, encoder, texture, size)
+    }
+}
+
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+        R: WgpuType,
+    > BindGroup2<StorageTextureData<ACCESS, VIEWDIMENSION, T>, R>
+{
+    pub fn setup_read_0(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> MyImageView<T> {
+        let (texture, size) = self.data.get(0).unwrap().get_image().unwrap();
+        MyImageView::new(device, encoder, texture, size)
+    }
+}
+
+impl<
+        const ACCESS: wgpu::StorageTextureAccess,
+        const VIEWDIMENSION: wgpu::TextureViewDimension,
+        T: Texel,
+        R: WgpuType,
+    > BindGroup2<R, StorageTextureData<ACCESS, VIEWDIMENSION, T>>
+{
+    pub fn setup_read_1(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> MyImageView<T> {
+        let (texture, size) = self.data.get(1).unwrap().get_image().unwrap();
+        MyImageView::new(device, encoder, texture, size)
+    }
+}
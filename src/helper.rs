@@ -122,7 +122,6 @@ macro_rules! mx_correction {
     };
 }
 
-
 pub fn generate_light_projection(pos: [f32; 4], fov: f32) -> cgmath::Matrix4<f32> {
     use cgmath::{Deg, EuclideanSpace, Matrix4, PerspectiveFov, Point3, Vector3};
     let mx_view = Matrix4::look_at_rh(
@@ -213,7 +212,7 @@ pub fn rotate_vec4(start: &[[f32; 4]], delta_y: f32) -> Vec<[f32; 4]> {
     vec![[temp_vec3.x, temp_vec3.y, temp_vec3.z, temp_vec3.w]]
 }
 
-/// For some examples, a list of example texels are needed to create a texture on the standard cube. 
+/// For some examples, a list of example texels are needed to create a texture on the standard cube.
 pub fn create_texels(size: usize) -> Vec<u8> {
     use std::iter;
 
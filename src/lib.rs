@@ -6,23 +6,37 @@ use wgpu::BufferSlice;
 #[macro_use]
 pub mod debug;
 pub mod bind;
+pub use wgpu_common::builtins;
 pub mod helper;
-pub mod shared;
 pub mod read;
+pub mod shared;
 pub mod write;
 
 pub mod wgpu_compute_header;
 pub mod wgpu_graphics_header;
 
 // Traits for the proc macros
-pub trait AbstractBind {
+pub trait AbstractBind: for<'a> Keep<'a> {
     fn new() -> Self;
 }
 
+// What a Context keeps for each out of a parameter, which is the entry to pipe once the parameter is Bound
+pub trait Keep<'a> {
+    type Kept: Copy;
+}
+
 pub struct Bound {}
 
 pub struct Unbound {}
 
+impl<'a> Keep<'a> for Bound {
+    type Kept = bind::PipeSource<'a>;
+}
+
+impl<'a> Keep<'a> for Unbound {
+    type Kept = ();
+}
+
 impl AbstractBind for Bound {
     fn new() -> Self {
         Bound {}
@@ -39,6 +53,22 @@ pub trait ContextInputs {
     fn inputs(&self) -> Vec<String>;
 }
 
+// A letter of the name of a parameter, which is a tuple of them at the type level, so `in1` is `(Char<'i'>, Char<'n'>, Char<'1'>)`
+pub struct Char<const C: char>;
+
+// Implemented by a Context for each parameter that is alone in its group, with `N` the name of the parameter,
+// so an out of the same name from the program before can be bound to it if it is data `D` that pipes into its type
+pub trait PipeIn<'a, T: RuntimePass<'a>, N, D> {
+    type Output;
+    fn pipe_in(self, rpass: &mut T, data: bind::Piped<'a, D>) -> Self::Output;
+}
+
+// Implemented by the pass of each program, so that the next program can continue it with its own pipeline
+pub trait ProgramPass<'a> {
+    type Pass;
+    fn into_pass(self) -> Self::Pass;
+}
+
 pub trait RuntimePass<'a> {
     fn set_bind_group(
         &mut self,
@@ -48,6 +78,8 @@ pub trait RuntimePass<'a> {
     );
 
     fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: BufferSlice<'a>);
+
+    fn set_push_constants(&mut self, offset: u32, data: &[u8]);
 }
 
 impl<'a> RuntimePass<'a> for wgpu::RenderPass<'a> {
@@ -63,6 +95,15 @@ impl<'a> RuntimePass<'a> for wgpu::RenderPass<'a> {
     fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: BufferSlice<'a>) {
         self.set_vertex_buffer(slot, buffer_slice)
     }
+
+    // Both stages declare every push constant
+    fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+        self.set_push_constants(
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            offset,
+            data,
+        )
+    }
 }
 impl<'a> RuntimePass<'a> for wgpu::ComputePass<'a> {
     fn set_bind_group(
@@ -78,6 +119,10 @@ impl<'a> RuntimePass<'a> for wgpu::ComputePass<'a> {
     fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: BufferSlice<'a>) {
         self.set_vertex_buffer(slot, buffer_slice)
     }
+
+    fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+        self.set_push_constants(offset, data)
+    }
 }
 
 #[macro_export]
@@ -96,5 +141,10 @@ macro_rules! eager_graphics_shader {
 
 #[macro_export]
 macro_rules! eager_binding {
-    ($context_name:tt = $($macro_name:tt!()),*) => {eager! { lazy! { wgpu_macros::generic_bindings! { $context_name = eager!{ $($macro_name!()),*}}}}}
+    ($context_name:ident = $($macro_name:tt!()),*) => {eager! { lazy! { wgpu_macros::generic_bindings! { $context_name = eager!{ $($macro_name!()),*}}}}}
+}
+
+#[macro_export]
+macro_rules! binding_context {
+    ($vis:vis $context_name:ident = $($macro_name:tt!()),*) => {eager! { lazy! { wgpu_macros::generic_bindings! { $vis $context_name = eager!{ $($macro_name!()),*}}}}}
 }
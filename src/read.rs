@@ -1,3 +1,6 @@
+use crate::bind::Texel;
+use std::marker::PhantomData;
+
 pub struct MyBufferView {
     read_buffer: wgpu::Buffer,
     bounds: std::ops::Range<u64>,
@@ -10,7 +13,12 @@ impl Drop for MyBufferView {
 }
 
 impl<'a> MyBufferView {
-    pub fn new(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, src: &wgpu::Buffer, bounds: std::ops::Range<u64>) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        src: &wgpu::Buffer,
+        bounds: std::ops::Range<u64>,
+    ) -> Self {
         assert!(!bounds.is_empty());
 
         let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -30,11 +38,14 @@ impl<'a> MyBufferView {
 
         MyBufferView {
             read_buffer,
-            bounds
+            bounds,
         }
     }
 
-    pub async fn read(&'a self, device: &wgpu::Device) -> Result<wgpu::BufferView<'a>, &'static str>{
+    pub async fn read(
+        &'a self,
+        device: &wgpu::Device,
+    ) -> Result<wgpu::BufferView<'a>, &'static str> {
         let buffer_slice = self
             .read_buffer
             .slice(0..(self.bounds.end - self.bounds.start));
@@ -49,3 +60,82 @@ impl<'a> MyBufferView {
         }
     }
 }
+
+/// A copy of a storage image that is read back as its texels, row by row
+pub struct MyImageView<T: Texel> {
+    read_buffer: wgpu::Buffer,
+    size: wgpu::Extent3d,
+    // Each row of the copy is padded out to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`
+    padded_row: u32,
+    texel: PhantomData<T>,
+}
+
+impl<T: Texel> MyImageView<T> {
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        src: &wgpu::Texture,
+        size: wgpu::Extent3d,
+    ) -> Self {
+        let row = size.width * std::mem::size_of::<T>() as u32;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = (row + align - 1) / align * align;
+
+        let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("image_reading_buffer"),
+            size: (padded_row * size.height * size.depth) as u64,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: src,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &read_buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: padded_row,
+                    rows_per_image: size.height,
+                },
+            },
+            size,
+        );
+
+        MyImageView {
+            read_buffer,
+            size,
+            padded_row,
+            texel: PhantomData,
+        }
+    }
+
+    pub async fn read(&self, device: &wgpu::Device) -> Result<Vec<T>, &'static str> {
+        let buffer_slice = self.read_buffer.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+
+        if buffer_future.await.is_err() {
+            return Err("failed to read image on gpu!");
+        }
+        let texels = {
+            let bytes = buffer_slice.get_mapped_range();
+            let row = self.size.width as usize * std::mem::size_of::<T>();
+            bytes
+                .chunks_exact(self.padded_row as usize)
+                .flat_map(|r| r[..row].chunks_exact(std::mem::size_of::<T>()))
+                // The mapped range and the padded rows are aligned for any texel
+                .map(|t| {
+                    zerocopy::LayoutVerified::<_, T>::new(t)
+                        .map(|v| *v)
+                        .unwrap()
+                })
+                .collect()
+        };
+        self.read_buffer.unmap();
+        Ok(texels)
+    }
+}
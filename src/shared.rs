@@ -1,91 +1,1004 @@
-use glsl_to_spirv::ShaderType;
+use crate::builtins::{find_builtin, stage_inputs, Stage};
 use regex::Regex;
+use std::borrow::Cow;
 use std::fmt;
-use std::io::Read;
 use wgpu::ShaderModule;
 
+// The body of a shader or library as it was written
+#[derive(Debug, Clone, Copy)]
+pub enum ShaderBody {
+    // `{{ void main() { ... } }}` which `stringify!` turned into a string
+    Tokens(&'static str),
+    // `{r#"void main() { ... }"#}` with each included snippet between `#snippet` and `#end_snippet` lines
+    Source(&'static str),
+}
+
 // Remove spaces between tokens that should be one token
 // Strip off the starting and ending { }
-pub fn process_body(body: &str) -> String {
-    let plus = Regex::new(r"\+(\n| )*\+").unwrap();
-    //println!("{:?}", body);
-    let in_progress = body.strip_prefix("{").unwrap().strip_suffix("}").unwrap();
-    plus.replace_all(in_progress, "++").into_owned()
+pub fn process_body(body: ShaderBody) -> String {
+    match body {
+        ShaderBody::Tokens(body) => {
+            let plus = Regex::new(r"\+(\n| )*\+").unwrap();
+            //println!("{:?}", body);
+            let in_progress = body.strip_prefix("{").unwrap().strip_suffix("}").unwrap();
+            plus.replace_all(in_progress, "++").into_owned()
+        }
+        ShaderBody::Source(source) => include_snippets_once(source),
+    }
+}
+
+// Keep the first copy of each snippet and drop the others, so snippets can include each other
+fn include_snippets_once(source: &str) -> String {
+    let mut included = Vec::new();
+    // The snippet being dropped, if any
+    let mut skipping: Option<&str> = None;
+    let mut result = String::new();
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix("#snippet ") {
+            if skipping.is_none() {
+                if included.contains(&name) {
+                    skipping = Some(name);
+                } else {
+                    included.push(name);
+                }
+            }
+        } else if let Some(name) = trimmed.strip_prefix("#end_snippet ") {
+            if skipping == Some(name) {
+                skipping = None;
+            }
+        } else if skipping.is_none() {
+            result.push_str(line);
+        }
+    }
+    result
+}
+
+// The generated source of a shader along with where each of its lines came from
+#[derive(Default)]
+pub struct ShaderSource {
+    pub source: String,
+    // For each line before the body, the parameter it declares if any
+    header: Vec<Option<String>>,
+}
+
+impl ShaderSource {
+    // Lines that are generated but don't belong to any parameter like `#version 450`
+    pub fn push(&mut self, text: &str) {
+        self.push_lines(text, None)
+    }
+
+    // Lines that are generated from the declaration of a parameter
+    pub fn declare(&mut self, param: &str, text: &str) {
+        self.push_lines(text, Some(param))
+    }
+
+    fn push_lines(&mut self, text: &str, param: Option<&str>) {
+        self.source.push_str(text);
+        for _ in text.matches('\n') {
+            self.header.push(param.map(|p| p.to_string()));
+        }
+    }
+
+    // The body always goes last so everything after the header lines is the user's
+    pub fn body(mut self, body: &str) -> Self {
+        self.source.push_str(body);
+        self
+    }
+
+    // Take a line(0 indexed) of the generated source and find the parameter or body line it came from
+    fn locate(&self, line: usize) -> (Option<String>, Option<usize>) {
+        match self.header.get(line) {
+            Some(param) => (param.clone(), None),
+            None => (None, Some(line - self.header.len() + 1)),
+        }
+    }
+}
+
+/// A shader that failed to compile.
+/// Positions are given relative to the body of the shader and not the generated source.
+#[derive(Debug, Clone)]
+pub struct ShaderCompileError {
+    pub stage: Stage,
+    pub message: String,
+    /// The line(1 indexed) of the body the error is on
+    pub line: Option<usize>,
+    /// The parameter whose generated declaration the error is in
+    pub parameter: Option<String>,
+}
+
+// The name glslang gives the generated source in its messages
+const SOURCE_NAME: &str = "shader";
+
+impl ShaderCompileError {
+    fn new(stage: Stage, error: shaderc::Error, source: &ShaderSource) -> Self {
+        let log = match error {
+            shaderc::Error::CompilationError(_, log) => log,
+            e => e.to_string(),
+        };
+        // glslang reports each error on a line of `shader:<line>: error: <message>`, the first one is where it stopped
+        let first = log.lines().find_map(|l| {
+            let (line, message) = l
+                .strip_prefix(SOURCE_NAME)?
+                .strip_prefix(':')?
+                .split_once(": error: ")?;
+            Some((line.parse::<usize>().ok()?, message))
+        });
+        match first {
+            Some((line, message)) => {
+                let (parameter, line) = source.locate(line - 1);
+                ShaderCompileError {
+                    stage,
+                    message: message.trim().to_string(),
+                    line,
+                    parameter,
+                }
+            }
+            None => ShaderCompileError {
+                stage,
+                message: log.trim().to_string(),
+                line: None,
+                parameter: None,
+            },
+        }
+    }
 }
 
-// Read in a given file that should be a certain shader type and create a shader module out of it
-pub fn compile_shader(contents: String, shader: ShaderType, device: &wgpu::Device) -> ShaderModule {
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} shader failed to compile", self.stage)?;
+        match (&self.parameter, self.line) {
+            (Some(p), _) => write!(f, " in the declaration of parameter `{}`", p)?,
+            (None, Some(l)) => write!(f, " at line {} of the body", l)?,
+            _ => {}
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ShaderCompileError {}
+
+// Take a shader written in GLSL and create a shader module out of it, its entry point is `main`
+pub fn compile_shader(
+    contents: ShaderSource,
+    stage: Stage,
+    device: &wgpu::Device,
+) -> Result<ShaderModule, ShaderCompileError> {
+    let kind = match stage {
+        Stage::Vertex => shaderc::ShaderKind::Vertex,
+        Stage::Fragment => shaderc::ShaderKind::Fragment,
+        Stage::Compute => shaderc::ShaderKind::Compute,
+    };
     // Convert our shader(in GLSL) to SPIR-V format
     // https://en.wikipedia.org/wiki/Standard_Portable_Intermediate_Representation
-    /*         print!("{}", contents);
-    print!("\n\n"); */
-    let x = glsl_to_spirv::compile(&contents, shader);
-    //debug!(x);
-    let mut vert_file =
-        x.unwrap_or_else(|_| panic!("{}: {}", "You gave a bad shader source", contents));
-    let mut vs = Vec::new();
-    vert_file
-        .read_to_end(&mut vs)
-        .expect("Somehow reading the file got interrupted");
+    let mut compiler = shaderc::Compiler::new().ok_or_else(|| ShaderCompileError {
+        stage,
+        message: "Failed to create the shaderc compiler".to_string(),
+        line: None,
+        parameter: None,
+    })?;
+    let spv = compiler
+        .compile_into_spirv(&contents.source, kind, SOURCE_NAME, "main", None)
+        .map_err(|e| ShaderCompileError::new(stage, e, &contents))?;
     // Take the shader, ...,  and return
+    Ok(device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::SpirV(Cow::Borrowed(spv.as_binary())),
+        flags: wgpu::ShaderFlags::VALIDATION,
+    }))
+}
+
+// Take a shader written in WGSL and create a shader module out of it
+pub fn compile_wgsl_shader(contents: String, device: &wgpu::Device) -> ShaderModule {
     device.create_shader_module(&wgpu::ShaderModuleDescriptor {
         label: None,
-        source: wgpu::util::make_spirv(&vs[..]),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(contents)),
         flags: wgpu::ShaderFlags::VALIDATION,
     })
 }
 
+/// Which shading language the declarations are generated in.
+/// The body of each shader needs to be written in the same language.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderBackend {
+    /// `#version 450` GLSL which is compiled to SPIR-V
+    Glsl,
+    /// WGSL which is handed straight to wgpu
+    Wgsl,
+}
+
+impl Default for ShaderBackend {
+    fn default() -> Self {
+        ShaderBackend::Glsl
+    }
+}
+
+// WGSL has no `void main()` convention so the entry point attribute is attached to the user's `fn main`
+pub fn wgsl_entry_point(body: &str, attribute: &str) -> String {
+    let main = Regex::new(r"fn(\n| )+main(\n| )*\(").unwrap();
+    main.replace(body, format!("{}\nfn main(", attribute).as_str())
+        .into_owned()
+}
+
+// GLSL is always entered at `main`, so a shader that wraps the body renames the `void main()` of the body
+pub fn rename_main(body: &str, name: &str) -> String {
+    let main = Regex::new(r"void(\n| )+main(\n| )*\(").unwrap();
+    main.replace(body, format!("void {}(", name).as_str())
+        .into_owned()
+}
+
+// The WGSL builtin and its type that stands in for a GLSL `gl_` variable
+pub fn wgsl_builtin(p: &str) -> Option<(&'static str, &'static str)> {
+    find_builtin(p).and_then(|b| b.wgsl)
+}
+
+// GLSL makes the inputs of a stage available without declaring them. WGSL does not so declare the ones the body uses.
+pub fn wgsl_builtin_inputs(body: &str, stage: Stage) -> String {
+    stage_inputs(stage)
+        .filter(|b| body.contains(b.name))
+        .filter_map(|b| {
+            let (builtin, wtype) = b.wgsl?;
+            Some(format!(
+                "[[builtin({})]] var<in> {} : {};\n",
+                builtin, b.name, wtype
+            ))
+        })
+        .collect()
+}
+
 //todo phase out glsltype infavor of WgpuType
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum GLSLTYPE {
+    // Scalars
     Bool,
     Int,
     Uint,
     Float,
+    Double,
     Vec1,
-    Vec2,
+    // Vectors
+    Bvec2,
+    Bvec3,
+    Bvec4,
+    Ivec2,
+    Ivec3,
+    Ivec4,
+    Uvec2,
     Uvec3,
+    Uvec4,
+    Vec2,
     Vec3,
     Vec4,
+    Dvec2,
+    Dvec3,
+    Dvec4,
+    // Matrices with `cols`x`rows`
+    Mat2,
+    Mat2x3,
+    Mat2x4,
+    Mat3x2,
+    Mat3,
+    Mat3x4,
+    Mat4x2,
+    Mat4x3,
     Mat4,
+    Dmat2,
+    Dmat2x3,
+    Dmat2x4,
+    Dmat3x2,
+    Dmat3,
+    Dmat3x4,
+    Dmat4x2,
+    Dmat4x3,
+    Dmat4,
+    // Runtime sized arrays
+    ArrayBool,
     ArrayInt,
     ArrayUint,
     ArrayFloat,
+    ArrayDouble,
+    ArrayBvec2,
+    ArrayBvec3,
+    ArrayBvec4,
+    ArrayIvec2,
+    ArrayIvec3,
+    ArrayIvec4,
+    ArrayUvec2,
+    ArrayUvec3,
+    ArrayUvec4,
     ArrayVec2,
     ArrayVec3,
     ArrayVec4,
+    ArrayDvec2,
+    ArrayDvec3,
+    ArrayDvec4,
     Sampler,
     SamplerShadow,
     TextureCube,
     Texture2D,
     Texture2DArray,
+    // Storage images that a compute shader reads and writes with `imageLoad` and `imageStore`
+    Image2D,
+    Image3D,
+    Uimage2D,
+    Struct(&'static StructDescription),
+    ArrayStruct(&'static StructDescription),
+}
+
+/// The type of each component of a scalar, vector or matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    Double,
+}
+
+impl Scalar {
+    // Bools take up as much space as a uint in a block
+    pub fn size(&self) -> usize {
+        match self {
+            Scalar::Double => 8,
+            _ => 4,
+        }
+    }
+
+    pub fn to_wgsl(&self) -> &'static str {
+        match self {
+            Scalar::Bool => "bool",
+            Scalar::Int => "i32",
+            Scalar::Uint => "u32",
+            Scalar::Float => "f32",
+            Scalar::Double => "f64",
+        }
+    }
+}
+
+/// Describes a Rust struct that derives `WgpuStruct` so it can be declared in GLSL.
+#[derive(Debug, PartialEq)]
+pub struct StructDescription {
+    pub name: &'static str,
+    pub fields: &'static [(&'static str, GLSLTYPE)],
+}
+
+/// The rules for how members are laid out in a uniform(std140) or buffer(std430) block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockLayout {
+    Std140,
+    Std430,
+}
+
+pub(crate) fn round_up(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
+
+// The offset of each member of a block or struct from its start
+fn member_offsets<'a>(
+    gtypes: impl Iterator<Item = &'a GLSLTYPE>,
+    layout: BlockLayout,
+) -> Vec<usize> {
+    let mut offset = 0;
+    gtypes
+        .map(|gtype| {
+            let (align, size) = gtype.align_and_size(layout);
+            let member_offset = round_up(offset, align);
+            offset = member_offset + size;
+            member_offset
+        })
+        .collect()
+}
+
+impl StructDescription {
+    /// The offset of each field from the start of the struct
+    pub fn offsets(&self, layout: BlockLayout) -> Vec<usize> {
+        member_offsets(self.fields.iter().map(|(_, gtype)| gtype), layout)
+    }
+
+    pub fn align(&self, layout: BlockLayout) -> usize {
+        let align = self
+            .fields
+            .iter()
+            .map(|(_, gtype)| gtype.align_and_size(layout).0)
+            .max()
+            .unwrap_or(4);
+        // std140 rounds the alignment of structs up to that of a vec4
+        match layout {
+            BlockLayout::Std140 => round_up(align, 16),
+            BlockLayout::Std430 => align,
+        }
+    }
+
+    /// The size of the struct including the padding at the end, this is also the stride of an array of them
+    pub fn size(&self, layout: BlockLayout) -> usize {
+        let end = match (self.fields.last(), self.offsets(layout).last()) {
+            (Some((_, gtype)), Some(offset)) => offset + gtype.align_and_size(layout).1,
+            _ => 0,
+        };
+        round_up(end, self.align(layout))
+    }
+
+    fn declaration(&self) -> String {
+        format!(
+            "struct {} {{\n{}}};\n",
+            self.name,
+            self.fields
+                .iter()
+                .map(|(name, gtype)| format!("\t{} {};\n", gtype, name))
+                .collect::<String>()
+        )
+    }
+
+    fn wgsl_declaration(&self) -> String {
+        format!(
+            "struct {} {{\n{}}};\n",
+            self.name,
+            self.fields
+                .iter()
+                .map(|(name, gtype)| format!("\t{} : {};\n", name, gtype.to_wgsl()))
+                .collect::<String>()
+        )
+    }
+}
+
+// Find the structs used by a type with the structs they contain coming first
+fn collect_structs(gtype: &GLSLTYPE, structs: &mut Vec<&'static StructDescription>) {
+    if let GLSLTYPE::Struct(s) | GLSLTYPE::ArrayStruct(s) = gtype {
+        for (_, field) in s.fields {
+            collect_structs(field, structs);
+        }
+        if !structs.iter().any(|x| x.name == s.name) {
+            structs.push(s);
+        }
+    }
+}
+
+// The declarations of every struct the parameters need, each struct is declared once
+pub fn struct_declarations<'a>(
+    gtypes: impl Iterator<Item = &'a GLSLTYPE>,
+    backend: ShaderBackend,
+) -> String {
+    let mut structs = Vec::new();
+    gtypes.for_each(|gtype| collect_structs(gtype, &mut structs));
+    structs
+        .iter()
+        .map(|s| match backend {
+            ShaderBackend::Glsl => s.declaration(),
+            ShaderBackend::Wgsl => s.wgsl_declaration(),
+        })
+        .collect()
+}
+
+// Push constants are not part of a bind group. Every stage declares all of the program's push constants in one block
+// in the order they are first declared so that the offsets match the ones the Context sets them at.
+pub fn push_constants<'a>(params: impl Iterator<Item = &'a PARAMETER>) -> Vec<&'a PARAMETER> {
+    let mut push: Vec<&PARAMETER> = Vec::new();
+    for p in params.filter(|p| p.qual.contains(&QUALIFIER::PUSH)) {
+        if !push.iter().any(|x| x.name == p.name) {
+            push.push(p);
+        }
+    }
+    push
+}
+
+/// The offset of each push constant in the block, which is laid out like a buffer.
+pub fn push_constant_offsets(gtypes: &[GLSLTYPE]) -> Vec<usize> {
+    member_offsets(gtypes.iter(), BlockLayout::Std430)
+}
+
+pub fn push_constant_declaration(push: &[&PARAMETER]) -> String {
+    if push.is_empty() {
+        return String::new();
+    }
+    format!(
+        "layout(push_constant) uniform PUSH_CONSTANTS {{\n{}}};\n",
+        push.iter()
+            .map(|p| format!("\t{} {};\n", p.gtype, p.name))
+            .collect::<String>()
+    )
+}
+
+// WGSL only allows a single push constant variable so the body would need to name the block
+pub fn wgsl_push_constants(push: &[&PARAMETER], stage: Stage) -> Result<(), ShaderCompileError> {
+    match push.first() {
+        Some(p) => Err(ShaderCompileError {
+            stage,
+            message: format!(
+                "Push constants are not supported by the WGSL backend, declare {} as a uniform instead",
+                p.name
+            ),
+            line: None,
+            parameter: Some(p.name.to_string()),
+        }),
+        None => Ok(()),
+    }
+}
+
+// One range covers every push constant for all of the stages that declare them
+pub fn push_constant_ranges(
+    push: &[&PARAMETER],
+    stages: wgpu::ShaderStage,
+) -> Vec<wgpu::PushConstantRange> {
+    let gtypes: Vec<GLSLTYPE> = push.iter().map(|p| p.gtype.clone()).collect();
+    match (gtypes.last(), push_constant_offsets(&gtypes).last()) {
+        (Some(gtype), Some(offset)) => vec![wgpu::PushConstantRange {
+            stages,
+            range: 0..round_up(offset + gtype.align_and_size(BlockLayout::Std430).1, 4) as u32,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+// The interpolation qualifiers of a varying, like `flat ` to go before the `in` or `out` in GLSL
+// or `, interpolate(flat)` to go in the attributes in WGSL
+pub fn interpolation(qual: &[QUALIFIER], backend: ShaderBackend) -> String {
+    qual.iter()
+        .filter_map(|q| match (q, backend) {
+            (QUALIFIER::FLAT, _) => Some("flat"),
+            (QUALIFIER::NOPERSPECTIVE, ShaderBackend::Glsl) => Some("noperspective"),
+            (QUALIFIER::NOPERSPECTIVE, ShaderBackend::Wgsl) => Some("linear"),
+            (QUALIFIER::CENTROID, _) => Some("centroid"),
+            (QUALIFIER::SAMPLE, _) => Some("sample"),
+            _ => None,
+        })
+        .map(|q| match backend {
+            ShaderBackend::Glsl => format!("{} ", q),
+            ShaderBackend::Wgsl => format!(", interpolate({})", q),
+        })
+        .collect()
+}
+
+/// The texel format of a storage image, given as a qualifier like `[group1 [out rgba8] image2D] img;`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Rgba8,
+    Rgba32f,
+    R32f,
+    Rgba32ui,
+    R32ui,
+}
+
+impl ImageFormat {
+    pub fn to_glsl(&self) -> &'static str {
+        match self {
+            ImageFormat::Rgba8 => "rgba8",
+            ImageFormat::Rgba32f => "rgba32f",
+            ImageFormat::R32f => "r32f",
+            ImageFormat::Rgba32ui => "rgba32ui",
+            ImageFormat::R32ui => "r32ui",
+        }
+    }
+
+    pub fn to_wgsl(&self) -> &'static str {
+        match self {
+            ImageFormat::Rgba8 => "rgba8unorm",
+            ImageFormat::Rgba32f => "rgba32float",
+            ImageFormat::R32f => "r32float",
+            ImageFormat::Rgba32ui => "rgba32uint",
+            ImageFormat::R32ui => "r32uint",
+        }
+    }
 }
 
 impl GLSLTYPE {
-    pub fn size_of(&self) -> usize {
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self,
+            GLSLTYPE::Image2D | GLSLTYPE::Image3D | GLSLTYPE::Uimage2D
+        )
+    }
+}
+
+// The format qualifier of an image or the default for its type when it doesn't have one
+pub fn image_format(qual: &[QUALIFIER], gtype: &GLSLTYPE) -> ImageFormat {
+    qual.iter()
+        .find_map(|q| match q {
+            QUALIFIER::FORMAT(f) => Some(*f),
+            _ => None,
+        })
+        .unwrap_or(match gtype {
+            GLSLTYPE::Uimage2D => ImageFormat::R32ui,
+            _ => ImageFormat::Rgba32f,
+        })
+}
+
+// A storage buffer that is only `in` is read only, the Context binds it with `read_only: true` so the layout agrees.
+// A binding layout has no write only storage buffers, so one that is only `out` is declared to be read and written.
+pub fn buffer_access(qual: &[QUALIFIER], backend: ShaderBackend) -> &'static str {
+    let (read, write) = (
+        qual.contains(&QUALIFIER::IN),
+        qual.contains(&QUALIFIER::OUT),
+    );
+    match backend {
+        ShaderBackend::Glsl => match (read, write) {
+            (true, false) => "readonly ",
+            _ => "",
+        },
+        // WGSL storage buffers are only read or read and written
+        ShaderBackend::Wgsl => match (read, write) {
+            (true, false) => "[[access(read)]] ",
+            _ => "[[access(read_write)]] ",
+        },
+    }
+}
+
+// An image that is only `in` is read only and one that is only `out` is write only
+pub fn image_declaration(
+    name: &str,
+    group_number: u32,
+    binding_number: u32,
+    gtype: &GLSLTYPE,
+    qual: &[QUALIFIER],
+    backend: ShaderBackend,
+) -> String {
+    let format = image_format(qual, gtype);
+    let (read, write) = (
+        qual.contains(&QUALIFIER::IN),
+        qual.contains(&QUALIFIER::OUT),
+    );
+    match backend {
+        ShaderBackend::Glsl => format!(
+            "layout(set = {}, binding = {}, {}) uniform {}{} {};\n",
+            group_number,
+            binding_number,
+            format.to_glsl(),
+            match (read, write) {
+                (true, false) => "readonly ",
+                (false, true) => "writeonly ",
+                _ => "",
+            },
+            gtype,
+            name
+        ),
+        ShaderBackend::Wgsl => format!(
+            "[[group({}), binding({})]] var {} : [[access({})]] {}<{}>;\n",
+            group_number,
+            binding_number,
+            name,
+            match (read, write) {
+                (true, false) => "read",
+                (false, true) => "write",
+                _ => panic!(
+                    "WGSL storage textures are either read or written, {} needs to be split in two",
+                    name
+                ),
+            },
+            match gtype {
+                GLSLTYPE::Image3D => "texture_storage_3d",
+                _ => "texture_storage_2d",
+            },
+            format.to_wgsl()
+        ),
+    }
+}
+
+/// The value of a `const` parameter.
+/// The Context creates these with `constants` so each value has the Rust type of its parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Bool(bool),
+    Int(i32),
+    Uint(u32),
+    Float(f32),
+    Double(f64),
+}
+
+impl ConstValue {
+    fn gtype(&self) -> GLSLTYPE {
         match self {
-            GLSLTYPE::Bool => std::mem::size_of::<bool>(),
-            GLSLTYPE::Float => std::mem::size_of::<f32>(),
-            GLSLTYPE::Int => std::mem::size_of::<i32>(),
-            GLSLTYPE::Uint => std::mem::size_of::<u32>(),
-            GLSLTYPE::Vec1 => std::mem::size_of::<f32>(),
-            GLSLTYPE::Vec2 => std::mem::size_of::<[f32; 2]>(),
-            GLSLTYPE::Uvec3 => std::mem::size_of::<[u32; 3]>(),
-            GLSLTYPE::Vec3 => std::mem::size_of::<[f32; 4]>(),
-            GLSLTYPE::Vec4 => std::mem::size_of::<[f32; 4]>(),
-            GLSLTYPE::Mat4 => 64,
-            GLSLTYPE::ArrayInt => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::ArrayUint => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::ArrayFloat => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::ArrayVec2 => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::ArrayVec3 => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::ArrayVec4 => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::Sampler => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::SamplerShadow => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::TextureCube => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::Texture2D => panic!("TODO: I haven't checked the size of this yet"),
-            GLSLTYPE::Texture2DArray => panic!("TODO: I haven't checked the size of this yet"),
+            ConstValue::Bool(_) => GLSLTYPE::Bool,
+            ConstValue::Int(_) => GLSLTYPE::Int,
+            ConstValue::Uint(_) => GLSLTYPE::Uint,
+            ConstValue::Float(_) => GLSLTYPE::Float,
+            ConstValue::Double(_) => GLSLTYPE::Double,
+        }
+    }
+
+    // Floats are printed with `{:?}` so that whole numbers keep their `.0`
+    fn literal(&self, backend: ShaderBackend) -> String {
+        match (self, backend) {
+            (ConstValue::Bool(b), _) => b.to_string(),
+            (ConstValue::Int(i), _) => i.to_string(),
+            (ConstValue::Uint(u), _) => format!("{}u", u),
+            (ConstValue::Float(f), _) => format!("{:?}", f),
+            (ConstValue::Double(d), ShaderBackend::Glsl) => format!("{:?}lf", d),
+            (ConstValue::Double(d), ShaderBackend::Wgsl) => format!("{:?}", d),
+        }
+    }
+}
+
+impl From<bool> for ConstValue {
+    fn from(b: bool) -> Self {
+        ConstValue::Bool(b)
+    }
+}
+
+impl From<i32> for ConstValue {
+    fn from(i: i32) -> Self {
+        ConstValue::Int(i)
+    }
+}
+
+impl From<u32> for ConstValue {
+    fn from(u: u32) -> Self {
+        ConstValue::Uint(u)
+    }
+}
+
+impl From<f32> for ConstValue {
+    fn from(f: f32) -> Self {
+        ConstValue::Float(f)
+    }
+}
+
+impl From<f64> for ConstValue {
+    fn from(d: f64) -> Self {
+        ConstValue::Double(d)
+    }
+}
+
+// A `const` parameter is declared as a constant in the generated source with the value given when compiling.
+// wgpu can not override specialization constants yet so the value is part of the source.
+pub fn constant_declaration(
+    p: &PARAMETER,
+    constants: &[(&str, ConstValue)],
+    backend: ShaderBackend,
+    stage: Stage,
+) -> Result<String, ShaderCompileError> {
+    let err = |message: String| ShaderCompileError {
+        stage,
+        message,
+        line: None,
+        parameter: Some(p.name.to_string()),
+    };
+    let value = match constants.iter().find(|(name, _)| *name == p.name) {
+        Some((_, v)) => v,
+        None => {
+            return Err(err(format!(
+                "No value was given for the const parameter {}, use the Context's `constants` to create them",
+                p.name
+            )))
+        }
+    };
+    if value.gtype() != p.gtype {
+        return Err(err(format!(
+            "The const parameter {} is a {} but was given {:?}",
+            p.name, p.gtype, value
+        )));
+    }
+    Ok(match backend {
+        ShaderBackend::Glsl => format!(
+            "const {} {} = {};\n",
+            p.gtype,
+            p.name,
+            value.literal(backend)
+        ),
+        ShaderBackend::Wgsl => format!(
+            "const {} : {} = {};\n",
+            p.name,
+            p.gtype.to_wgsl(),
+            value.literal(backend)
+        ),
+    })
+}
+
+// A size written as a number or the name of an integer `const` parameter
+pub fn const_size(n: &str, constants: &[(&str, ConstValue)]) -> Result<u32, ShaderCompileError> {
+    match n.parse() {
+        Ok(n) => Ok(n),
+        Err(_) => match constants.iter().find(|(name, _)| *name == n) {
+            Some((_, ConstValue::Uint(u))) => Ok(*u),
+            Some((_, ConstValue::Int(i))) => Ok(*i as u32),
+            // Only compute shaders have sizes
+            _ => Err(ShaderCompileError {
+                stage: Stage::Compute,
+                message: format!(
+                    "The size {} is not a number or an integer const parameter with a value, use the Context's `constants` to create them",
+                    n
+                ),
+                line: None,
+                parameter: Some(n.to_string()),
+            }),
+        },
+    }
+}
+
+// A `shared` array is declared with its length resolved so WGSL sees a number
+pub fn shared_declaration(
+    p: &PARAMETER,
+    constants: &[(&str, ConstValue)],
+    backend: ShaderBackend,
+) -> Result<String, ShaderCompileError> {
+    let element = p
+        .gtype
+        .element()
+        .unwrap_or_else(|| panic!("The shared parameter {} needs to be an array", p.name));
+    let len = const_size(
+        p.len
+            .unwrap_or_else(|| panic!("The shared array {} needs a length", p.name)),
+        constants,
+    )?;
+    Ok(match backend {
+        ShaderBackend::Glsl => format!("shared {} {}[{}];\n", element, p.name, len),
+        ShaderBackend::Wgsl => format!(
+            "var<workgroup> {} : array<{}, {}>;\n",
+            p.name,
+            element.to_wgsl(),
+            len
+        ),
+    })
+}
+
+impl GLSLTYPE {
+    // The scalar type, columns and rows of scalars, vectors and matrices
+    pub fn shape(&self) -> Option<(Scalar, usize, usize)> {
+        match self {
+            GLSLTYPE::Bool => Some((Scalar::Bool, 1, 1)),
+            GLSLTYPE::Int => Some((Scalar::Int, 1, 1)),
+            GLSLTYPE::Uint => Some((Scalar::Uint, 1, 1)),
+            GLSLTYPE::Float => Some((Scalar::Float, 1, 1)),
+            GLSLTYPE::Double => Some((Scalar::Double, 1, 1)),
+            GLSLTYPE::Vec1 => Some((Scalar::Float, 1, 1)),
+            GLSLTYPE::Bvec2 => Some((Scalar::Bool, 1, 2)),
+            GLSLTYPE::Bvec3 => Some((Scalar::Bool, 1, 3)),
+            GLSLTYPE::Bvec4 => Some((Scalar::Bool, 1, 4)),
+            GLSLTYPE::Ivec2 => Some((Scalar::Int, 1, 2)),
+            GLSLTYPE::Ivec3 => Some((Scalar::Int, 1, 3)),
+            GLSLTYPE::Ivec4 => Some((Scalar::Int, 1, 4)),
+            GLSLTYPE::Uvec2 => Some((Scalar::Uint, 1, 2)),
+            GLSLTYPE::Uvec3 => Some((Scalar::Uint, 1, 3)),
+            GLSLTYPE::Uvec4 => Some((Scalar::Uint, 1, 4)),
+            GLSLTYPE::Vec2 => Some((Scalar::Float, 1, 2)),
+            GLSLTYPE::Vec3 => Some((Scalar::Float, 1, 3)),
+            GLSLTYPE::Vec4 => Some((Scalar::Float, 1, 4)),
+            GLSLTYPE::Dvec2 => Some((Scalar::Double, 1, 2)),
+            GLSLTYPE::Dvec3 => Some((Scalar::Double, 1, 3)),
+            GLSLTYPE::Dvec4 => Some((Scalar::Double, 1, 4)),
+            GLSLTYPE::Mat2 => Some((Scalar::Float, 2, 2)),
+            GLSLTYPE::Mat2x3 => Some((Scalar::Float, 2, 3)),
+            GLSLTYPE::Mat2x4 => Some((Scalar::Float, 2, 4)),
+            GLSLTYPE::Mat3x2 => Some((Scalar::Float, 3, 2)),
+            GLSLTYPE::Mat3 => Some((Scalar::Float, 3, 3)),
+            GLSLTYPE::Mat3x4 => Some((Scalar::Float, 3, 4)),
+            GLSLTYPE::Mat4x2 => Some((Scalar::Float, 4, 2)),
+            GLSLTYPE::Mat4x3 => Some((Scalar::Float, 4, 3)),
+            GLSLTYPE::Mat4 => Some((Scalar::Float, 4, 4)),
+            GLSLTYPE::Dmat2 => Some((Scalar::Double, 2, 2)),
+            GLSLTYPE::Dmat2x3 => Some((Scalar::Double, 2, 3)),
+            GLSLTYPE::Dmat2x4 => Some((Scalar::Double, 2, 4)),
+            GLSLTYPE::Dmat3x2 => Some((Scalar::Double, 3, 2)),
+            GLSLTYPE::Dmat3 => Some((Scalar::Double, 3, 3)),
+            GLSLTYPE::Dmat3x4 => Some((Scalar::Double, 3, 4)),
+            GLSLTYPE::Dmat4x2 => Some((Scalar::Double, 4, 2)),
+            GLSLTYPE::Dmat4x3 => Some((Scalar::Double, 4, 3)),
+            GLSLTYPE::Dmat4 => Some((Scalar::Double, 4, 4)),
+            _ => None,
+        }
+    }
+
+    // The type of each element of an array
+    pub fn element(&self) -> Option<GLSLTYPE> {
+        match self {
+            GLSLTYPE::ArrayBool => Some(GLSLTYPE::Bool),
+            GLSLTYPE::ArrayInt => Some(GLSLTYPE::Int),
+            GLSLTYPE::ArrayUint => Some(GLSLTYPE::Uint),
+            GLSLTYPE::ArrayFloat => Some(GLSLTYPE::Float),
+            GLSLTYPE::ArrayDouble => Some(GLSLTYPE::Double),
+            GLSLTYPE::ArrayBvec2 => Some(GLSLTYPE::Bvec2),
+            GLSLTYPE::ArrayBvec3 => Some(GLSLTYPE::Bvec3),
+            GLSLTYPE::ArrayBvec4 => Some(GLSLTYPE::Bvec4),
+            GLSLTYPE::ArrayIvec2 => Some(GLSLTYPE::Ivec2),
+            GLSLTYPE::ArrayIvec3 => Some(GLSLTYPE::Ivec3),
+            GLSLTYPE::ArrayIvec4 => Some(GLSLTYPE::Ivec4),
+            GLSLTYPE::ArrayUvec2 => Some(GLSLTYPE::Uvec2),
+            GLSLTYPE::ArrayUvec3 => Some(GLSLTYPE::Uvec3),
+            GLSLTYPE::ArrayUvec4 => Some(GLSLTYPE::Uvec4),
+            GLSLTYPE::ArrayVec2 => Some(GLSLTYPE::Vec2),
+            GLSLTYPE::ArrayVec3 => Some(GLSLTYPE::Vec3),
+            GLSLTYPE::ArrayVec4 => Some(GLSLTYPE::Vec4),
+            GLSLTYPE::ArrayDvec2 => Some(GLSLTYPE::Dvec2),
+            GLSLTYPE::ArrayDvec3 => Some(GLSLTYPE::Dvec3),
+            GLSLTYPE::ArrayDvec4 => Some(GLSLTYPE::Dvec4),
+            GLSLTYPE::ArrayStruct(s) => Some(GLSLTYPE::Struct(s)),
+            _ => None,
+        }
+    }
+
+    // The base alignment and size of a member of a block
+    pub fn align_and_size(&self, layout: BlockLayout) -> (usize, usize) {
+        match (self.shape(), self) {
+            (Some((scalar, cols, rows)), _) => {
+                let size = scalar.size();
+                // vec3's are aligned like vec4's
+                let align = match rows {
+                    1 => size,
+                    2 => 2 * size,
+                    _ => 4 * size,
+                };
+                if cols == 1 {
+                    (align, rows * size)
+                } else {
+                    // Matrices are laid out like an array of their column vectors
+                    let column = match layout {
+                        BlockLayout::Std140 => round_up(align, 16),
+                        BlockLayout::Std430 => align,
+                    };
+                    (column, cols * column)
+                }
+            }
+            (None, GLSLTYPE::Struct(s)) => (s.align(layout), s.size(layout)),
+            _ => panic!("{} can not be a member of a struct", self),
+        }
+    }
+
+    // The distance between each element of an array of this type
+    pub fn array_stride(&self, layout: BlockLayout) -> usize {
+        let (align, size) = self.align_and_size(layout);
+        match layout {
+            BlockLayout::Std140 => round_up(round_up(size, align), 16),
+            BlockLayout::Std430 => round_up(size, align),
+        }
+    }
+
+    pub fn size_of(&self) -> usize {
+        match (self.shape(), self.element()) {
+            (Some(_), _) => self.array_stride(BlockLayout::Std430),
+            (None, Some(element)) => element.size_of(),
+            (None, None) => match self {
+                GLSLTYPE::Struct(s) => s.size(BlockLayout::Std430),
+                _ => panic!("TODO: I haven't checked the size of this yet"),
+            },
+        }
+    }
+
+    // The format of a vertex attribute of this type, vec3's are padded out to a vec4 so the stride comes from `size_of`
+    pub fn vertex_format(&self) -> wgpu::VertexFormat {
+        match self.shape() {
+            Some((Scalar::Float, 1, 1)) => wgpu::VertexFormat::Float,
+            Some((Scalar::Float, 1, 2)) => wgpu::VertexFormat::Float2,
+            Some((Scalar::Float, 1, 3)) => wgpu::VertexFormat::Float3,
+            Some((Scalar::Float, 1, 4)) => wgpu::VertexFormat::Float4,
+            Some((Scalar::Int, 1, 1)) => wgpu::VertexFormat::Int,
+            Some((Scalar::Int, 1, 2)) => wgpu::VertexFormat::Int2,
+            Some((Scalar::Int, 1, 3)) => wgpu::VertexFormat::Int3,
+            Some((Scalar::Int, 1, 4)) => wgpu::VertexFormat::Int4,
+            Some((Scalar::Uint, 1, 1)) => wgpu::VertexFormat::Uint,
+            Some((Scalar::Uint, 1, 2)) => wgpu::VertexFormat::Uint2,
+            Some((Scalar::Uint, 1, 3)) => wgpu::VertexFormat::Uint3,
+            Some((Scalar::Uint, 1, 4)) => wgpu::VertexFormat::Uint4,
+            // Needs `Features::VERTEX_ATTRIBUTE_64BIT`
+            Some((Scalar::Double, 1, 1)) => wgpu::VertexFormat::Double,
+            Some((Scalar::Double, 1, 2)) => wgpu::VertexFormat::Double2,
+            Some((Scalar::Double, 1, 3)) => wgpu::VertexFormat::Double3,
+            Some((Scalar::Double, 1, 4)) => wgpu::VertexFormat::Double4,
+            // Bools and matrices are rejected by `my_shader!`
+            _ => panic!("{} can not be used as a vertex attribute", self),
+        }
+    }
+
+    // Uniforms and buffers are wrapped in a `[[block]]` struct in WGSL so arrays need an explicit stride
+    pub fn to_wgsl(&self) -> String {
+        match (self.shape(), self.element()) {
+            (Some((scalar, 1, 1)), _) => scalar.to_wgsl().to_string(),
+            (Some((scalar, 1, rows)), _) => format!("vec{}<{}>", rows, scalar.to_wgsl()),
+            (Some((scalar, cols, rows)), _) => {
+                format!("mat{}x{}<{}>", cols, rows, scalar.to_wgsl())
+            }
+            (None, Some(element)) => format!(
+                "[[stride({})]] array<{}>",
+                element.array_stride(BlockLayout::Std430),
+                element.to_wgsl()
+            ),
+            (None, None) => match self {
+                GLSLTYPE::Sampler => "sampler".to_string(),
+                GLSLTYPE::SamplerShadow => "sampler_comparison".to_string(),
+                GLSLTYPE::TextureCube => "texture_cube<f32>".to_string(),
+                GLSLTYPE::Texture2D => "texture_2d<f32>".to_string(),
+                GLSLTYPE::Texture2DArray => "texture_2d_array<f32>".to_string(),
+                GLSLTYPE::Struct(s) => s.name.to_string(),
+                _ => unreachable!(),
+            },
         }
     }
 }
@@ -94,27 +1007,75 @@ impl fmt::Display for GLSLTYPE {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             GLSLTYPE::Bool => write!(f, "bool"),
-            GLSLTYPE::Float => write!(f, "float"),
             GLSLTYPE::Int => write!(f, "int"),
             GLSLTYPE::Uint => write!(f, "uint"),
+            GLSLTYPE::Float => write!(f, "float"),
+            GLSLTYPE::Double => write!(f, "double"),
             // we have this as a vec1 to know it is an array of floats but glsl only has float
             GLSLTYPE::Vec1 => write!(f, "float"),
-            GLSLTYPE::Vec2 => write!(f, "vec2"),
+            GLSLTYPE::Bvec2 => write!(f, "bvec2"),
+            GLSLTYPE::Bvec3 => write!(f, "bvec3"),
+            GLSLTYPE::Bvec4 => write!(f, "bvec4"),
+            GLSLTYPE::Ivec2 => write!(f, "ivec2"),
+            GLSLTYPE::Ivec3 => write!(f, "ivec3"),
+            GLSLTYPE::Ivec4 => write!(f, "ivec4"),
+            GLSLTYPE::Uvec2 => write!(f, "uvec2"),
             GLSLTYPE::Uvec3 => write!(f, "uvec3"),
+            GLSLTYPE::Uvec4 => write!(f, "uvec4"),
+            GLSLTYPE::Vec2 => write!(f, "vec2"),
             GLSLTYPE::Vec3 => write!(f, "vec3"),
             GLSLTYPE::Vec4 => write!(f, "vec4"),
+            GLSLTYPE::Dvec2 => write!(f, "dvec2"),
+            GLSLTYPE::Dvec3 => write!(f, "dvec3"),
+            GLSLTYPE::Dvec4 => write!(f, "dvec4"),
+            GLSLTYPE::Mat2 => write!(f, "mat2"),
+            GLSLTYPE::Mat2x3 => write!(f, "mat2x3"),
+            GLSLTYPE::Mat2x4 => write!(f, "mat2x4"),
+            GLSLTYPE::Mat3x2 => write!(f, "mat3x2"),
+            GLSLTYPE::Mat3 => write!(f, "mat3"),
+            GLSLTYPE::Mat3x4 => write!(f, "mat3x4"),
+            GLSLTYPE::Mat4x2 => write!(f, "mat4x2"),
+            GLSLTYPE::Mat4x3 => write!(f, "mat4x3"),
             GLSLTYPE::Mat4 => write!(f, "mat4"),
+            GLSLTYPE::Dmat2 => write!(f, "dmat2"),
+            GLSLTYPE::Dmat2x3 => write!(f, "dmat2x3"),
+            GLSLTYPE::Dmat2x4 => write!(f, "dmat2x4"),
+            GLSLTYPE::Dmat3x2 => write!(f, "dmat3x2"),
+            GLSLTYPE::Dmat3 => write!(f, "dmat3"),
+            GLSLTYPE::Dmat3x4 => write!(f, "dmat3x4"),
+            GLSLTYPE::Dmat4x2 => write!(f, "dmat4x2"),
+            GLSLTYPE::Dmat4x3 => write!(f, "dmat4x3"),
+            GLSLTYPE::Dmat4 => write!(f, "dmat4"),
+            GLSLTYPE::ArrayBool => write!(f, "bool[]"),
             GLSLTYPE::ArrayInt => write!(f, "int[]"),
             GLSLTYPE::ArrayUint => write!(f, "uint[]"),
             GLSLTYPE::ArrayFloat => write!(f, "float[]"),
+            GLSLTYPE::ArrayDouble => write!(f, "double[]"),
+            GLSLTYPE::ArrayBvec2 => write!(f, "bvec2[]"),
+            GLSLTYPE::ArrayBvec3 => write!(f, "bvec3[]"),
+            GLSLTYPE::ArrayBvec4 => write!(f, "bvec4[]"),
+            GLSLTYPE::ArrayIvec2 => write!(f, "ivec2[]"),
+            GLSLTYPE::ArrayIvec3 => write!(f, "ivec3[]"),
+            GLSLTYPE::ArrayIvec4 => write!(f, "ivec4[]"),
+            GLSLTYPE::ArrayUvec2 => write!(f, "uvec2[]"),
+            GLSLTYPE::ArrayUvec3 => write!(f, "uvec3[]"),
+            GLSLTYPE::ArrayUvec4 => write!(f, "uvec4[]"),
             GLSLTYPE::ArrayVec2 => write!(f, "vec2[]"),
             GLSLTYPE::ArrayVec3 => write!(f, "vec3[]"),
             GLSLTYPE::ArrayVec4 => write!(f, "vec4[]"),
+            GLSLTYPE::ArrayDvec2 => write!(f, "dvec2[]"),
+            GLSLTYPE::ArrayDvec3 => write!(f, "dvec3[]"),
+            GLSLTYPE::ArrayDvec4 => write!(f, "dvec4[]"),
             GLSLTYPE::Sampler => write!(f, "sampler"),
             GLSLTYPE::SamplerShadow => write!(f, "samplerShadow"),
             GLSLTYPE::TextureCube => write!(f, "textureCube"),
             GLSLTYPE::Texture2D => write!(f, "texture2D"),
             GLSLTYPE::Texture2DArray => write!(f, "texture2DArray"),
+            GLSLTYPE::Image2D => write!(f, "image2D"),
+            GLSLTYPE::Image3D => write!(f, "image3D"),
+            GLSLTYPE::Uimage2D => write!(f, "uimage2D"),
+            GLSLTYPE::Struct(s) => write!(f, "{}", s.name),
+            GLSLTYPE::ArrayStruct(s) => write!(f, "{}[]", s.name),
         }
     }
 }
@@ -124,33 +1085,138 @@ macro_rules! typing {
     (bool) => {
         pipeline::shared::GLSLTYPE::Bool
     };
-    (uint) => {
-        pipeline::shared::GLSLTYPE::Uint
-    };
     (int) => {
         pipeline::shared::GLSLTYPE::Int
     };
+    (uint) => {
+        pipeline::shared::GLSLTYPE::Uint
+    };
     (float) => {
         pipeline::shared::GLSLTYPE::Float
     };
     (vec1) => {
         pipeline::shared::GLSLTYPE::Vec1
     };
-    (vec2) => {
-        pipeline::shared::GLSLTYPE::Vec2
+    (double) => {
+        pipeline::shared::GLSLTYPE::Double
+    };
+    (bvec2) => {
+        pipeline::shared::GLSLTYPE::Bvec2
+    };
+    (bvec3) => {
+        pipeline::shared::GLSLTYPE::Bvec3
+    };
+    (bvec4) => {
+        pipeline::shared::GLSLTYPE::Bvec4
+    };
+    (ivec2) => {
+        pipeline::shared::GLSLTYPE::Ivec2
+    };
+    (ivec3) => {
+        pipeline::shared::GLSLTYPE::Ivec3
+    };
+    (ivec4) => {
+        pipeline::shared::GLSLTYPE::Ivec4
+    };
+    (uvec2) => {
+        pipeline::shared::GLSLTYPE::Uvec2
     };
     (uvec3) => {
         pipeline::shared::GLSLTYPE::Uvec3
     };
+    (uvec4) => {
+        pipeline::shared::GLSLTYPE::Uvec4
+    };
+    (vec2) => {
+        pipeline::shared::GLSLTYPE::Vec2
+    };
     (vec3) => {
         pipeline::shared::GLSLTYPE::Vec3
     };
     (vec4) => {
         pipeline::shared::GLSLTYPE::Vec4
     };
+    (dvec2) => {
+        pipeline::shared::GLSLTYPE::Dvec2
+    };
+    (dvec3) => {
+        pipeline::shared::GLSLTYPE::Dvec3
+    };
+    (dvec4) => {
+        pipeline::shared::GLSLTYPE::Dvec4
+    };
+    (mat2) => {
+        pipeline::shared::GLSLTYPE::Mat2
+    };
+    (mat2x3) => {
+        pipeline::shared::GLSLTYPE::Mat2x3
+    };
+    (mat2x4) => {
+        pipeline::shared::GLSLTYPE::Mat2x4
+    };
+    (mat3x2) => {
+        pipeline::shared::GLSLTYPE::Mat3x2
+    };
+    (mat3) => {
+        pipeline::shared::GLSLTYPE::Mat3
+    };
+    (mat3x4) => {
+        pipeline::shared::GLSLTYPE::Mat3x4
+    };
+    (mat4x2) => {
+        pipeline::shared::GLSLTYPE::Mat4x2
+    };
+    (mat4x3) => {
+        pipeline::shared::GLSLTYPE::Mat4x3
+    };
     (mat4) => {
         pipeline::shared::GLSLTYPE::Mat4
     };
+    (dmat2) => {
+        pipeline::shared::GLSLTYPE::Dmat2
+    };
+    (dmat2x3) => {
+        pipeline::shared::GLSLTYPE::Dmat2x3
+    };
+    (dmat2x4) => {
+        pipeline::shared::GLSLTYPE::Dmat2x4
+    };
+    (dmat3x2) => {
+        pipeline::shared::GLSLTYPE::Dmat3x2
+    };
+    (dmat3) => {
+        pipeline::shared::GLSLTYPE::Dmat3
+    };
+    (dmat3x4) => {
+        pipeline::shared::GLSLTYPE::Dmat3x4
+    };
+    (dmat4x2) => {
+        pipeline::shared::GLSLTYPE::Dmat4x2
+    };
+    (dmat4x3) => {
+        pipeline::shared::GLSLTYPE::Dmat4x3
+    };
+    (dmat4) => {
+        pipeline::shared::GLSLTYPE::Dmat4
+    };
+    (mat2x2) => {
+        pipeline::shared::GLSLTYPE::Mat2
+    };
+    (mat3x3) => {
+        pipeline::shared::GLSLTYPE::Mat3
+    };
+    (mat4x4) => {
+        pipeline::shared::GLSLTYPE::Mat4
+    };
+    (dmat2x2) => {
+        pipeline::shared::GLSLTYPE::Dmat2
+    };
+    (dmat3x3) => {
+        pipeline::shared::GLSLTYPE::Dmat3
+    };
+    (dmat4x4) => {
+        pipeline::shared::GLSLTYPE::Dmat4
+    };
     (sampler) => {
         pipeline::shared::GLSLTYPE::Sampler
     };
@@ -166,27 +1232,56 @@ macro_rules! typing {
     (texture2DArray) => {
         pipeline::shared::GLSLTYPE::Texture2DArray
     };
+    (image2D) => {
+        pipeline::shared::GLSLTYPE::Image2D
+    };
+    (image3D) => {
+        pipeline::shared::GLSLTYPE::Image3D
+    };
+    (uimage2D) => {
+        pipeline::shared::GLSLTYPE::Uimage2D
+    };
+    // Anything else should be a struct deriving `WgpuStruct`
+    ($struct_name:ident) => {
+        pipeline::shared::GLSLTYPE::Struct(
+            &<$struct_name as pipeline::bind::WgpuStruct>::DESCRIPTION,
+        )
+    };
 }
 
 //todo why do I have this again?
 pub const fn array_type(gtype: GLSLTYPE, depth: i64) -> GLSLTYPE {
     if depth == 1 {
         match gtype {
-            GLSLTYPE::Float => GLSLTYPE::ArrayFloat,
+            GLSLTYPE::Bool => GLSLTYPE::ArrayBool,
             GLSLTYPE::Int => GLSLTYPE::ArrayInt,
             GLSLTYPE::Uint => GLSLTYPE::ArrayUint,
+            GLSLTYPE::Float => GLSLTYPE::ArrayFloat,
+            GLSLTYPE::Double => GLSLTYPE::ArrayDouble,
+            GLSLTYPE::Bvec2 => GLSLTYPE::ArrayBvec2,
+            GLSLTYPE::Bvec3 => GLSLTYPE::ArrayBvec3,
+            GLSLTYPE::Bvec4 => GLSLTYPE::ArrayBvec4,
+            GLSLTYPE::Ivec2 => GLSLTYPE::ArrayIvec2,
+            GLSLTYPE::Ivec3 => GLSLTYPE::ArrayIvec3,
+            GLSLTYPE::Ivec4 => GLSLTYPE::ArrayIvec4,
+            GLSLTYPE::Uvec2 => GLSLTYPE::ArrayUvec2,
+            GLSLTYPE::Uvec3 => GLSLTYPE::ArrayUvec3,
+            GLSLTYPE::Uvec4 => GLSLTYPE::ArrayUvec4,
             GLSLTYPE::Vec1 => GLSLTYPE::ArrayVec2,
             GLSLTYPE::Vec2 => GLSLTYPE::ArrayVec2,
             GLSLTYPE::Vec3 => GLSLTYPE::ArrayVec3,
             GLSLTYPE::Vec4 => GLSLTYPE::ArrayVec4,
-            x =>
-            /* todo panic!("yikes") I want to panic but I can't as of the current nightly re;ease so we will just return itself*/
-            {
-                x
-            }
+            GLSLTYPE::Dvec2 => GLSLTYPE::ArrayDvec2,
+            GLSLTYPE::Dvec3 => GLSLTYPE::ArrayDvec3,
+            GLSLTYPE::Dvec4 => GLSLTYPE::ArrayDvec4,
+            GLSLTYPE::Struct(s) => GLSLTYPE::ArrayStruct(s),
+            // There are no runtime sized arrays of matrices, samplers, textures, images or arrays
+            _ => panic!("Only scalars, vectors and structs can be in an array"),
         }
-    } else {
+    } else if depth == 0 {
         gtype
+    } else {
+        panic!("Arrays can only have one dimension")
     }
 }
 
@@ -202,24 +1297,26 @@ pub enum QUALIFIER {
     LOOP,
     // things for samplers/textures
     COMPARE,
+    // set with `set_push_constants` instead of being part of a bind group
+    PUSH,
+    // a value given when the program is compiled
+    CONST,
+    // how a varying is interpolated between the vertex and fragment shader
+    FLAT,
+    NOPERSPECTIVE,
+    CENTROID,
+    SAMPLE,
+    // the texel format of a storage image
+    FORMAT(ImageFormat),
+    // memory shared by the invocations of a workgroup
+    SHARED,
 }
 
-// I assume there will only be one gl builtin qualifier so find that one and the match should return true
+// Whether the parameter is a builtin declared with the type the builtin has
 pub fn check_gl_builtin_type(p: &str, t: &GLSLTYPE) -> bool {
-    match (p, t) {
-        ("gl_VertexID", GLSLTYPE::Int)
-        | ("gl_InstanceID", GLSLTYPE::Int)
-        | ("gl_FragCoord", GLSLTYPE::Vec4)
-        | ("gl_FrontFacing", GLSLTYPE::Bool)
-        | ("gl_PointCoord", GLSLTYPE::Vec2)
-        | ("gl_SampleID", GLSLTYPE::Int)
-        | ("gl_SamplePosition", GLSLTYPE::Vec2)
-        | ("gl_NumWorkGroups", GLSLTYPE::Uvec3)
-        | ("gl_WorkGroupID", GLSLTYPE::Uvec3)
-        | ("gl_LocalInvocationID", GLSLTYPE::Uvec3)
-        | ("gl_GlobalInvocationID", GLSLTYPE::Uvec3)
-        | ("gl_LocalInvocationIndex", GLSLTYPE::Uint) => true,
-        _ => false,
+    match find_builtin(p) {
+        Some(b) => b.gtype == t.to_string(),
+        None => false,
     }
 }
 
@@ -240,18 +1337,7 @@ pub const fn string_compare(string1: &str, string2: &str) -> bool {
 }
 
 pub const fn is_gl_builtin(p: &str) -> bool {
-    string_compare(p, "gl_VertexID")
-        || string_compare(p, "gl_InstanceID")
-        || string_compare(p, "gl_FragCoord")
-        || string_compare(p, "gl_FrontFacing")
-        || string_compare(p, "gl_PointCoord")
-        || string_compare(p, "gl_SampleID")
-        || string_compare(p, "gl_SamplePosition")
-        || string_compare(p, "gl_NumWorkGroups")
-        || string_compare(p, "gl_WorkGroupID")
-        || string_compare(p, "gl_LocalInvocationID")
-        || string_compare(p, "gl_GlobalInvocationID")
-        || string_compare(p, "gl_LocalInvocationIndex")
+    find_builtin(p).is_some()
 }
 
 #[macro_export]
@@ -276,23 +1362,95 @@ macro_rules! qualifying {
     };
     (compare) => {
         pipeline::shared::QUALIFIER::COMPARE
+    };
+    (push) => {
+        pipeline::shared::QUALIFIER::PUSH
+    };
+    (const) => {
+        pipeline::shared::QUALIFIER::CONST
+    };
+    (flat) => {
+        pipeline::shared::QUALIFIER::FLAT
+    };
+    (noperspective) => {
+        pipeline::shared::QUALIFIER::NOPERSPECTIVE
+    };
+    (centroid) => {
+        pipeline::shared::QUALIFIER::CENTROID
+    };
+    (sample) => {
+        pipeline::shared::QUALIFIER::SAMPLE
+    };
+    (shared) => {
+        pipeline::shared::QUALIFIER::SHARED
+    };
+    (rgba8) => {
+        pipeline::shared::QUALIFIER::FORMAT(pipeline::shared::ImageFormat::Rgba8)
+    };
+    (rgba32f) => {
+        pipeline::shared::QUALIFIER::FORMAT(pipeline::shared::ImageFormat::Rgba32f)
+    };
+    (r32f) => {
+        pipeline::shared::QUALIFIER::FORMAT(pipeline::shared::ImageFormat::R32f)
+    };
+    (rgba32ui) => {
+        pipeline::shared::QUALIFIER::FORMAT(pipeline::shared::ImageFormat::Rgba32ui)
+    };
+    (r32ui) => {
+        pipeline::shared::QUALIFIER::FORMAT(pipeline::shared::ImageFormat::R32ui)
     }; //todo add all the others
 }
 
 #[macro_export]
 macro_rules! munch_body {
+        // A body given as a string is already the source once its snippets are included
+        ($body:literal) => {pipeline::shared::ShaderBody::Source(wgpu_macros::shader_source!($body))};
+        ($($token:tt)*) => {pipeline::shared::ShaderBody::Tokens(munch_tokens!($($token)*))};
+    }
+
+#[macro_export]
+macro_rules! munch_tokens {
         () => {};
         ($token:tt) => {stringify!($token)};
         ($token:tt $($rest:tt)*) =>
         {
-            concat!(stringify!($token), munch_body!($($rest)*))
+            concat!(stringify!($token), munch_tokens!($($rest)*))
         };
     }
 
+// `shader_snippet!{LIGHTING = r#"float diffuse(vec3 n) { ... }"#}` declares a snippet
+// that string bodies after it can pull in with an `#include LIGHTING` line
+#[macro_export]
+macro_rules! shader_snippet {
+    ($name:ident = $body:literal) => {
+        macro_rules! $name {
+            () => {
+                concat!(
+                    "#snippet ",
+                    stringify!($name),
+                    "\n",
+                    wgpu_macros::shader_source!($body),
+                    "\n#end_snippet ",
+                    stringify!($name),
+                    "\n"
+                )
+            };
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! count_brackets {
         () => {0};
         ([] $($rest:tt)*) => {1 + count_brackets!($($rest)*)};
+        ([$len:tt] $($rest:tt)*) => {1 + count_brackets!($($rest)*)};
+    }
+
+#[macro_export]
+macro_rules! array_len {
+        () => {None};
+        ([] $($rest:tt)*) => {array_len!($($rest)*)};
+        ([$len:tt] $($rest:tt)*) => {Some(stringify!($len))};
     }
 
 pub const fn has_in_qual(p: &[QUALIFIER]) -> bool {
@@ -346,6 +1504,143 @@ pub struct PARAMETER {
     pub qual: &'static [QUALIFIER],
     pub gtype: GLSLTYPE,
     pub name: &'static str,
+    // The length of a `shared` array, a number or the name of an integer `const` parameter
+    pub len: Option<&'static str>,
+}
+
+/// The numbers a Context gives its parameters, which the proc macro creates as a const.
+/// Programs are compiled with these numbers so the shaders declare each binding where the Context sets it.
+#[derive(Debug)]
+pub struct LayoutDescription {
+    pub bindings: &'static [BindingDescription],
+    // The location of each value passed into or out of a vertex or fragment shader
+    pub locations: &'static [LocationDescription],
+}
+
+#[derive(Debug)]
+pub struct BindingDescription {
+    pub name: &'static str,
+    pub group: u32,
+    pub binding: u32,
+    // The stages whose shaders declare the parameter
+    pub visibility: &'static [Stage],
+}
+
+#[derive(Debug)]
+pub struct LocationDescription {
+    pub name: &'static str,
+    pub stage: Stage,
+    // An out of the vertex shader has the same location as the in of the fragment shader it is passed to
+    pub is_out: bool,
+    pub location: u32,
+}
+
+impl LayoutDescription {
+    // The group and binding of a parameter that a shader of `stage` declares
+    pub fn binding(&self, name: &str, stage: Stage) -> (u32, u32) {
+        match self.bindings.iter().find(|b| b.name == name) {
+            Some(b) if b.visibility.contains(&stage) => (b.group, b.binding),
+            _ => panic!(
+                "The layout has no {:?} binding for `{}`, compile the program with the layout of the Context for its shaders",
+                stage, name
+            ),
+        }
+    }
+
+    // The location of an in or out of a shader of `stage`, the same name can be both an in and an out of a stage
+    pub fn location(&self, name: &str, stage: Stage, is_out: bool) -> u32 {
+        match self
+            .locations
+            .iter()
+            .find(|l| l.name == name && l.stage == stage && l.is_out == is_out)
+        {
+            Some(l) => l.location,
+            None => panic!(
+                "The layout has no {:?} {} location for `{}`, compile the program with the layout of the Context for its shaders",
+                stage,
+                if is_out { "out" } else { "in" },
+                name
+            ),
+        }
+    }
+
+    // A group is visible to the stages of the shaders that declare any of its parameters
+    pub fn visibility(&self, group: u32) -> wgpu::ShaderStage {
+        self.bindings
+            .iter()
+            .filter(|b| b.group == group)
+            .flat_map(|b| b.visibility)
+            .fold(wgpu::ShaderStage::NONE, |visibility, stage| {
+                visibility
+                    | match stage {
+                        Stage::Vertex => wgpu::ShaderStage::VERTEX,
+                        Stage::Fragment => wgpu::ShaderStage::FRAGMENT,
+                        Stage::Compute => wgpu::ShaderStage::COMPUTE,
+                    }
+            })
+    }
+}
+
+/// What a Context's `get_layout` returns, the layout of each of its bind groups and the numbers they are declared with
+pub struct ContextLayout {
+    pub bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    pub description: &'static LayoutDescription,
+}
+
+// Functions that shaders share by importing them with `use NAME;`, created with `shader_lib!`
+#[derive(Debug)]
+pub struct ShaderLib {
+    pub name: &'static str,
+    // The libraries whose functions these functions call
+    pub imports: &'static [&'static ShaderLib],
+    // The parameters these functions use, every shader that imports the library has to declare them
+    pub params: &'static [&'static str],
+    pub body: ShaderBody,
+}
+
+// Check that the shader declares every parameter that its libraries, and the libraries they import, use
+pub const fn valid_imports(params: &[PARAMETER], imports: &[&ShaderLib]) {
+    let mut acc = 0;
+    while acc < imports.len() {
+        let mut p = 0;
+        while p < imports[acc].params.len() {
+            let mut declared = false;
+            let mut i = 0;
+            while i < params.len() {
+                if string_compare(params[i].name, imports[acc].params[p]) {
+                    declared = true;
+                }
+                i += 1;
+            }
+            if !declared {
+                panic!("A library imported by this shader uses a parameter that the shader does not declare")
+            }
+            p += 1;
+        }
+        valid_imports(params, imports[acc].imports);
+        acc += 1;
+    }
+}
+
+// The source of every imported library with each library after the ones it imports and each one only once
+pub fn library_source(imports: &[&ShaderLib]) -> String {
+    fn add<'a>(lib: &'a ShaderLib, added: &mut Vec<&'a str>, source: &mut String) {
+        if added.contains(&lib.name) {
+            return;
+        }
+        added.push(lib.name);
+        for i in lib.imports {
+            add(i, added, source);
+        }
+        source.push_str(&process_body(lib.body));
+        source.push('\n');
+    }
+    let mut added = Vec::new();
+    let mut source = String::new();
+    for i in imports {
+        add(i, &mut added, &mut source);
+    }
+    source
 }
 
 // To help view macros
@@ -356,7 +1651,11 @@ pub struct PARAMETER {
 // https://doc.rust-lang.org/stable/rust-by-example/macros.html
 #[macro_export]
 macro_rules! shader {
-    ( $([$($group:ident)? [$($qualifier:tt)*] $type:ident $($brack:tt)*] $param:ident;)*
+    ( $(use $($lib:ident),+;)?
+      // The workgroup size of a compute shader, each a number or the name of an integer `const` parameter
+      $(workgroup_size($($size:tt),+);)?
+      // `@group(n) @binding(m)` and `@location(n)` are read by the Context, whose layout gives compile the numbers
+      $($(@$annotation:ident($number:literal))* [$($group:ident)? [$($qualifier:tt)*] $type:ident $($brack:tt)*] $param:ident;)*
       {$($tt:tt)*}) =>
       {
         {
@@ -365,10 +1664,16 @@ macro_rules! shader {
                                             gtype:pipeline::shared::array_type(typing!($type), count_brackets!($($brack)*)),
                                             name:stringify!($param),
                                             group:{let mut x : Option<&'static str> = None; $(x = Some(stringify!($group)); )? x},
+                                            len:array_len!($($brack)*),
                                         }),*];
 
 
-            const B: &'static str = munch_body!($($tt)*);
+            const B: pipeline::shared::ShaderBody = munch_body!($($tt)*);
+
+            const I: &[&pipeline::shared::ShaderLib] = &[$($(&$lib),+)?];
+            const _: () = pipeline::shared::valid_imports(S, I);
+
+            const W: &[&'static str] = &[$($(stringify!($size)),+)?];
 
             let mut INBINDCONTEXT  = [""; 32];
             let mut OUTBINDCONTEXT = [""; 32];
@@ -386,7 +1691,7 @@ macro_rules! shader {
                 }
                 acc += 1;
             }
-            (S, B)
+            (S, B, I, W)
         }
       };
     }
@@ -402,3 +1707,31 @@ macro_rules! my_shader {
             }
         }}
 }
+
+// `shader_lib!{LIGHTING = {use COMMON; u_light_dir; {{ float diffuse(vec3 n) {...} }}}}`
+// declares a library named LIGHTING that imports COMMON and uses the parameter `u_light_dir`
+#[macro_export]
+macro_rules! shader_lib {
+    ($vis:vis $name:ident = {use $($lib:ident),+; $($rest:tt)*}) => {
+        shader_lib!{@lib $vis $name [$($lib),+] $($rest)*}
+    };
+    ($vis:vis $name:ident = {$($rest:tt)*}) => {
+        shader_lib!{@lib $vis $name [] $($rest)*}
+    };
+    (@lib $vis:vis $name:ident [$($lib:ident),*] $($param:ident;)* {$($tt:tt)*}) => {
+        $vis const $name: pipeline::shared::ShaderLib = pipeline::shared::ShaderLib {
+            name: stringify!($name),
+            imports: &[$(&$lib),*],
+            params: &[$(stringify!($param)),*],
+            body: munch_body!($($tt)*),
+        };
+    };
+}
+
+// Like `my_shader!` with the parameters and body read from a file relative to Cargo.toml
+#[macro_export]
+macro_rules! shader_file {
+    ($name:tt = $path:literal) => {
+        wgpu_macros::shader_file! {$name = $path}
+    };
+}
@@ -1,13 +1,25 @@
-use glsl_to_spirv::ShaderType;
-
-use std::collections::HashMap;
-
-use crate::shared::{check_gl_builtin_type, compile_shader, process_body, PARAMETER, QUALIFIER};
+use crate::shared::{
+    buffer_access, check_gl_builtin_type, compile_shader, compile_wgsl_shader, const_size,
+    constant_declaration, image_declaration, library_source, process_body,
+    push_constant_declaration, push_constant_ranges, push_constants, rename_main,
+    shared_declaration, struct_declarations, wgsl_builtin_inputs, wgsl_entry_point,
+    wgsl_push_constants, BlockLayout, ConstValue, ContextLayout, LayoutDescription, ShaderBackend,
+    ShaderBody, ShaderCompileError, ShaderLib, ShaderSource, PARAMETER, QUALIFIER,
+};
 
 use crate::bind::DefaultBinding;
+use crate::builtins::{Stage, DISPATCH_SIZE};
+use regex::Regex;
+use wgpu::util::DeviceExt;
+use wgpu_common::limits::MAX_WORKGROUP_STORAGE_SIZE;
+use zerocopy::AsBytes as _;
 
 pub struct ComputeProgram {
     pub pipeline: wgpu::ComputePipeline,
+    // The size of each workgroup as declared by the shader
+    pub local_size: [u32; 3],
+    // The group the size of a dispatch is bound in, after the groups of the Context
+    pub dispatch_group: u32,
 }
 
 #[derive(Debug)]
@@ -20,16 +32,281 @@ pub struct OutComputeBindings {
     pub bindings: Vec<DefaultBinding>,
 }
 
+// The workgroup size declared with `workgroup_size(8, 8);`, any dimension that isn't declared is 1.
+// A dimension can also be the name of an integer `const` parameter.
+fn local_size(
+    s: &ComputeShader,
+    constants: &[(&str, ConstValue)],
+) -> Result<[u32; 3], ShaderCompileError> {
+    let mut size = [1; 3];
+    for (dim, n) in s.workgroup_size.iter().enumerate() {
+        size[dim] = const_size(n, constants)?;
+    }
+    Ok(size)
+}
+
+// The `shared` arrays can't take more workgroup memory than there is.
+// The macro checks this when every length is a number, the lengths that are constants are only known here.
+fn check_workgroup_storage_size(
+    s: &ComputeShader,
+    constants: &[(&str, ConstValue)],
+) -> Result<(), ShaderCompileError> {
+    let mut total = 0;
+    for p in shared_params(s) {
+        let len = const_size(p.len.unwrap(), constants)? as usize;
+        total += p.gtype.element().unwrap().array_stride(BlockLayout::Std430) * len;
+        if total > MAX_WORKGROUP_STORAGE_SIZE {
+            return Err(ShaderCompileError {
+                stage: Stage::Compute,
+                message: format!(
+                    "The shared arrays up to {} take {} bytes of workgroup memory but only {} can be used",
+                    p.name, total, MAX_WORKGROUP_STORAGE_SIZE
+                ),
+                line: None,
+                parameter: Some(p.name.to_string()),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn shared_params(s: &ComputeShader) -> impl Iterator<Item = &PARAMETER> {
+    s.params
+        .iter()
+        .filter(|p| p.qual.contains(&QUALIFIER::SHARED))
+}
+
+// The invocations of the last workgroup in a dimension can be past the size of the dispatch.
+// They return before the `main` of the body, which is renamed to this, so the body can index with gl_GlobalInvocationID as before.
+// A shader with `shared` arrays isn't guarded, as its barriers need every invocation of the workgroup to reach them.
+const UNGUARDED_MAIN: &str = "unguarded_main";
+
+fn is_guarded(s: &ComputeShader) -> bool {
+    shared_params(s).next().is_none()
+}
+
+// The size of the dispatch is bound in the group after the groups of the Context
+fn dispatch_size_declaration(group: u32, backend: ShaderBackend) -> String {
+    match backend {
+        ShaderBackend::Glsl => format!(
+            "layout(set = {}, binding = 0) uniform DISPATCH_SIZE {{\nuvec3 {};\n}};\n",
+            group, DISPATCH_SIZE
+        ),
+        ShaderBackend::Wgsl => format!(
+            "[[block]] struct DISPATCH_SIZE {{\n\tdata : vec3<u32>;\n}};\n[[group({}), binding(0)]] var<uniform> {} : DISPATCH_SIZE;\n",
+            group, DISPATCH_SIZE
+        ),
+    }
+}
+
+fn guarded_main() -> String {
+    format!(
+        "\nvoid main() {{\n    if (any(greaterThanEqual(gl_GlobalInvocationID, {}))) {{\n        return;\n    }}\n    {}();\n}}\n",
+        DISPATCH_SIZE, UNGUARDED_MAIN
+    )
+}
+
+// WGSL can't call a function that returns nothing as a statement, so the guard starts the `main` of the body instead
+fn guard_wgsl_main(body: &str) -> String {
+    let main = Regex::new(r"fn(\n| )+main(\n| )*\((\n| )*\)(\n| )*\{").unwrap();
+    main.replace(
+        body,
+        format!(
+            "fn main() {{\n    if (gl_GlobalInvocationID.x >= {0}.data.x || gl_GlobalInvocationID.y >= {0}.data.y || gl_GlobalInvocationID.z >= {0}.data.z) {{\n        return;\n    }}\n",
+            DISPATCH_SIZE
+        )
+        .as_str(),
+    )
+    .into_owned()
+}
+
 //todo unify this with the graphics version into shared
-fn stringify_shader(s: &ComputeShader, b: &ComputeBindings, b_out: &OutComputeBindings) -> String {
-    let mut buffer = Vec::new();
+fn stringify_shader(
+    s: &ComputeShader,
+    b: &ComputeBindings,
+    b_out: &OutComputeBindings,
+    constants: &[(&str, ConstValue)],
+    dispatch_group: u32,
+) -> Result<ShaderSource, ShaderCompileError> {
+    let mut source = ShaderSource::default();
+    source.push("#version 450\n");
+    let size = local_size(s, constants)?;
+    source.push(&format!(
+        "layout(local_size_x = {}, local_size_y = {}, local_size_z = {}) in;\n",
+        size[0], size[1], size[2]
+    ));
+    for p in s
+        .params
+        .iter()
+        .filter(|p| p.qual.contains(&QUALIFIER::CONST))
+    {
+        source.declare(
+            p.name,
+            &constant_declaration(p, constants, ShaderBackend::Glsl, Stage::Compute)?,
+        );
+    }
+    source.push(&struct_declarations(
+        b.bindings
+            .iter()
+            .chain(b_out.bindings.iter())
+            .map(|i| &i.gtype)
+            .chain(shared_params(s).map(|p| &p.gtype)),
+        ShaderBackend::Glsl,
+    ));
+    for p in shared_params(s) {
+        source.declare(
+            p.name,
+            &shared_declaration(p, constants, ShaderBackend::Glsl)?,
+        );
+    }
+    source.push(&push_constant_declaration(&push_constants(s.params.iter())));
+    source.push(&dispatch_size_declaration(
+        dispatch_group,
+        ShaderBackend::Glsl,
+    ));
     for i in &b.bindings[..] {
+        if i.gtype.is_image() {
+            source.declare(
+                &i.name,
+                &image_declaration(
+                    &i.name,
+                    i.group_number,
+                    i.binding_number,
+                    &i.gtype,
+                    &i.qual,
+                    ShaderBackend::Glsl,
+                ),
+            );
+            continue;
+        }
+        source.declare(
+            &i.name,
+            &format!(
+                "layout(set = {}, binding = {}) {}{} BINDINGS{}{} {{\n{} {};\n}};\n",
+                i.group_number,
+                i.binding_number,
+                if i.qual.contains(&QUALIFIER::BUFFER) {
+                    buffer_access(&i.qual, ShaderBackend::Glsl)
+                } else {
+                    ""
+                },
+                if i.qual.contains(&QUALIFIER::BUFFER) {
+                    "buffer"
+                } else if i.qual.contains(&QUALIFIER::UNIFORM) {
+                    "uniform"
+                } else {
+                    panic!(
+                        "You are trying to do something with something that isn't a buffer or uniform"
+                    )
+                },
+                i.group_number,
+                i.binding_number,
+                i.gtype,
+                i.name
+            ),
+        );
+    }
+    for i in &b_out.bindings[..] {
+        if i.gtype.is_image() {
+            source.declare(
+                &i.name,
+                &image_declaration(
+                    &i.name,
+                    i.group_number,
+                    i.binding_number,
+                    &i.gtype,
+                    &i.qual,
+                    ShaderBackend::Glsl,
+                ),
+            );
+        } else if i.qual.contains(&QUALIFIER::BUFFER) {
+            source.declare(
+                &i.name,
+                &format!(
+                    "layout(set = {}, binding = {}) {}buffer BINDINGS{}{} {{\n{} {};\n}};\n",
+                    i.group_number,
+                    i.binding_number,
+                    buffer_access(&i.qual, ShaderBackend::Glsl),
+                    i.group_number,
+                    i.binding_number,
+                    i.gtype,
+                    i.name
+                ),
+            );
+        }
+    }
+    source.push(&library_source(s.imports));
+    source.push("\n\n");
+    let body = process_body(s.body);
+    if is_guarded(s) {
+        Ok(source.body(&format!(
+            "{}\n{}",
+            rename_main(&body, UNGUARDED_MAIN),
+            guarded_main()
+        )))
+    } else {
+        Ok(source.body(&body))
+    }
+}
+
+// Each binding is wrapped in a `[[block]]` struct with a single `data` member so the body accesses `name.data`
+fn stringify_shader_wgsl(
+    s: &ComputeShader,
+    b: &ComputeBindings,
+    b_out: &OutComputeBindings,
+    constants: &[(&str, ConstValue)],
+    dispatch_group: u32,
+) -> Result<String, ShaderCompileError> {
+    let mut buffer = s
+        .params
+        .iter()
+        .filter(|p| p.qual.contains(&QUALIFIER::CONST))
+        .map(|p| constant_declaration(p, constants, ShaderBackend::Wgsl, Stage::Compute))
+        .collect::<Result<Vec<String>, ShaderCompileError>>()?;
+    buffer.push(struct_declarations(
+        b.bindings
+            .iter()
+            .chain(b_out.bindings.iter())
+            .map(|i| &i.gtype)
+            .chain(shared_params(s).map(|p| &p.gtype)),
+        ShaderBackend::Wgsl,
+    ));
+    for p in shared_params(s) {
+        buffer.push(shared_declaration(p, constants, ShaderBackend::Wgsl)?);
+    }
+    buffer.push(dispatch_size_declaration(
+        dispatch_group,
+        ShaderBackend::Wgsl,
+    ));
+    for i in b.bindings.iter().chain(
+        b_out
+            .bindings
+            .iter()
+            .filter(|i| i.qual.contains(&QUALIFIER::BUFFER) || i.gtype.is_image()),
+    ) {
+        if i.gtype.is_image() {
+            buffer.push(image_declaration(
+                &i.name,
+                i.group_number,
+                i.binding_number,
+                &i.gtype,
+                &i.qual,
+                ShaderBackend::Wgsl,
+            ));
+            continue;
+        }
         buffer.push(format!(
-            "layout(set = {}, binding = {}) {} BINDINGS{}{} {{\n",
+            "[[block]] struct BINDINGS{}{} {{\n\tdata : {};\n}};\n",
+            i.group_number,
+            i.binding_number,
+            i.gtype.to_wgsl()
+        ));
+        buffer.push(format!(
+            "[[group({}), binding({})]] var<{}> {} : {}BINDINGS{}{};\n",
             i.group_number,
             i.binding_number,
             if i.qual.contains(&QUALIFIER::BUFFER) {
-                "buffer"
+                "storage"
             } else if i.qual.contains(&QUALIFIER::UNIFORM) {
                 "uniform"
             } else {
@@ -37,55 +314,53 @@ fn stringify_shader(s: &ComputeShader, b: &ComputeBindings, b_out: &OutComputeBi
                     "You are trying to do something with something that isn't a buffer or uniform"
                 )
             },
+            i.name,
+            if i.qual.contains(&QUALIFIER::BUFFER) {
+                buffer_access(&i.qual, ShaderBackend::Wgsl)
+            } else {
+                ""
+            },
             i.group_number,
             i.binding_number
         ));
-
-        buffer.push(i.gtype.to_string() + " " + &i.name + ";\n");
-        buffer.push("};\n".to_string());
     }
-    for i in &b_out.bindings[..] {
-        if i.qual.contains(&QUALIFIER::BUFFER) {
-            buffer.push(format!(
-                "layout(set = {}, binding = {}) buffer BINDINGS{}{} {{\n",
-                i.group_number, i.binding_number, i.group_number, i.binding_number
-            ));
-
-            buffer.push(i.gtype.to_string() + " " + &i.name + ";\n");
-            buffer.push("};\n".to_string());
-        }
+    let mut body = process_body(s.body);
+    if is_guarded(s) {
+        body = guard_wgsl_main(&body);
     }
-    format!(
-        //todo figure out how to use a non-1 local size
-        "#version 450\nlayout(local_size_x = 1) in;\n{}\n\n{}\n",
+    let size = local_size(s, constants)?;
+    Ok(format!(
+        "{}\n{}\n\n{}{}\n",
         buffer.join(""),
-        process_body(s.body)
-    )
+        wgsl_builtin_inputs(&body, Stage::Compute),
+        library_source(s.imports),
+        wgsl_entry_point(
+            &body,
+            &format!(
+                "[[stage(compute), workgroup_size({}, {}, {})]]",
+                size[0], size[1], size[2]
+            )
+        )
+    ))
 }
 
-fn create_bindings(compute: &ComputeShader) -> (ComputeBindings, OutComputeBindings) {
+fn create_bindings(
+    compute: &ComputeShader,
+    layout: &LayoutDescription,
+) -> (ComputeBindings, OutComputeBindings) {
     let mut binding_struct = Vec::new();
     let mut out_binding_struct = Vec::new();
-    let mut group_map = HashMap::new();
-    let mut uniform_binding_number_map = HashMap::new();
-    let mut group_set_number = 0;
     for i in &compute.params[..] {
         // Bindings that are kept between runs
-        if !check_gl_builtin_type(i.name, &i.gtype) {
+        if !check_gl_builtin_type(i.name, &i.gtype)
+            && !i.qual.contains(&QUALIFIER::PUSH)
+            && !i.qual.contains(&QUALIFIER::CONST)
+            && !i.qual.contains(&QUALIFIER::SHARED)
+        {
             if i.qual.contains(&QUALIFIER::IN) && !i.qual.contains(&QUALIFIER::OUT) {
-                let group_number = match group_map.get(i.group.unwrap()) {
-                    Some(i) => *i,
-                    None => {
-                        let x = group_set_number;
-                        group_map.insert(i.group.unwrap(), x);
-                        group_set_number += 1;
-                        x
-                    }
-                };
-                let uniform_binding_number =
-                    uniform_binding_number_map.entry(group_number).or_insert(0);
+                let (group_number, binding_number) = layout.binding(i.name, Stage::Compute);
                 binding_struct.push(DefaultBinding {
-                    binding_number: *uniform_binding_number,
+                    binding_number,
                     group_number,
                     name: i.name.to_string(),
                     data: None,
@@ -93,22 +368,11 @@ fn create_bindings(compute: &ComputeShader) -> (ComputeBindings, OutComputeBindi
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                *uniform_binding_number += 1;
             // Bindings that are invalidated after a run
             } else if i.qual.contains(&QUALIFIER::OUT) {
-                let group_number = match group_map.get(i.group.unwrap()) {
-                    Some(i) => *i,
-                    None => {
-                        let x = group_set_number;
-                        group_map.insert(i.group.unwrap(), x);
-                        group_set_number += 1;
-                        x
-                    }
-                };
-                let uniform_binding_number =
-                    uniform_binding_number_map.entry(group_number).or_insert(0);
+                let (group_number, binding_number) = layout.binding(i.name, Stage::Compute);
                 out_binding_struct.push(DefaultBinding {
-                    binding_number: *uniform_binding_number,
+                    binding_number,
                     group_number,
                     name: i.name.to_string(),
                     data: None,
@@ -116,7 +380,6 @@ fn create_bindings(compute: &ComputeShader) -> (ComputeBindings, OutComputeBindi
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                *uniform_binding_number += 1;
             }
         }
     }
@@ -131,26 +394,65 @@ fn create_bindings(compute: &ComputeShader) -> (ComputeBindings, OutComputeBindi
     )
 }
 
+#[derive(Default)]
+pub struct ComputeCompileArgs {
+    pub backend: ShaderBackend,
+    // The value of each `const` parameter, from the Context's `constants`
+    pub constants: Vec<(&'static str, ConstValue)>,
+}
+
 pub async fn compile(
     compute: &ComputeShader,
     device: &wgpu::Device,
-    bind_group_layout: Vec<wgpu::BindGroupLayout>,
-) -> ComputeProgram {
-    let (program_bindings, out_program_bindings) = create_bindings(&compute);
+    layout: ContextLayout,
+    args: ComputeCompileArgs,
+) -> Result<ComputeProgram, ShaderCompileError> {
+    let (program_bindings, out_program_bindings) = create_bindings(&compute, layout.description);
+    let dispatch_group = layout.bind_group_layouts.len() as u32;
+
+    check_workgroup_storage_size(compute, &args.constants)?;
 
-    let cs_module = compile_shader(
-        stringify_shader(&compute, &program_bindings, &out_program_bindings),
-        ShaderType::Compute,
-        &device,
-    );
+    let cs_module = match args.backend {
+        ShaderBackend::Glsl => compile_shader(
+            stringify_shader(
+                &compute,
+                &program_bindings,
+                &out_program_bindings,
+                &args.constants,
+                dispatch_group,
+            )?,
+            Stage::Compute,
+            &device,
+        )?,
+        ShaderBackend::Wgsl => {
+            wgsl_push_constants(&push_constants(compute.params.iter()), Stage::Compute)?;
+            compile_wgsl_shader(
+                stringify_shader_wgsl(
+                    &compute,
+                    &program_bindings,
+                    &out_program_bindings,
+                    &args.constants,
+                    dispatch_group,
+                )?,
+                &device,
+            )
+        }
+    };
 
-    let bind_group_layout_ref: Vec<&wgpu::BindGroupLayout> =
-        bind_group_layout.iter().map(|a| a).collect();
+    let dispatch_size_layout = DispatchSize::get_layout(device);
+    let bind_group_layout_ref: Vec<&wgpu::BindGroupLayout> = layout
+        .bind_group_layouts
+        .iter()
+        .chain(std::iter::once(&dispatch_size_layout))
+        .collect();
 
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: None,
         bind_group_layouts: &bind_group_layout_ref,
-        push_constant_ranges: &[],
+        push_constant_ranges: &push_constant_ranges(
+            &push_constants(compute.params.iter()),
+            wgpu::ShaderStage::COMPUTE,
+        ),
     });
 
     // The part where we actually bring it all together
@@ -161,27 +463,98 @@ pub async fn compile(
         entry_point: "main",
     });
 
-    ComputeProgram { pipeline }
+    Ok(ComputeProgram {
+        pipeline,
+        local_size: local_size(compute, &args.constants)?,
+        dispatch_group,
+    })
+}
+
+// The number of workgroups of the program's local size that cover a `size` problem.
+// The last workgroup in a dimension can have invocations past the end of the problem,
+// the guard returns early from them with the size the shader is given by `DispatchSize`.
+// Only the Context dispatches them, on a pass begun with its program
+pub fn workgroups(local_size: [u32; 3], size: [u32; 3]) -> [u32; 3] {
+    let mut workgroups = [0; 3];
+    for dim in 0..3 {
+        let local = local_size[dim];
+        workgroups[dim] = size[dim] / local + (size[dim] % local != 0) as u32;
+    }
+    workgroups
 }
 
-pub fn compute_run(rpass: &mut wgpu::ComputePass, length: u32) {
-    compute(rpass, length);
+// The size of the problem a Context dispatches over, which the shader reads as `dispatch_size`.
+// It is a uniform of its own so the same program can be dispatched over different sizes.
+pub struct DispatchSize {
+    pub size: [u32; 3],
+    buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
 }
 
-pub fn compute(cpass: &mut wgpu::ComputePass, length: u32) {
-    cpass.dispatch(length, 1, 1);
+impl DispatchSize {
+    // A uvec3 takes the space of a uvec4 in a uniform block
+    fn contents(size: [u32; 3]) -> [u32; 4] {
+        [size[0], size[1], size[2], 0]
+    }
+
+    pub fn get_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<[u32; 4]>() as u64),
+                },
+                count: None,
+            }],
+            label: None,
+        })
+    }
+
+    pub fn new(device: &wgpu::Device, size: [u32; 3]) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: DispatchSize::contents(size).as_bytes(),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &DispatchSize::get_layout(device),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        DispatchSize {
+            size,
+            buffer,
+            bind_group,
+        }
+    }
+
+    // Changes the size for the dispatches of the next submit
+    pub fn set(&mut self, queue: &wgpu::Queue, size: [u32; 3]) {
+        queue.write_buffer(&self.buffer, 0, DispatchSize::contents(size).as_bytes());
+        self.size = size;
+    }
 }
 
 #[derive(Debug)]
 pub struct ComputeShader {
     pub params: &'static [PARAMETER],
-    pub body: &'static str,
+    pub body: ShaderBody,
+    // The libraries the body uses functions from
+    pub imports: &'static [&'static ShaderLib],
+    // From `workgroup_size(x, y, z);`, any dimension that isn't given is 1
+    pub workgroup_size: &'static [&'static str],
 }
 
 #[macro_export]
 macro_rules! compute_shader {
         ($($body:tt)*) => {{
-            const S : (&[pipeline::shared::PARAMETER], &'static str) = shader!($($body)*);
-            (pipeline::wgpu_compute_header::ComputeShader{params:S.0, body:S.1})
+            const S : (&[pipeline::shared::PARAMETER], pipeline::shared::ShaderBody, &[&pipeline::shared::ShaderLib], &[&'static str]) = shader!($($body)*);
+            (pipeline::wgpu_compute_header::ComputeShader{params:S.0, body:S.1, imports:S.2, workgroup_size:S.3})
         }};
     }
@@ -1,15 +1,18 @@
-use glsl_to_spirv::ShaderType;
-
-use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fmt;
 use std::rc::Rc;
 
 use crate::shared::{
-    check_gl_builtin_type, compile_shader, has_out_qual, is_gl_builtin, process_body,
-    string_compare, GLSLTYPE, PARAMETER, QUALIFIER,
+    check_gl_builtin_type, compile_shader, compile_wgsl_shader, constant_declaration, has_out_qual,
+    interpolation, is_gl_builtin, library_source, process_body, push_constant_declaration,
+    push_constant_ranges, push_constants, string_compare, struct_declarations, wgsl_builtin,
+    wgsl_builtin_inputs, wgsl_entry_point, wgsl_push_constants, ConstValue, ContextLayout,
+    LayoutDescription, ShaderBackend, ShaderBody, ShaderCompileError, ShaderLib, ShaderSource,
+    GLSLTYPE, PARAMETER, QUALIFIER,
 };
 
-use crate::bind::{DefaultBinding, Indices, SamplerBinding, TextureBinding};
+use crate::bind::{DefaultBinding, SamplerBinding, TextureBinding};
+use crate::builtins::Stage;
 
 pub struct GraphicsProgram {
     pub pipeline: wgpu::RenderPipeline,
@@ -33,69 +36,223 @@ fn stringify_shader(
     s: &GraphicsShader,
     b: &GraphicsBindings,
     b_out: &OutGraphicsBindings,
-) -> String {
-    let mut buffer = Vec::new();
+    push: &[&PARAMETER],
+    constants: &[(&str, ConstValue)],
+    stage: Stage,
+) -> Result<ShaderSource, ShaderCompileError> {
+    let mut source = ShaderSource::default();
+    source.push("\n#version 450\n");
+    for p in s
+        .params
+        .iter()
+        .filter(|p| p.qual.contains(&QUALIFIER::CONST))
+    {
+        source.declare(
+            p.name,
+            &constant_declaration(p, constants, ShaderBackend::Glsl, stage)?,
+        );
+    }
+    source.push(&struct_declarations(
+        b.bindings
+            .iter()
+            .chain(b_out.bindings.iter())
+            .map(|i| &i.gtype),
+        ShaderBackend::Glsl,
+    ));
+    source.push(&push_constant_declaration(push));
     for i in &b.bindings[..] {
         if i.qual.contains(&QUALIFIER::UNIFORM) {
-            buffer.push(format!(
-                "layout(set = {}, binding = {}) uniform UNIFORM{}{} {{\n\t {} {};\n}};\n",
-                i.group_number, i.binding_number, i.group_number, i.binding_number, i.gtype, i.name
-            ));
+            source.declare(
+                &i.name,
+                &format!(
+                    "layout(set = {}, binding = {}) uniform UNIFORM{}{} {{\n\t {} {};\n}};\n",
+                    i.group_number,
+                    i.binding_number,
+                    i.group_number,
+                    i.binding_number,
+                    i.gtype,
+                    i.name
+                ),
+            );
         } else if !is_gl_builtin(&i.name) {
+            source.declare(
+                &i.name,
+                &format!(
+                    "layout(location={}) {}{} {} {};\n",
+                    i.binding_number,
+                    interpolation(&i.qual, ShaderBackend::Glsl),
+                    if i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
+                        "inout"
+                    } else if i.qual.contains(&QUALIFIER::IN) {
+                        "in"
+                    } else if i.qual.contains(&QUALIFIER::OUT) {
+                        "out"
+                    } else {
+                        panic!(
+                            "You are trying to do something with something that isn't an in or out"
+                        )
+                    },
+                    i.gtype,
+                    i.name
+                ),
+            );
+        }
+    }
+    for i in &b.textures[..] {
+        source.declare(
+            &i.name,
+            &format!(
+                "layout(set = {}, binding = {}) uniform {} {};\n",
+                i.group_number, i.binding_number, i.gtype, i.name
+            ),
+        );
+    }
+    for i in &b.samplers[..] {
+        source.declare(
+            &i.name,
+            &format!(
+                "layout(set = {}, binding = {}) uniform {} {};\n",
+                i.group_number, i.binding_number, i.gtype, i.name
+            ),
+        );
+    }
+    for i in &b_out.bindings[..] {
+        if !i.qual.contains(&QUALIFIER::UNIFORM) {
+            source.declare(
+                &i.name,
+                &format!(
+                    "layout(location={}) {}{} {} {};\n",
+                    i.binding_number,
+                    interpolation(&i.qual, ShaderBackend::Glsl),
+                    if i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
+                        "inout"
+                    } else if i.qual.contains(&QUALIFIER::IN) {
+                        "in"
+                    } else if i.qual.contains(&QUALIFIER::OUT) {
+                        "out"
+                    } else {
+                        panic!(
+                            "You are trying to do something with something that isn't an in or out"
+                        )
+                    },
+                    i.gtype,
+                    i.name
+                ),
+            );
+        }
+    }
+    source.push(&library_source(s.imports));
+    source.push("\n\n");
+    Ok(source.body(&process_body(s.body)))
+}
+
+fn wgsl_varying(i: &DefaultBinding) -> String {
+    format!(
+        "[[location({}){}]] var<{}> {} : {};\n",
+        i.binding_number,
+        interpolation(&i.qual, ShaderBackend::Wgsl),
+        if i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
+            panic!(
+                "WGSL does not have inout variables, {} needs to be split into an in and an out",
+                i.name
+            )
+        } else if i.qual.contains(&QUALIFIER::IN) {
+            "in"
+        } else if i.qual.contains(&QUALIFIER::OUT) {
+            "out"
+        } else {
+            panic!("You are trying to do something with something that isn't an in or out")
+        },
+        i.name,
+        i.gtype.to_wgsl()
+    )
+}
+
+// Uniforms are wrapped in a `[[block]]` struct with a single `data` member so the body accesses `name.data`
+fn stringify_shader_wgsl(
+    s: &GraphicsShader,
+    b: &GraphicsBindings,
+    b_out: &OutGraphicsBindings,
+    constants: &[(&str, ConstValue)],
+    stage: Stage,
+) -> Result<String, ShaderCompileError> {
+    let mut buffer = s
+        .params
+        .iter()
+        .filter(|p| p.qual.contains(&QUALIFIER::CONST))
+        .map(|p| constant_declaration(p, constants, ShaderBackend::Wgsl, stage))
+        .collect::<Result<Vec<String>, ShaderCompileError>>()?;
+    buffer.push(struct_declarations(
+        b.bindings
+            .iter()
+            .chain(b_out.bindings.iter())
+            .map(|i| &i.gtype),
+        ShaderBackend::Wgsl,
+    ));
+    for i in &b.bindings[..] {
+        if i.qual.contains(&QUALIFIER::UNIFORM) {
             buffer.push(format!(
-                "layout(location={}) {} {} {};\n",
+                "[[block]] struct UNIFORM{}{} {{\n\tdata : {};\n}};\n[[group({}), binding({})]] var<uniform> {} : UNIFORM{}{};\n",
+                i.group_number,
                 i.binding_number,
-                if i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
-                    "inout"
-                } else if i.qual.contains(&QUALIFIER::IN) {
-                    "in"
-                } else if i.qual.contains(&QUALIFIER::OUT) {
-                    "out"
-                } else {
-                    panic!("You are trying to do something with something that isn't an in or out")
-                },
-                i.gtype,
-                i.name
+                i.gtype.to_wgsl(),
+                i.group_number,
+                i.binding_number,
+                i.name,
+                i.group_number,
+                i.binding_number
             ));
+        } else if !is_gl_builtin(&i.name) {
+            buffer.push(wgsl_varying(i));
         }
     }
     for i in &b.textures[..] {
         buffer.push(format!(
-            "layout(set = {}, binding = {}) uniform {} {};\n",
-            i.group_number, i.binding_number, i.gtype, i.name
+            "[[group({}), binding({})]] var {} : {};\n",
+            i.group_number,
+            i.binding_number,
+            i.name,
+            i.gtype.to_wgsl()
         ));
     }
     for i in &b.samplers[..] {
         buffer.push(format!(
-            "layout(set = {}, binding = {}) uniform {} {};\n",
-            i.group_number, i.binding_number, i.gtype, i.name
+            "[[group({}), binding({})]] var {} : {};\n",
+            i.group_number,
+            i.binding_number,
+            i.name,
+            i.gtype.to_wgsl()
         ));
     }
     for i in &b_out.bindings[..] {
-        if i.name != "gl_Position" && !i.qual.contains(&QUALIFIER::UNIFORM) {
+        if !i.qual.contains(&QUALIFIER::UNIFORM) {
+            buffer.push(wgsl_varying(i));
+        }
+    }
+    // The builtins the shader writes are declared as parameters
+    for i in s.params.iter().filter(|i| is_gl_builtin(i.name)) {
+        if has_out_qual(i.qual) {
+            let (builtin, wtype) = wgsl_builtin(i.name)
+                .unwrap_or_else(|| panic!("WGSL does not have a builtin for {}", i.name));
             buffer.push(format!(
-                "layout(location={}) {} {} {};\n",
-                i.binding_number,
-                if i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
-                    "inout"
-                } else if i.qual.contains(&QUALIFIER::IN) {
-                    "in"
-                } else if i.qual.contains(&QUALIFIER::OUT) {
-                    "out"
-                } else {
-                    panic!("You are trying to do something with something that isn't an in or out")
-                },
-                i.gtype,
-                i.name
+                "[[builtin({})]] var<out> {} : {};\n",
+                builtin, i.name, wtype
             ));
         }
     }
-    format!(
-        //todo figure out how to use a non-1 local size
-        "\n#version 450\n{}\n\n{}",
+    let entry_point = match stage {
+        Stage::Vertex => "[[stage(vertex)]]",
+        Stage::Fragment => "[[stage(fragment)]]",
+        Stage::Compute => panic!("Graphics programs only have vertex and fragment shaders"),
+    };
+    let body = process_body(s.body);
+    Ok(format!(
+        "{}\n{}\n\n{}{}",
         buffer.join(""),
-        process_body(s.body)
-    )
+        wgsl_builtin_inputs(&body, stage),
+        library_source(s.imports),
+        wgsl_entry_point(&body, entry_point)
+    ))
 }
 
 pub fn generate_swap_chain(
@@ -120,6 +277,7 @@ pub fn generate_swap_chain(
 fn create_bindings(
     vertex: &GraphicsShader,
     fragment: &GraphicsShader,
+    layout: &LayoutDescription,
 ) -> (
     GraphicsBindings,
     OutGraphicsBindings,
@@ -130,22 +288,15 @@ fn create_bindings(
     let mut vertex_out_binding_struct = Vec::new();
     let mut fragment_binding_struct = Vec::new();
     let mut fragment_out_binding_struct = Vec::new();
-    let mut vertex_stage_binding_number = 0;
-    let mut vertex_binding_number = 0;
-    let mut vertex_to_fragment_binding_number = 0;
-    let mut vertex_to_fragment_map = HashMap::new();
-    let mut uniform_map = HashMap::new();
-    let mut uniform_binding_number_map = HashMap::new();
-    let mut uniform_binding_number_fragment_map = HashMap::new();
-    let mut fragment_out_binding_number = 0;
-    let mut group_map: HashMap<&str, u32> = HashMap::new();
-    let mut group_set_number = 0;
     for i in &vertex.params[..] {
-        if !check_gl_builtin_type(i.name, &i.gtype) {
+        if !check_gl_builtin_type(i.name, &i.gtype)
+            && !i.qual.contains(&QUALIFIER::PUSH)
+            && !i.qual.contains(&QUALIFIER::CONST)
+        {
             // Bindings that are kept between runs
             if i.qual.contains(&QUALIFIER::VERTEX) {
                 vertex_binding_struct.push(DefaultBinding {
-                    binding_number: vertex_binding_number,
+                    binding_number: layout.location(i.name, Stage::Vertex, false),
                     group_number: 0,
                     name: i.name.to_string(),
                     data: None,
@@ -153,22 +304,11 @@ fn create_bindings(
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                vertex_binding_number += 1;
             // Bindings that are invalidated after a run
             } else if i.qual.contains(&QUALIFIER::UNIFORM) {
-                let group_number = match group_map.get(i.group.unwrap()) {
-                    Some(i) => *i,
-                    None => {
-                        let x = group_set_number;
-                        group_map.insert(i.group.unwrap(), x);
-                        group_set_number += 1;
-                        x
-                    }
-                };
-                let uniform_binding_number =
-                    uniform_binding_number_map.entry(group_number).or_insert(0);
+                let (group_number, binding_number) = layout.binding(i.name, Stage::Vertex);
                 vertex_binding_struct.push(DefaultBinding {
-                    binding_number: *uniform_binding_number,
+                    binding_number,
                     group_number,
                     name: i.name.to_string(),
                     data: None,
@@ -176,11 +316,9 @@ fn create_bindings(
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                uniform_map.insert(i.group.unwrap(), *uniform_binding_number);
-                *uniform_binding_number += 1;
             } else if i.qual.contains(&QUALIFIER::IN) && !i.qual.contains(&QUALIFIER::OUT) {
                 vertex_binding_struct.push(DefaultBinding {
-                    binding_number: vertex_stage_binding_number,
+                    binding_number: layout.location(i.name, Stage::Vertex, false),
                     group_number: 0,
                     name: i.name.to_string(),
                     data: None,
@@ -188,11 +326,10 @@ fn create_bindings(
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                vertex_stage_binding_number += 1;
             // Bindings that are invalidated after a run
             } else if !i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
                 vertex_out_binding_struct.push(DefaultBinding {
-                    binding_number: vertex_to_fragment_binding_number,
+                    binding_number: layout.location(i.name, Stage::Vertex, true),
                     group_number: 0,
                     name: i.name.to_string(),
                     data: None,
@@ -200,8 +337,6 @@ fn create_bindings(
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                vertex_to_fragment_map.insert(i.name, vertex_to_fragment_binding_number);
-                vertex_to_fragment_binding_number += 1;
             } else {
                 dbg!(&i);
                 panic!("TODO We currently don't support both in and out qualifiers for vertex/fragment shaders")
@@ -213,70 +348,37 @@ fn create_bindings(
     let mut samplers_struct = Vec::new();
 
     for i in &fragment.params[..] {
-        //todo the binding_number's below are probably wrong
-        if !check_gl_builtin_type(i.name, &i.gtype) {
+        if !check_gl_builtin_type(i.name, &i.gtype)
+            && !i.qual.contains(&QUALIFIER::PUSH)
+            && !i.qual.contains(&QUALIFIER::CONST)
+        {
             // Bindings that are kept between runs
             if i.qual.contains(&QUALIFIER::UNIFORM) {
                 if i.gtype == GLSLTYPE::Sampler || i.gtype == GLSLTYPE::SamplerShadow {
-                    let group_number = match group_map.get(i.group.unwrap()) {
-                        Some(i) => *i,
-                        None => {
-                            let x = group_set_number;
-                            group_map.insert(i.group.unwrap(), x);
-                            group_set_number += 1;
-                            x
-                        }
-                    };
-                    let binding_number = uniform_binding_number_fragment_map
-                        .entry(group_number)
-                        .or_insert(0);
+                    let (group_number, binding_number) = layout.binding(i.name, Stage::Fragment);
                     samplers_struct.push(SamplerBinding {
-                        binding_number: *binding_number,
+                        binding_number,
                         group_number,
                         name: i.name.to_string(),
                         data: None,
                         gtype: i.gtype.clone(),
                         qual: i.qual.to_vec(),
                     });
-                    *binding_number += 1;
                 } else if i.gtype == GLSLTYPE::Texture2D
                     || i.gtype == GLSLTYPE::Texture2DArray
                     || i.gtype == GLSLTYPE::TextureCube
                 {
-                    let group_number = match group_map.get(i.group.unwrap()) {
-                        Some(i) => *i,
-                        None => {
-                            let x = group_set_number;
-                            group_map.insert(i.group.unwrap(), x);
-                            group_set_number += 1;
-                            x
-                        }
-                    };
-                    let binding_number = uniform_binding_number_fragment_map
-                        .entry(group_number)
-                        .or_insert(0);
+                    let (group_number, binding_number) = layout.binding(i.name, Stage::Fragment);
                     textures_struct.push(TextureBinding {
-                        binding_number: *binding_number,
+                        binding_number,
                         group_number,
                         name: i.name.to_string(),
                         data: None,
                         gtype: i.gtype.clone(),
                         qual: i.qual.to_vec(),
                     });
-                    *binding_number += 1;
                 } else {
-                    let group_number = match group_map.get(i.group.unwrap()) {
-                        Some(i) => *i,
-                        None => {
-                            let x = group_set_number;
-                            group_map.insert(i.group.unwrap(), x);
-                            group_set_number += 1;
-                            x
-                        }
-                    };
-                    let binding_number = uniform_binding_number_fragment_map
-                        .entry(group_number)
-                        .or_insert(0);
+                    let (group_number, binding_number) = layout.binding(i.name, Stage::Fragment);
                     /* let num = if uniform_map.get(i.name).is_some() {
                         *uniform_map.get(i.name).unwrap()
                     } else {
@@ -288,7 +390,7 @@ fn create_bindings(
                         x */
                     }; */
                     fragment_binding_struct.push(DefaultBinding {
-                        binding_number: *binding_number,
+                        binding_number,
                         group_number,
                         name: i.name.to_string(),
                         data: None,
@@ -296,13 +398,10 @@ fn create_bindings(
                         gtype: i.gtype.clone(),
                         qual: i.qual.to_vec(),
                     });
-                    *binding_number += 1;
                 }
             } else if i.qual.contains(&QUALIFIER::IN) && !i.qual.contains(&QUALIFIER::OUT) {
                 fragment_binding_struct.push(DefaultBinding {
-                    binding_number: *vertex_to_fragment_map
-                        .get(i.name)
-                        .unwrap_or_else(|| panic!("{} has not been bound", i.name)),
+                    binding_number: layout.location(i.name, Stage::Fragment, false),
                     group_number: 0,
                     name: i.name.to_string(),
                     data: None,
@@ -313,7 +412,7 @@ fn create_bindings(
             // Bindings that are invalidated after a run
             } else if !i.qual.contains(&QUALIFIER::IN) && i.qual.contains(&QUALIFIER::OUT) {
                 fragment_out_binding_struct.push(DefaultBinding {
-                    binding_number: fragment_out_binding_number,
+                    binding_number: layout.location(i.name, Stage::Fragment, true),
                     group_number: 0,
                     name: i.name.to_string(),
                     data: None,
@@ -321,7 +420,6 @@ fn create_bindings(
                     gtype: i.gtype.clone(),
                     qual: i.qual.to_vec(),
                 });
-                fragment_out_binding_number += 1;
             } else {
                 //panic!("TODO We currently don't support both in and out qualifiers for vertex/fragment shaders")
             }
@@ -353,27 +451,33 @@ fn create_bindings(
 }
 
 pub struct GraphicsCompileArgs {
-    pub color_target_state: Option<wgpu::ColorTargetState>,
+    // One for each out of the fragment shader in the order of their locations
+    pub color_target_states: Vec<wgpu::ColorTargetState>,
     pub primitive_state: wgpu::PrimitiveState,
     pub depth_stencil_state: Option<wgpu::DepthStencilState>,
     pub multisample_state: wgpu::MultisampleState,
+    pub backend: ShaderBackend,
+    // The value of each `const` parameter, from the Context's `constants`
+    pub constants: Vec<(&'static str, ConstValue)>,
 }
 
 impl Default for GraphicsCompileArgs {
     fn default() -> Self {
         GraphicsCompileArgs {
-            color_target_state: Some(wgpu::ColorTargetState {
+            color_target_states: vec![wgpu::ColorTargetState {
                 format: wgpu::TextureFormat::Bgra8UnormSrgb,
                 alpha_blend: wgpu::BlendState::default(),
                 color_blend: wgpu::BlendState::default(),
                 write_mask: wgpu::ColorWrite::default(),
-            }),
+            }],
             primitive_state: wgpu::PrimitiveState {
                 cull_mode: wgpu::CullMode::Back,
                 ..Default::default()
             },
             depth_stencil_state: None,
             multisample_state: wgpu::MultisampleState::default(),
+            backend: ShaderBackend::default(),
+            constants: Vec::new(),
         }
     }
 }
@@ -381,30 +485,23 @@ impl Default for GraphicsCompileArgs {
 pub async fn graphics_compile(
     vec_buffer: &mut [wgpu::VertexAttribute; 32],
     device: &wgpu::Device,
-    bind_group_layout: Vec<wgpu::BindGroupLayout>,
+    layout: ContextLayout,
     vertex: &GraphicsShader,
     fragment: &GraphicsShader,
     args: GraphicsCompileArgs,
-) -> GraphicsProgram {
+) -> Result<GraphicsProgram, ShaderCompileError> {
     // the adapter is the handler to the physical graphics unit
 
     let (program_bindings1, out_program_bindings1, program_bindings2, out_program_bindings2) =
-        create_bindings(&vertex, &fragment);
+        create_bindings(&vertex, &fragment, layout.description);
 
     for i in &program_bindings1.bindings[..] {
-        if i.qual.contains(&QUALIFIER::VERTEX) {
+        if !i.qual.contains(&QUALIFIER::UNIFORM) && !i.qual.contains(&QUALIFIER::BUFFER) {
             vec_buffer[i.binding_number as usize] = wgpu::VertexAttribute {
                 offset: 0,
                 // This is our connection to shader.vert
-                // TODO WOW I had an error because I hardcoded the format's below. That should not be a thing
                 shader_location: i.binding_number,
-                format: if i.gtype == GLSLTYPE::Vec3 {
-                    wgpu::VertexFormat::Float3
-                } else if i.gtype == GLSLTYPE::Vec2 {
-                    wgpu::VertexFormat::Float2
-                } else {
-                    wgpu::VertexFormat::Float
-                },
+                format: i.gtype.vertex_format(),
             };
         }
     }
@@ -427,40 +524,96 @@ pub async fn graphics_compile(
         }
     }
 
-    let x = stringify_shader(vertex, &program_bindings1, &out_program_bindings1);
-
-    println!("{}", x);
-
-    // Our compiled vertex shader
-    let vs_module = compile_shader(x, ShaderType::Vertex, &device);
-
-    let y = stringify_shader(fragment, &program_bindings2, &out_program_bindings2);
-
-    println!("{}", y);
-
-    // Our compiled fragment shader
-    let fs_module = compile_shader(y, ShaderType::Fragment, &device);
+    let push = push_constants(vertex.params.iter().chain(fragment.params.iter()));
+
+    let (vs_module, fs_module) = match args.backend {
+        ShaderBackend::Glsl => {
+            let x = stringify_shader(
+                vertex,
+                &program_bindings1,
+                &out_program_bindings1,
+                &push,
+                &args.constants,
+                Stage::Vertex,
+            )?;
+
+            println!("{}", x.source);
+
+            // Our compiled vertex shader
+            let vs_module = compile_shader(x, Stage::Vertex, &device)?;
+
+            let y = stringify_shader(
+                fragment,
+                &program_bindings2,
+                &out_program_bindings2,
+                &push,
+                &args.constants,
+                Stage::Fragment,
+            )?;
+
+            println!("{}", y.source);
+
+            // Our compiled fragment shader
+            let fs_module = compile_shader(y, Stage::Fragment, &device)?;
+            (vs_module, fs_module)
+        }
+        ShaderBackend::Wgsl => {
+            wgsl_push_constants(&push_constants(vertex.params.iter()), Stage::Vertex)?;
+            wgsl_push_constants(&push_constants(fragment.params.iter()), Stage::Fragment)?;
+
+            let x = stringify_shader_wgsl(
+                vertex,
+                &program_bindings1,
+                &out_program_bindings1,
+                &args.constants,
+                Stage::Vertex,
+            )?;
+
+            let y = stringify_shader_wgsl(
+                fragment,
+                &program_bindings2,
+                &out_program_bindings2,
+                &args.constants,
+                Stage::Fragment,
+            )?;
+
+            (
+                compile_wgsl_shader(x, &device),
+                compile_wgsl_shader(y, &device),
+            )
+        }
+    };
 
     let bind_group_layout_ref: Vec<&wgpu::BindGroupLayout> =
-        bind_group_layout.iter().map(|a| a).collect();
+        layout.bind_group_layouts.iter().collect();
 
     // Bind no values to none of the bindings.
     // Use for something like textures
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: None,
         bind_group_layouts: &bind_group_layout_ref,
-        push_constant_ranges: &[],
+        push_constant_ranges: &push_constant_ranges(
+            &push,
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+        ),
     });
 
     //debug!(pipeline_layout);
     debug!(vertex_binding_desc);
 
-    let mut color_target_state = Vec::new();
-
-    match args.color_target_state {
-        Some(cts) => color_target_state.push(cts),
-        None => {}
-    };
+    // Each out of the fragment shader is written to the color attachment at its location
+    if args.color_target_states.len() != out_program_bindings2.bindings.len() {
+        return Err(ShaderCompileError {
+            stage: Stage::Fragment,
+            message: format!(
+                "The fragment shader has {} outs but {} color target states were given",
+                out_program_bindings2.bindings.len(),
+                args.color_target_states.len()
+            ),
+            line: None,
+            parameter: None,
+        });
+    }
 
     // The part where we actually bring it all together
     let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -482,7 +635,7 @@ pub async fn graphics_compile(
             module: &fs_module,
             // The name of the method in shader.frag to use
             entry_point: "main",
-            targets: &color_target_state,
+            targets: &args.color_target_states,
         }),
         // Use Triangles
         primitive: args.primitive_state,
@@ -534,39 +687,61 @@ pub async fn graphics_compile(
         multisample: args.multisample_state,
     });
 
-    GraphicsProgram {
+    Ok(GraphicsProgram {
         pipeline: render_pipeline,
-    }
+    })
 }
 
-fn draw(
-    rpass: &mut wgpu::RenderPass,
-    vertices: core::ops::Range<u32>,
-    instances: core::ops::Range<u32>,
-) {
-    rpass.draw(vertices, instances);
+/// A draw that would read past the end of the shortest vertex or instance buffer bound for it.
+#[derive(Debug, Clone)]
+pub enum DrawError {
+    Vertices { drawn: u32, count: u32 },
+    Instances { drawn: u32, count: u32 },
 }
 
-fn draw_indexed(
-    rpass: &mut wgpu::RenderPass,
-    indexes: core::ops::Range<u32>,
-    instances: core::ops::Range<u32>,
-) {
-    rpass.draw_indexed(indexes, 0, instances);
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawError::Vertices { drawn, count } => write!(
+                f,
+                "Drawing {} vertices but the shortest vertex buffer bound has {}",
+                drawn, count
+            ),
+            DrawError::Instances { drawn, count } => write!(
+                f,
+                "Drawing {} instances but the shortest instance buffer bound has {}",
+                drawn, count
+            ),
+        }
+    }
 }
 
-pub fn graphics_run(rpass: &mut wgpu::RenderPass, num_verts: u32, num_instances: u32) {
-    draw(rpass, 0..num_verts, 0..num_instances);
-}
+impl std::error::Error for DrawError {}
 
-pub fn graphics_run_indices<'a>(
-    rpass: &mut wgpu::RenderPass<'a>,
-    indices: &'a Indices,
+// The Context only draws when it doesn't read past the end of the buffers bound for it
+pub fn check_draw_counts(
+    num_verts: u32,
     num_instances: u32,
-) {
-    rpass.set_index_buffer(indices.buffer.slice(..), wgpu::IndexFormat::Uint16);
-
-    draw_indexed(rpass, 0..indices.len, 0..num_instances);
+    vertex_count: Option<u32>,
+    instance_count: Option<u32>,
+) -> Result<(), DrawError> {
+    if let Some(count) = vertex_count {
+        if num_verts > count {
+            return Err(DrawError::Vertices {
+                drawn: num_verts,
+                count,
+            });
+        }
+    }
+    if let Some(count) = instance_count {
+        if num_instances > count {
+            return Err(DrawError::Instances {
+                drawn: num_instances,
+                count,
+            });
+        }
+    }
+    Ok(())
 }
 
 pub fn setup_render_pass<'a, 'b>(
@@ -641,7 +816,9 @@ wgpu::RenderPassDescriptor {
 #[derive(Debug)]
 pub struct GraphicsShader {
     pub params: &'static [PARAMETER],
-    pub body: &'static str,
+    pub body: ShaderBody,
+    // The libraries the body uses functions from
+    pub imports: &'static [&'static ShaderLib],
 }
 
 pub const fn valid_vertex_shader(vert: &GraphicsShader) {
@@ -659,67 +836,69 @@ pub const fn valid_vertex_shader(vert: &GraphicsShader) {
     panic!("This is not a valid vertex shader! Remember you need 'gl_Position' as an out of a vertex shader")
 }
 
+// Each out of a fragment shader is written to a color attachment so it has to be a scalar or vector
 pub const fn valid_fragment_shader(frag: &GraphicsShader) {
+    let mut outs = 0;
     let mut acc = 0;
     while acc < frag.params.len() {
-        if string_compare(frag.params[acc].name, "color") && has_out_qual(frag.params[acc].qual) {
-            if let GLSLTYPE::Vec4 = frag.params[acc].gtype {
-                return;
+        if has_out_qual(frag.params[acc].qual) && !is_gl_builtin(frag.params[acc].name) {
+            match frag.params[acc].gtype {
+                GLSLTYPE::Float
+                | GLSLTYPE::Int
+                | GLSLTYPE::Uint
+                | GLSLTYPE::Vec2
+                | GLSLTYPE::Vec3
+                | GLSLTYPE::Vec4
+                | GLSLTYPE::Ivec2
+                | GLSLTYPE::Ivec3
+                | GLSLTYPE::Ivec4
+                | GLSLTYPE::Uvec2
+                | GLSLTYPE::Uvec3
+                | GLSLTYPE::Uvec4 => outs += 1,
+                _ => panic!("This is not a valid fragment shader! The outs of a fragment shader need to be scalars or vectors"),
             }
         }
         acc += 1;
     }
-    panic!("This is not a valid fragment shader! Remember you need 'color' as an out of a fragment shader")
+    if outs == 0 {
+        panic!("This is not a valid fragment shader! Remember you need an out like 'color' in a fragment shader")
+    }
 }
 
 #[macro_export]
 macro_rules! graphics_shader {
     ($($body:tt)*) => {{
-        const S : (&[pipeline::shared::PARAMETER], &'static str) = shader!($($body)*);
-        (pipeline::wgpu_graphics_header::GraphicsShader{params:S.0, body:S.1})
+        const S : (&[pipeline::shared::PARAMETER], pipeline::shared::ShaderBody, &[&pipeline::shared::ShaderLib], &[&'static str]) = shader!($($body)*);
+        (pipeline::wgpu_graphics_header::GraphicsShader{params:S.0, body:S.1, imports:S.2})
     }};
 }
 
 #[macro_export]
 macro_rules! compile_valid_graphics_program {
-    ($device:tt, $context:tt, $vertex:tt, $fragment:tt, $args:expr) => {{
+    ($device:tt, $program:ty, $vertex:tt, $fragment:tt, $args:expr) => {{
         let mut compile_buffer: [wgpu::VertexAttribute; 32] =
             pipeline::wgpu_graphics_header::compile_buffer();
 
         const _: () = pipeline::wgpu_graphics_header::valid_vertex_shader(&$vertex);
         const _: () = pipeline::wgpu_graphics_header::valid_fragment_shader(&$fragment);
-        let x = pipeline::wgpu_graphics_header::graphics_compile(
-            &mut compile_buffer,
-            &$device,
-            $context.get_layout(&$device),
-            &$vertex,
-            &$fragment,
-            $args,
-        )
-        .await;
-        (x, compile_buffer)
+        let x =
+            <$program>::compile(&mut compile_buffer, &$device, &$vertex, &$fragment, $args).await;
+        x.map(|x| (x, compile_buffer))
     }};
 }
 
 #[macro_export]
 macro_rules! compile_valid_stencil_program {
-    ($device:tt, $context:tt, $vertex:tt, $fragment:tt, $args:expr) => {{
+    ($device:tt, $program:ty, $vertex:tt, $fragment:tt, $args:expr) => {{
         let mut compile_buffer: [wgpu::VertexAttribute; 32] =
             pipeline::wgpu_graphics_header::compile_buffer();
 
         const _: () = pipeline::wgpu_graphics_header::valid_vertex_shader(&$vertex);
         //todo maybe some validation for a fragment stencil shader?
         //todo make sure these are running at compile time
-        let x = pipeline::wgpu_graphics_header::graphics_compile(
-            &mut compile_buffer,
-            &$device,
-            $context.get_layout(&$device),
-            &$vertex,
-            &$fragment,
-            $args,
-        )
-        .await;
-        (x, compile_buffer)
+        let x =
+            <$program>::compile(&mut compile_buffer, &$device, &$vertex, &$fragment, $args).await;
+        x.map(|x| (x, compile_buffer))
     }};
 }
 
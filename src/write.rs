@@ -7,11 +7,7 @@ pub struct MyBufferViewMut {
 }
 
 impl MyBufferViewMut {
-    pub fn new(
-        device: &wgpu::Device,
-        src: Rc<wgpu::Buffer>,
-        bounds: std::ops::Range<u64>,
-    ) -> Self {
+    pub fn new(device: &wgpu::Device, src: Rc<wgpu::Buffer>, bounds: std::ops::Range<u64>) -> Self {
         let write_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("compute_writing_buffer"),
             size: bounds.end - bounds.start,
@@ -30,7 +26,9 @@ impl MyBufferViewMut {
         &self,
         device: &wgpu::Device,
     ) -> Result<wgpu::BufferViewMut<'_>, &'static str> {
-        let buffer_slice = self.write_buffer.slice(0..(self.bounds.end - self.bounds.start));
+        let buffer_slice = self
+            .write_buffer
+            .slice(0..(self.bounds.end - self.bounds.start));
 
         let buffer_future = buffer_slice.map_async(wgpu::MapMode::Write);
         device.poll(wgpu::Maintain::Wait);
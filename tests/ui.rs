@@ -0,0 +1,7 @@
+// The shaders and Contexts that have to be rejected when the examples are compiled
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("examples/ui/*.rs");
+    t.pass("examples/ui/pass/pipeline.rs");
+}
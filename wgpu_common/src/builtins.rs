@@ -0,0 +1,164 @@
+// The `gl_` variables a shader can declare as parameters
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    // The GLSL type the parameter has to be declared with
+    pub gtype: &'static str,
+    pub stage: Stage,
+    // Whether the shader writes the builtin, these have to be declared `out`
+    pub is_out: bool,
+    // The WGSL builtin and its type if WGSL has one
+    pub wgsl: Option<(&'static str, &'static str)>,
+}
+
+const fn builtin(
+    name: &'static str,
+    gtype: &'static str,
+    stage: Stage,
+    is_out: bool,
+    wgsl: Option<(&'static str, &'static str)>,
+) -> Builtin {
+    Builtin {
+        name,
+        gtype,
+        stage,
+        is_out,
+        wgsl,
+    }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    builtin(
+        "gl_VertexIndex",
+        "int",
+        Stage::Vertex,
+        false,
+        Some(("vertex_index", "u32")),
+    ),
+    builtin(
+        "gl_InstanceIndex",
+        "int",
+        Stage::Vertex,
+        false,
+        Some(("instance_index", "u32")),
+    ),
+    builtin(
+        "gl_Position",
+        "vec4",
+        Stage::Vertex,
+        true,
+        Some(("position", "vec4<f32>")),
+    ),
+    builtin("gl_PointSize", "float", Stage::Vertex, true, None),
+    builtin(
+        "gl_FragCoord",
+        "vec4",
+        Stage::Fragment,
+        false,
+        Some(("frag_coord", "vec4<f32>")),
+    ),
+    builtin(
+        "gl_FrontFacing",
+        "bool",
+        Stage::Fragment,
+        false,
+        Some(("front_facing", "bool")),
+    ),
+    builtin("gl_PointCoord", "vec2", Stage::Fragment, false, None),
+    builtin(
+        "gl_SampleID",
+        "int",
+        Stage::Fragment,
+        false,
+        Some(("sample_index", "u32")),
+    ),
+    builtin("gl_SamplePosition", "vec2", Stage::Fragment, false, None),
+    builtin(
+        "gl_FragDepth",
+        "float",
+        Stage::Fragment,
+        true,
+        Some(("frag_depth", "f32")),
+    ),
+    builtin("gl_NumWorkGroups", "uvec3", Stage::Compute, false, None),
+    builtin(
+        "gl_WorkGroupID",
+        "uvec3",
+        Stage::Compute,
+        false,
+        Some(("workgroup_id", "vec3<u32>")),
+    ),
+    builtin(
+        "gl_LocalInvocationID",
+        "uvec3",
+        Stage::Compute,
+        false,
+        Some(("local_invocation_id", "vec3<u32>")),
+    ),
+    builtin(
+        "gl_GlobalInvocationID",
+        "uvec3",
+        Stage::Compute,
+        false,
+        Some(("global_invocation_id", "vec3<u32>")),
+    ),
+    builtin(
+        "gl_LocalInvocationIndex",
+        "uint",
+        Stage::Compute,
+        false,
+        Some(("local_invocation_index", "u32")),
+    ),
+];
+
+// OpenGL builtins that Vulkan GLSL renamed, these don't compile for wgpu
+pub const REPLACED: &[(&str, &str)] = &[
+    ("gl_VertexID", "gl_VertexIndex"),
+    ("gl_InstanceID", "gl_InstanceIndex"),
+];
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut acc = 0;
+    while acc < a.len() {
+        if a[acc] != b[acc] {
+            return false;
+        }
+        acc += 1;
+    }
+    true
+}
+
+pub const fn find_builtin(name: &str) -> Option<&'static Builtin> {
+    let mut acc = 0;
+    while acc < BUILTINS.len() {
+        if str_eq(BUILTINS[acc].name, name) {
+            return Some(&BUILTINS[acc]);
+        }
+        acc += 1;
+    }
+    None
+}
+
+// The builtins a stage reads without declaring them
+pub fn stage_inputs(stage: Stage) -> impl Iterator<Item = &'static Builtin> {
+    BUILTINS
+        .iter()
+        .filter(move |b| b.stage == stage && !b.is_out)
+}
+
+// The size of the problem a compute shader is dispatched over, which the pipeline declares for the body as a `uvec3`.
+// Invocations of the last workgroups can be past it, a shader with `shared` arrays has to check for them itself.
+pub const DISPATCH_SIZE: &str = "dispatch_size";
@@ -0,0 +1,3 @@
+// The tables that both pipeline and wgpu_macros check shaders against
+pub mod builtins;
+pub mod limits;
@@ -0,0 +1,6 @@
+// The least workgroup memory that every backend supports, in bytes.
+// The `Limits` of wgpu 0.7 don't have it yet, later ones call it `max_compute_workgroup_storage_size`
+pub const MAX_WORKGROUP_STORAGE_SIZE: usize = 16384;
+
+// The vertex attributes a pipeline can have, `compile_buffer` has one for each location
+pub const MAX_VERTEX_ATTRIBUTES: u32 = 32;
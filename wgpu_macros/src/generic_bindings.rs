@@ -2,15 +2,18 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
-use syn::{braced, bracketed, parse_macro_input, Ident, Token};
+use syn::{braced, bracketed, parenthesized, parse_macro_input, Ident, Token};
 
 use std::collections::HashMap;
 use std::iter;
 
-use rand::Rng;
+use crate::builtins::{find_builtin, Stage, DISPATCH_SIZE, REPLACED};
+use crate::numbering::{assign_bindings, assign_numbers};
+use crate::shader_body::{is_wgsl, string_body, validate_body, BodyParam};
+use wgpu_common::limits::{MAX_VERTEX_ATTRIBUTES, MAX_WORKGROUP_STORAGE_SIZE};
 
 // For Types like `vec` which can have dimensions `vec2`, `vec3`, and `vec4`
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum GLSLDimension {
     Two,
     Three,
@@ -28,7 +31,7 @@ impl From<&GLSLDimension> for &str {
 }
 
 // All possible GLSL types that are supported or that I should support
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 enum GLSLType {
     // Non-vector types
     Bool,
@@ -42,7 +45,7 @@ enum GLSLType {
     UVec(GLSLDimension),
     Vec(GLSLDimension),
     DVec(GLSLDimension),
-    // Matrix types
+    // Matrix types with their columns then rows
     Mat(GLSLDimension, GLSLDimension),
     DMat(GLSLDimension, GLSLDimension),
     // todo I think I need to describe arrays somehow?
@@ -51,7 +54,11 @@ enum GLSLType {
     ArrayUint,
     ArrayFloat,
     ArrayDouble,
+    ArrayBVec(GLSLDimension),
+    ArrayIVec(GLSLDimension),
+    ArrayUVec(GLSLDimension),
     ArrayVec(GLSLDimension),
+    ArrayDVec(GLSLDimension),
     // Sampler types todo add more https://www.khronos.org/opengl/wiki/Sampler_(GLSL)
     Sampler,
     // Shadow Sampler types todo add more https://www.khronos.org/opengl/wiki/Sampler_(GLSL)
@@ -60,36 +67,78 @@ enum GLSLType {
     TextureCube,
     Texture2D,
     Texture2DArray,
+    // Storage image types
+    Image2D,
+    Image3D,
+    UImage2D,
+    // Rust structs that derive `WgpuStruct`
+    Struct(Ident),
+    ArrayStruct(Ident),
 }
 
 impl GLSLType {
     // For vertex parameters, you bind an array of values but the pipeline give the vertex/fragment shader one value at a time so even if the parameter is of type `float`, you need to bind `float[]`
-    fn arrayify(&self) -> Self {
-        match self {
-            GLSLType::Bool => GLSLType::ArrayBool,
+    fn arrayify(&self, span: proc_macro2::Span) -> Result<Self> {
+        Ok(match self {
             GLSLType::Int => GLSLType::ArrayInt,
             GLSLType::Uint => GLSLType::ArrayUint,
             GLSLType::Float => GLSLType::ArrayFloat,
             GLSLType::Double => GLSLType::ArrayDouble,
-            GLSLType::BVec(_) => {todo!()}
-            GLSLType::IVec(_) => {todo!()}
-            GLSLType::UVec(_) => {todo!()}
-            GLSLType::Vec(dim) => {GLSLType::ArrayVec(*dim)}
-            GLSLType::DVec(_) => {todo!()}
-            GLSLType::Mat(_, _) => {todo!()}
-            GLSLType::DMat(_, _) => {todo!()}
-            GLSLType::ArrayBool => {todo!()}
-            GLSLType::ArrayInt => {todo!()}
-            GLSLType::ArrayUint => {todo!()}
-            GLSLType::ArrayFloat => {todo!()}
-            GLSLType::ArrayDouble => {todo!()}
-            GLSLType::ArrayVec(_) => {todo!()}
-            GLSLType::Sampler => {todo!()}
-            GLSLType::SamplerShadow => {todo!()}
-            GLSLType::TextureCube => {todo!()}
-            GLSLType::Texture2D => {todo!()}
-            GLSLType::Texture2DArray => {todo!()}
-        }
+            GLSLType::IVec(dim) => GLSLType::ArrayIVec(*dim),
+            GLSLType::UVec(dim) => GLSLType::ArrayUVec(*dim),
+            GLSLType::Vec(dim) => GLSLType::ArrayVec(*dim),
+            GLSLType::DVec(dim) => GLSLType::ArrayDVec(*dim),
+            // There is no vertex format for bools
+            GLSLType::Bool | GLSLType::BVec(_) => {
+                return Err(syn::Error::new(
+                    span,
+                    "A bool can't be vertex data, use an int or uint instead",
+                ))
+            }
+            // A matrix takes one location for each of its columns which the numbering doesn't do
+            GLSLType::Mat(_, _) | GLSLType::DMat(_, _) => {
+                return Err(syn::Error::new(
+                    span,
+                    "A matrix can't be vertex data, pass each of its columns as a vector instead",
+                ))
+            }
+            // The shader sees one vertex at a time so the array is only in the data that is bound
+            GLSLType::ArrayBool
+            | GLSLType::ArrayInt
+            | GLSLType::ArrayUint
+            | GLSLType::ArrayFloat
+            | GLSLType::ArrayDouble
+            | GLSLType::ArrayBVec(_)
+            | GLSLType::ArrayIVec(_)
+            | GLSLType::ArrayUVec(_)
+            | GLSLType::ArrayVec(_)
+            | GLSLType::ArrayDVec(_) => {
+                return Err(syn::Error::new(
+                    span,
+                    "Vertex data is declared as the type of one vertex, like `vec3` for data bound as a `vec3[]`",
+                ))
+            }
+            GLSLType::Sampler
+            | GLSLType::SamplerShadow
+            | GLSLType::TextureCube
+            | GLSLType::Texture2D
+            | GLSLType::Texture2DArray
+            | GLSLType::Image2D
+            | GLSLType::Image3D
+            | GLSLType::UImage2D => {
+                return Err(syn::Error::new(
+                    span,
+                    "Samplers, textures and images can't be vertex data, they are bound in a group",
+                ))
+            }
+            // A vertex buffer is read one attribute at a time and a struct has no vertex format
+            GLSLType::Struct(name) | GLSLType::ArrayStruct(name) => {
+                return Err(syn::Error::new(
+                    span,
+                    format!("`{}` is a struct so it can't be vertex data", name),
+                ))
+            }
+        })
     }
 }
 
@@ -100,6 +149,8 @@ impl Parse for GLSLType {
         while !input.is_empty() {
             let _x;
             bracketed!(_x in input);
+            // The length of a `shared` array is read by `Parameters`
+            _x.parse::<proc_macro2::TokenStream>()?;
             arr_num += 1;
         }
         match (glsl_type.to_string().as_ref(), arr_num) {
@@ -112,9 +163,11 @@ impl Parse for GLSLType {
             ("vec3", 0) => Ok(GLSLType::Vec(GLSLDimension::Three)),
             ("vec4", 0) => Ok(GLSLType::Vec(GLSLDimension::Four)),
             ("mat4", 0) => Ok(GLSLType::Mat(GLSLDimension::Four, GLSLDimension::Four)),
+            ("bool", 1) => Ok(GLSLType::ArrayBool),
             ("int", 1) => Ok(GLSLType::ArrayInt),
             ("uint", 1) => Ok(GLSLType::ArrayUint),
             ("float", 1) => Ok(GLSLType::ArrayFloat),
+            ("double", 1) => Ok(GLSLType::ArrayDouble),
             ("vec2", 1) => Ok(GLSLType::ArrayVec(GLSLDimension::Two)),
             ("vec3", 1) => Ok(GLSLType::ArrayVec(GLSLDimension::Three)),
             ("vec4", 1) => Ok(GLSLType::ArrayVec(GLSLDimension::Four)),
@@ -123,10 +176,99 @@ impl Parse for GLSLType {
             ("texture2D", 0) => Ok(GLSLType::Texture2D),
             ("texture2DArray", 0) => Ok(GLSLType::Texture2DArray),
             ("textureCube", 0) => Ok(GLSLType::TextureCube),
-            (x, _) => {
-                panic!("We currently do not support {}", x)
-            }
+            ("image2D", 0) => Ok(GLSLType::Image2D),
+            ("image3D", 0) => Ok(GLSLType::Image3D),
+            ("uimage2D", 0) => Ok(GLSLType::UImage2D),
+            (x, arr_num) => match (split_dimensions(x), arr_num) {
+                (Some(("bvec", dim, None)), 0) => Ok(GLSLType::BVec(dim)),
+                (Some(("ivec", dim, None)), 0) => Ok(GLSLType::IVec(dim)),
+                (Some(("uvec", dim, None)), 0) => Ok(GLSLType::UVec(dim)),
+                (Some(("dvec", dim, None)), 0) => Ok(GLSLType::DVec(dim)),
+                (Some(("bvec", dim, None)), 1) => Ok(GLSLType::ArrayBVec(dim)),
+                (Some(("ivec", dim, None)), 1) => Ok(GLSLType::ArrayIVec(dim)),
+                (Some(("uvec", dim, None)), 1) => Ok(GLSLType::ArrayUVec(dim)),
+                (Some(("dvec", dim, None)), 1) => Ok(GLSLType::ArrayDVec(dim)),
+                // `mat3` is the same as `mat3x3`
+                (Some(("mat", cols, rows)), 0) => Ok(GLSLType::Mat(cols, rows.unwrap_or(cols))),
+                (Some(("dmat", cols, rows)), 0) => Ok(GLSLType::DMat(cols, rows.unwrap_or(cols))),
+                (Some(("mat", _, _)), _) | (Some(("dmat", _, _)), _) => Err(syn::Error::new(
+                    glsl_type.span(),
+                    format!(
+                        "There are no arrays of matrices, use an array of a struct with a {} field instead",
+                        x
+                    ),
+                )),
+                _ if is_glsl_type(x) => Err(syn::Error::new(
+                    glsl_type.span(),
+                    format!("We currently do not support {}{}", x, "[]".repeat(arr_num)),
+                )),
+                // Any other type is a struct and the compiler will complain if it doesn't implement `WgpuStruct`
+                (_, 0) => Ok(GLSLType::Struct(glsl_type)),
+                (_, 1) => Ok(GLSLType::ArrayStruct(glsl_type)),
+                _ => Err(syn::Error::new(
+                    glsl_type.span(),
+                    format!("A struct can only be in an array of one dimension, not {}", x),
+                )),
+            },
+        }
+    }
+}
+
+// Whether a name is one of GLSL's own types, so it can't be the name of a struct
+fn is_glsl_type(name: &str) -> bool {
+    let base = match split_dimensions(name) {
+        Some((prefix, _, _)) => prefix,
+        None => name,
+    };
+    [
+        "void",
+        "bool",
+        "int",
+        "uint",
+        "float",
+        "double",
+        "vec",
+        "bvec",
+        "ivec",
+        "uvec",
+        "dvec",
+        "mat",
+        "dmat",
+        "atomic_uint",
+    ]
+    .contains(&base)
+        || [
+            "sampler",
+            "isampler",
+            "usampler",
+            "texture",
+            "itexture",
+            "utexture",
+            "image",
+            "iimage",
+            "uimage",
+            "subpassInput",
+        ]
+        .iter()
+        .any(|p| name.starts_with(p))
+}
+
+// Splits `dvec3` into `("dvec", Three, None)` and `mat2x4` into `("mat", Two, Some(Four))`
+fn split_dimensions(name: &str) -> Option<(&str, GLSLDimension, Option<GLSLDimension>)> {
+    let dimension = |c| match c {
+        "2" => Some(GLSLDimension::Two),
+        "3" => Some(GLSLDimension::Three),
+        "4" => Some(GLSLDimension::Four),
+        _ => None,
+    };
+    let split = name.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, dims) = name.split_at(split);
+    match dims.len() {
+        1 => Some((prefix, dimension(dims)?, None)),
+        3 if &dims[1..2] == "x" => {
+            Some((prefix, dimension(&dims[..1])?, Some(dimension(&dims[2..])?)))
         }
+        _ => None,
     }
 }
 
@@ -136,12 +278,25 @@ impl Parse for GLSLType {
 // where `[buffer loop in out]` is a list of qualifiers
 // where `uint[]` is the type
 // where `indices` is the name of the parameter
+// It can be preceded by `@group(n) @binding(m)` or `@location(n)` to give the numbers it is declared with
 #[derive(Debug, Clone)]
 struct Parameters {
     group: Option<Ident>,
     quals: Vec<Ident>, //todo Add Qualifiers parsing instead of ident
     glsl_type: GLSLType,
     name: Ident,
+    // The length of a `shared` array, a number or the name of a `const` parameter
+    len: Option<proc_macro2::TokenTree>,
+    annotations: Vec<(Ident, u32)>,
+}
+
+impl Parameters {
+    fn annotation(&self, name: &str) -> Option<u32> {
+        self.annotations
+            .iter()
+            .find(|(a, _)| a == name)
+            .map(|(_, n)| *n)
+    }
 }
 
 impl PartialEq for Parameters {
@@ -153,6 +308,31 @@ impl Eq for Parameters {}
 
 impl Parse for Parameters {
     fn parse(input: ParseStream) -> Result<Self> {
+        let mut annotations: Vec<(Ident, u32)> = Vec::new();
+        while input.peek(Token![@]) {
+            input.parse::<Token![@]>()?;
+            let kind = input.parse::<Ident>()?;
+            let number;
+            parenthesized!(number in input);
+            let number = number.parse::<syn::LitInt>()?.base10_parse::<u32>()?;
+            if kind != "group" && kind != "binding" && kind != "location" {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!(
+                        "There is no `@{}`, only `@group`, `@binding` and `@location`",
+                        kind
+                    ),
+                ));
+            }
+            if annotations.iter().any(|(a, _)| *a == kind) {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!("`@{}` is given twice", kind),
+                ));
+            }
+            annotations.push((kind, number));
+        }
+
         let qual_and_type;
         bracketed!(qual_and_type in input);
         let group = if qual_and_type.peek(Ident) {
@@ -172,24 +352,156 @@ impl Parse for Parameters {
             } else if qual_lst.peek(Token!(in)) {
                 qual_lst.parse::<Token!(in)>()?;
                 quals.push(format_ident!("in"));
+            } else if qual_lst.peek(Token!(const)) {
+                qual_lst.parse::<Token!(const)>()?;
+                quals.push(format_ident!("const"));
             } else {
                 quals.push(qual_lst.parse::<Ident>()?);
             }
         }
 
+        let len =
+            {
+                let fork = qual_and_type.fork();
+                fork.parse::<Ident>()?;
+                if fork.peek(syn::token::Bracket) {
+                    let content;
+                    bracketed!(content in fork);
+                    let mut tokens = content.parse::<proc_macro2::TokenStream>()?.into_iter();
+                    match (tokens.next(), tokens.next()) {
+                        (None, _) => None,
+                        (Some(t), None) => Some(t),
+                        (Some(t), Some(_)) => return Err(syn::Error::new(
+                            t.span(),
+                            "The length of an array is a number or the name of a const parameter",
+                        )),
+                    }
+                } else {
+                    None
+                }
+            };
+        let type_span = qual_and_type.span();
         let mut glsl_type = qual_and_type.parse::<GLSLType>()?;
 
         // Then the input into the parameter is from an array and we need to promote it to an array
         if quals.contains(&format_ident!("vertex")) {
-            glsl_type = glsl_type.arrayify();
+            glsl_type = glsl_type.arrayify(type_span)?;
         }
 
         let name = input.parse::<Ident>()?;
+        // Push constants are set on the pass like any other input but can't be written by the shader
+        if quals.contains(&format_ident!("push"))
+            && (quals.contains(&format_ident!("out")) || !quals.contains(&format_ident!("in")))
+        {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "`{}` is a push constant so it needs to be `in` and not `out`",
+                    name
+                ),
+            ));
+        }
+        if quals
+            .iter()
+            .any(|q| INTERPOLATION.contains(&q.to_string().as_str()))
+            && !is_varying(&group, &quals)
+        {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "`{}` is not passed between the vertex and fragment shader so it can't have an interpolation qualifier",
+                    name
+                ),
+            ));
+        }
+        check_image(&name, &group, &glsl_type, &quals)?;
+        // Workgroup memory is declared by the shader instead of being bound
+        if quals.contains(&format_ident!("shared")) {
+            if quals.len() != 1 || group.is_some() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "`{}` is shared so it can't be in a group or have any other qualifiers",
+                        name
+                    ),
+                ));
+            }
+            match &len {
+                Some(proc_macro2::TokenTree::Ident(_)) | Some(proc_macro2::TokenTree::Literal(_)) => {}
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!(
+                            "`{}` is shared so it needs to be an array with a length like `float[64]` or `float[TILE]`",
+                            name
+                        ),
+                    ))
+                }
+            }
+        } else if len.is_some() {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "Only shared arrays have a length so `{}` needs to be `[]`",
+                    name
+                ),
+            ));
+        }
+        // Constants are given when the program is compiled instead of being bound
+        if quals.contains(&format_ident!("const")) {
+            if quals.len() != 1 {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!(
+                        "`{}` is a const so it can't have any other qualifiers",
+                        name
+                    ),
+                ));
+            }
+            match glsl_type {
+                GLSLType::Bool
+                | GLSLType::Int
+                | GLSLType::Uint
+                | GLSLType::Float
+                | GLSLType::Double => {}
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("`{}` is a const so it needs to be a scalar", name),
+                    ))
+                }
+            }
+        }
+        // Only bound parameters have a group and binding and only the ins and outs of the stages have a location
+        for (kind, _) in &annotations {
+            let has_location = group.is_none()
+                && !name.to_string().starts_with("gl_")
+                && !quals
+                    .iter()
+                    .any(|q| q == "push" || q == "const" || q == "shared");
+            if kind == "location" && !has_location {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!(
+                        "`{}` is not passed between stages so it has no location",
+                        name
+                    ),
+                ));
+            }
+            if kind != "location" && group.is_none() {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!("`{}` is not in a group so it has no group or binding", name),
+                ));
+            }
+        }
         Ok(Parameters {
             group,
             glsl_type,
             quals: quals.into_iter().collect(),
             name,
+            len,
+            annotations,
         })
     }
 }
@@ -198,76 +510,802 @@ impl Parse for Parameters {
 // todo Later this should also contain the body of the shader
 struct Shader {
     params: Vec<Parameters>, //body: String,
+    // The `gl_` parameters, which are checked once the stage of the shader is known
+    builtins: Vec<Parameters>,
+    // `workgroup_size(x, y, z);` with each a number or the name of an integer `const`
+    workgroup_size: Option<(Ident, Vec<proc_macro2::TokenTree>)>,
+}
+
+// Checks a `gl_` parameter against the builtins of the stage it is declared in
+// The stage is only known when the shader is part of a vertex and fragment pair or is a lone compute shader
+fn check_builtin(p: &Parameters, stage: Option<Stage>) -> Result<()> {
+    let name = p.name.to_string();
+    let err = |msg: String| Err(syn::Error::new(p.name.span(), msg));
+    if let Some((_, new)) = REPLACED.iter().find(|(old, _)| *old == name) {
+        return err(format!(
+            "`{}` is only in OpenGL, the Vulkan GLSL that wgpu uses calls it `{}`",
+            name, new
+        ));
+    }
+    let builtin = match find_builtin(&name) {
+        Some(b) => b,
+        None => return err(format!("`{}` is not a builtin", name)),
+    };
+    if let Some(stage) = stage {
+        if builtin.stage != stage {
+            return err(format!(
+                "`{}` is a builtin of {:?} shaders so it can't be used in a {:?} shader",
+                name, builtin.stage, stage
+            ));
+        }
+    }
+    if syn::parse_str::<GLSLType>(builtin.gtype).ok().as_ref() != Some(&p.glsl_type) {
+        return err(format!("`{}` has the type {}", name, builtin.gtype));
+    }
+    let is_out = p.quals.contains(&format_ident!("out"));
+    if builtin.is_out && !is_out {
+        return err(format!(
+            "`{}` is written by the shader so it needs to be `out`",
+            name
+        ));
+    }
+    if !builtin.is_out && is_out {
+        return err(format!(
+            "`{}` is read by the shader so it can't be `out`",
+            name
+        ));
+    }
+    Ok(())
 }
 
-fn is_gl_builtin(p: &str) -> bool {
-    let builtin = vec![
-        "gl_VertexID",
-        "gl_InstanceID",
-        "gl_FragCoord",
-        "gl_FrontFacing",
-        "gl_PointCoord",
-        "gl_SampleID",
-        "gl_SamplePosition",
-        "gl_NumWorkGroups",
-        "gl_WorkGroupID",
-        "gl_LocalInvocationID",
-        "gl_GlobalInvocationID",
-        "gl_LocalInvocationIndex",
-    ];
-    builtin.contains(&p)
+// What comes before the body of a shader, the libraries it imports, its workgroup size and its parameters
+// `shader_file!` parses the start of a file with it to find where the body begins
+pub(crate) struct Header {
+    params: Vec<Parameters>,
+    workgroup_size: Option<(Ident, Vec<proc_macro2::TokenTree>)>,
 }
 
-impl Parse for Shader {
+impl Parse for Header {
     fn parse(input: ParseStream) -> Result<Self> {
+        // The libraries from `shader_lib!` that the shader imports only matter once it is compiled
+        if input.peek(Token![use]) {
+            input.parse::<Token![use]>()?;
+            Punctuated::<Ident, Token![,]>::parse_separated_nonempty(input)?;
+            input.parse::<Token![;]>()?;
+        }
+
+        let workgroup_size = if input.peek(Ident) {
+            let keyword = input.parse::<Ident>()?;
+            if keyword != "workgroup_size" {
+                return Err(syn::Error::new(
+                    keyword.span(),
+                    format!(
+                        "Expected a parameter or `workgroup_size` but found `{}`",
+                        keyword
+                    ),
+                ));
+            }
+            let sizes;
+            parenthesized!(sizes in input);
+            let sizes =
+                Punctuated::<proc_macro2::TokenTree, Token![,]>::parse_separated_nonempty(&sizes)?;
+            input.parse::<Token![;]>()?;
+            Some((keyword, sizes.into_iter().collect()))
+        } else {
+            None
+        };
+
         let mut params = Vec::new();
-        while !input.peek(syn::token::Brace) {
+        while !input.is_empty() && !input.peek(syn::token::Brace) {
             let p = input.parse::<Parameters>()?;
-            if !is_gl_builtin(&p.name.to_string()) {
-                params.push(p);
+            if p.name == DISPATCH_SIZE {
+                return Err(syn::Error::new(
+                    p.name.span(),
+                    format!(
+                        "`{}` is the size of the dispatch, which every compute shader is given",
+                        DISPATCH_SIZE
+                    ),
+                ));
             }
-
+            params.push(p);
             input.parse::<Token![;]>()?;
         }
+        Ok(Header {
+            params,
+            workgroup_size,
+        })
+    }
+}
+
+impl Parse for Shader {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let Header {
+            params,
+            workgroup_size,
+        } = input.parse()?;
 
         // The body/code of the shader
         let x;
-        let y;
         braced!(x in input);
-        braced!(y in x);
-        if !y.is_empty() {
-            y.step(|cursor| {
-                (*cursor).token_stream();
-                Ok(((), syn::buffer::Cursor::empty()))
-            })?;
-            //parse_any(&y)?;
+        // The names a snippet declares aren't known here
+        let mut has_snippets = false;
+        let body = if x.peek(syn::LitStr) {
+            let lit = x.parse::<syn::LitStr>()?;
+            has_snippets = lit
+                .value()
+                .lines()
+                .any(|l| l.trim().starts_with("#include"));
+            string_body(&lit)
+        } else {
+            let y;
+            braced!(y in x);
+            Some(y.parse::<proc_macro2::TokenStream>()?)
+        };
+        let body_params: Vec<BodyParam> = params
+            .iter()
+            .map(|p| BodyParam {
+                name: &p.name,
+                is_out: p.quals.contains(&format_ident!("out")),
+                is_shared: p.quals.contains(&format_ident!("shared")),
+            })
+            .collect();
+        if let Some(body) = body {
+            // WGSL has no storage textures that are both read and written
+            if is_wgsl(&body.clone().into_iter().collect::<Vec<_>>()) {
+                let read_write = params.iter().find(|p| {
+                    is_image(&p.glsl_type)
+                        && p.quals.contains(&format_ident!("in"))
+                        && p.quals.contains(&format_ident!("out"))
+                });
+                if let Some(p) = read_write {
+                    return Err(syn::Error::new(
+                        p.name.span(),
+                        format!(
+                            "WGSL storage textures are either read or written, so `{}` can't be `in out`",
+                            p.name
+                        ),
+                    ));
+                }
+            }
+            validate_body(&body_params, body, !has_snippets)?;
         }
+
+        let (builtins, params) = params
+            .into_iter()
+            .partition(|p| p.name.to_string().starts_with("gl_"));
         Ok(Shader {
-            params: params.into_iter().collect(),
+            params,
+            builtins,
+            workgroup_size,
+        })
+    }
+}
+
+const INTERPOLATION: &[&str] = &["flat", "noperspective", "centroid", "sample"];
+
+// The bytes between the elements of a `shared` array, if the size of the element is known without the constants
+fn shared_stride(ty: &GLSLType) -> Option<usize> {
+    let scalar = match ty {
+        GLSLType::ArrayDouble | GLSLType::ArrayDVec(_) => 8,
+        _ => 4,
+    };
+    match ty {
+        GLSLType::ArrayBool
+        | GLSLType::ArrayInt
+        | GLSLType::ArrayUint
+        | GLSLType::ArrayFloat
+        | GLSLType::ArrayDouble => Some(scalar),
+        GLSLType::ArrayBVec(dim)
+        | GLSLType::ArrayIVec(dim)
+        | GLSLType::ArrayUVec(dim)
+        | GLSLType::ArrayVec(dim)
+        | GLSLType::ArrayDVec(dim) => Some(match dim {
+            GLSLDimension::Two => 2 * scalar,
+            // vec3's are padded out to a vec4
+            _ => 4 * scalar,
+        }),
+        _ => None,
+    }
+}
+
+// The workgroup size is only for compute shaders, with up to three dimensions that are each
+// a number of at least 1 or an integer `const` of the same shader
+fn check_workgroup_size(s: &Shader, stage: Option<Stage>) -> Result<()> {
+    let (keyword, sizes) = match &s.workgroup_size {
+        Some(w) => w,
+        None => return Ok(()),
+    };
+    if stage != Some(Stage::Compute) {
+        return Err(syn::Error::new(
+            keyword.span(),
+            "Only a compute shader has a workgroup size",
+        ));
+    }
+    if sizes.len() > 3 {
+        return Err(syn::Error::new(
+            sizes[3].span(),
+            "A workgroup has at most three dimensions",
+        ));
+    }
+    for size in sizes {
+        let valid = match size {
+            proc_macro2::TokenTree::Literal(l) => {
+                matches!(l.to_string().parse::<u32>(), Ok(n) if n > 0)
+            }
+            proc_macro2::TokenTree::Ident(n) => s.params.iter().any(|c| {
+                c.name == *n
+                    && c.quals.contains(&format_ident!("const"))
+                    && matches!(c.glsl_type, GLSLType::Int | GLSLType::Uint)
+            }),
+            _ => false,
+        };
+        if !valid {
+            return Err(syn::Error::new(
+                size.span(),
+                format!(
+                    "`{}` needs to be a number of at least 1 or an `int` or `uint` const parameter to be a workgroup size",
+                    size
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// `shared` arrays are only in compute shaders and are sized by a number or an integer `const` of the same shader.
+// When every size is a number the total is checked against the workgroup memory there is.
+// The value of a `const` only comes from Rust when the program is compiled, so once an array is sized by one
+// the rest are left to `compile`, which returns an error for them instead.
+fn check_shared(params: &[Parameters], stage: Option<Stage>) -> Result<()> {
+    let mut total = Some(0);
+    for p in params
+        .iter()
+        .filter(|p| p.quals.contains(&format_ident!("shared")))
+    {
+        let err = |msg: String| Err(syn::Error::new(p.name.span(), msg));
+        if stage != Some(Stage::Compute) {
+            return err(format!(
+                "`{}` is shared so it can only be used in a compute shader",
+                p.name
+            ));
+        }
+        let len = match p.len.as_ref().unwrap() {
+            proc_macro2::TokenTree::Literal(l) => match l.to_string().parse::<usize>() {
+                Ok(n) => Some(n),
+                Err(_) => return err(format!("The length of `{}` is not a number", p.name)),
+            },
+            proc_macro2::TokenTree::Ident(n) => {
+                let is_int_const = params.iter().any(|c| {
+                    c.name == *n
+                        && c.quals.contains(&format_ident!("const"))
+                        && matches!(c.glsl_type, GLSLType::Int | GLSLType::Uint)
+                });
+                if !is_int_const {
+                    return Err(syn::Error::new(
+                        n.span(),
+                        format!("`{}` needs to be an `int` or `uint` const parameter to be the length of `{}`", n, p.name),
+                    ));
+                }
+                None
+            }
+            _ => unreachable!(),
+        };
+        total = match (total, len, shared_stride(&p.glsl_type)) {
+            (Some(t), Some(len), Some(stride)) => Some(t + len * stride),
+            _ => None,
+        };
+        if let Some(t) = total {
+            if t > MAX_WORKGROUP_STORAGE_SIZE {
+                return err(format!(
+                    "The shared arrays up to `{}` take {} bytes of workgroup memory but only {} can be used",
+                    p.name, t, MAX_WORKGROUP_STORAGE_SIZE
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The texel formats of storage images with the Rust type of a texel and whether it is unsigned integers
+const IMAGE_FORMATS: &[(&str, &str, bool)] = &[
+    ("rgba8", "[u8; 4]", false),
+    ("rgba32f", "[f32; 4]", false),
+    ("r32f", "f32", false),
+    ("rgba32ui", "[u32; 4]", true),
+    ("r32ui", "u32", true),
+];
+
+fn is_image(ty: &GLSLType) -> bool {
+    matches!(
+        ty,
+        GLSLType::Image2D | GLSLType::Image3D | GLSLType::UImage2D
+    )
+}
+
+// The format qualifier of an image, or the default for its type
+fn image_format(ty: &GLSLType, quals: &[Ident]) -> &'static (&'static str, &'static str, bool) {
+    IMAGE_FORMATS
+        .iter()
+        .find(|(f, _, _)| quals.iter().any(|q| q == f))
+        .unwrap_or(match ty {
+            GLSLType::UImage2D => &IMAGE_FORMATS[4],
+            _ => &IMAGE_FORMATS[1],
+        })
+}
+
+// Images are bound in a group and can only be qualified with how they are accessed and one format
+// that has the kind of texel the type of image has
+fn check_image(name: &Ident, group: &Option<Ident>, ty: &GLSLType, quals: &[Ident]) -> Result<()> {
+    let formats: Vec<&Ident> = quals
+        .iter()
+        .filter(|q| IMAGE_FORMATS.iter().any(|(f, _, _)| q == f))
+        .collect();
+    if !is_image(ty) {
+        if !formats.is_empty() {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("`{}` is not an image so it can't have a format", name),
+            ));
+        }
+        return Ok(());
+    }
+    let err = |msg: String| Err(syn::Error::new(name.span(), msg));
+    if group.is_none() {
+        return err(format!("`{}` is an image so it needs a group", name));
+    }
+    if let Some(q) = quals
+        .iter()
+        .find(|q| *q != "in" && *q != "out" && !formats.contains(q))
+    {
+        return err(format!(
+            "`{}` is an image so it can't be `{}`, only `in`, `out` and a format",
+            name, q
+        ));
+    }
+    if !quals.iter().any(|q| q == "in" || q == "out") {
+        return err(format!(
+            "`{}` is an image so it needs to be `in`, `out` or both",
+            name
+        ));
+    }
+    if formats.len() > 1 {
+        return err(format!("`{}` can only have one format", name));
+    }
+    let is_uint = *ty == GLSLType::UImage2D;
+    if image_format(ty, quals).2 != is_uint {
+        return err(format!(
+            "`{}` has a format of {} texels which a {:?} can't hold",
+            name,
+            if is_uint { "float" } else { "unsigned integer" },
+            ty
+        ));
+    }
+    Ok(())
+}
+
+// Whether a parameter is passed from the vertex shader to the fragment shader instead of being bound
+fn is_varying(group: &Option<Ident>, quals: &[Ident]) -> bool {
+    group.is_none()
+        && !quals.iter().any(|q| {
+            ["vertex", "uniform", "buffer", "push", "const", "shared"]
+                .contains(&q.to_string().as_str())
         })
+}
+
+fn interpolation_quals(p: &Parameters) -> Vec<String> {
+    let mut quals: Vec<String> = p
+        .quals
+        .iter()
+        .map(|q| q.to_string())
+        .filter(|q| INTERPOLATION.contains(&q.as_str()))
+        .collect();
+    quals.sort();
+    quals
+}
+
+// The out of the vertex shader and the in of the fragment shader need to be interpolated the same way,
+// and only floats can be interpolated
+fn check_varying(out: &Parameters, input: &Parameters) -> Result<()> {
+    if !is_varying(&out.group, &out.quals) || !is_varying(&input.group, &input.quals) {
+        return Ok(());
+    }
+    if interpolation_quals(out) != interpolation_quals(input) {
+        return Err(syn::Error::new(
+            input.name.span(),
+            format!(
+                "`{}` needs the same interpolation qualifiers as the out of the same name, which has {:?}",
+                input.name,
+                interpolation_quals(out)
+            ),
+        ));
+    }
+    let scalar = scalar_type(&input.glsl_type);
+    if scalar != "f32" && !input.quals.contains(&format_ident!("flat")) {
+        return Err(syn::Error::new(
+            input.name.span(),
+            format!(
+                "`{}` is made of {} which can't be interpolated so it needs to be `flat`",
+                input.name, scalar
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// The group and binding of every parameter in a bind group, numbered the same way the shaders are.
+// Annotations that disagree are an error and so are gaps, since the layout has a bind group for each index
+// and a BindGroup binds its data from 0.
+fn bind_group_numbers(shaders: &[&Shader]) -> Result<HashMap<Ident, (u32, u32)>> {
+    let mut grouped: Vec<&Parameters> = Vec::new();
+    for p in shaders
+        .iter()
+        .flat_map(|s| s.params.iter())
+        .filter(|p| p.group.is_some())
+    {
+        if !grouped.contains(&p) {
+            grouped.push(p);
+        }
+    }
+    for (i, p) in grouped.iter().enumerate() {
+        let err = |msg: String| Err(syn::Error::new(p.name.span(), msg));
+        for q in &grouped[..i] {
+            let same_group = p.group == q.group;
+            match (p.annotation("group"), q.annotation("group")) {
+                (Some(a), Some(b)) if same_group && a != b => {
+                    return err(format!(
+                        "`{}` is @group({}) but `{}` of the same group is @group({})",
+                        p.name, a, q.name, b
+                    ))
+                }
+                (Some(a), Some(b)) if !same_group && a == b => {
+                    return err(format!(
+                        "`{}` and `{}` are both @group({}) but they are in different groups",
+                        p.name, q.name, a
+                    ))
+                }
+                _ => {}
+            }
+            if let (true, Some(a), Some(b)) =
+                (same_group, p.annotation("binding"), q.annotation("binding"))
+            {
+                if a == b {
+                    return err(format!(
+                        "`{}` and `{}` of the same group are both @binding({})",
+                        p.name, q.name, a
+                    ));
+                }
+            }
+        }
+    }
+    let labels: Vec<String> = grouped
+        .iter()
+        .map(|p| p.group.as_ref().unwrap().to_string())
+        .collect();
+    let numbers = assign_bindings(
+        &grouped
+            .iter()
+            .zip(&labels)
+            .map(|(p, label)| {
+                (
+                    label.as_str(),
+                    p.annotation("group"),
+                    p.annotation("binding"),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let mut distinct_labels = labels.clone();
+    distinct_labels.sort();
+    distinct_labels.dedup();
+    for (p, (group, binding)) in grouped.iter().zip(&numbers) {
+        let size = grouped.iter().filter(|q| q.group == p.group).count() as u32;
+        if *group >= distinct_labels.len() as u32 {
+            return Err(syn::Error::new(
+                p.name.span(),
+                format!(
+                    "`{}` is @group({}) but there are only {} groups, which are numbered from 0 without gaps",
+                    p.name,
+                    group,
+                    distinct_labels.len()
+                ),
+            ));
+        }
+        if *binding >= size {
+            return Err(syn::Error::new(
+                p.name.span(),
+                format!(
+                    "`{}` is @binding({}) but its group has {} parameters, whose bindings are numbered from 0 without gaps",
+                    p.name, binding, size
+                ),
+            ));
+        }
+    }
+    Ok(grouped
+        .iter()
+        .map(|p| p.name.clone())
+        .zip(numbers)
+        .collect())
+}
+
+// A value passed by location, the same name can be both an in and an out of a stage
+struct Location {
+    name: Ident,
+    stage: Stage,
+    is_out: bool,
+    location: u32,
+}
+
+// Two ins or two outs of a stage can't have the same location and a value passed from the vertex shader
+// to the fragment shader has the same location on both sides. The outs of the fragment shader are its
+// color attachments so their locations are numbered from 0 without gaps.
+// Returns the location of every value passed by location.
+fn locations(shaders: &[&Shader]) -> Result<Vec<Location>> {
+    let is_out = |p: &Parameters| {
+        p.quals.contains(&format_ident!("out")) && !p.quals.contains(&format_ident!("in"))
+    };
+    let has_location = |p: &&Parameters| {
+        p.group.is_none()
+            && !p
+                .quals
+                .iter()
+                .any(|q| q == "push" || q == "const" || q == "shared")
+    };
+    let number = |located: &[(&Parameters, Option<u32>)]| -> Result<Vec<(Ident, u32)>> {
+        for (i, (p, location)) in located.iter().enumerate() {
+            if let Some(l) = location {
+                if let Some((q, _)) = located[..i].iter().find(|(_, m)| m == location) {
+                    return Err(syn::Error::new(
+                        p.name.span(),
+                        format!("`{}` and `{}` are both @location({})", p.name, q.name, l),
+                    ));
+                }
+            }
+        }
+        let numbers = assign_numbers(&located.iter().map(|(_, l)| *l).collect::<Vec<_>>());
+        Ok(located
+            .iter()
+            .map(|(p, _)| p.name.clone())
+            .zip(numbers)
+            .collect())
+    };
+    if shaders.len() != 2 {
+        if let Some(p) = shaders
+            .iter()
+            .flat_map(|s| s.params.iter())
+            .find(|p| p.annotation("location").is_some())
+        {
+            return Err(syn::Error::new(
+                p.name.span(),
+                format!("`{}` has a location but only a vertex and fragment shader pass values by location", p.name),
+            ));
+        }
+        return Ok(Vec::new());
+    }
+    let (vertex, fragment) = (shaders[0], shaders[1]);
+    let attributes: Vec<(&Parameters, Option<u32>)> = vertex
+        .params
+        .iter()
+        .filter(has_location)
+        .filter(|p| !is_out(p))
+        .map(|p| (p, p.annotation("location")))
+        .collect();
+    let attribute_locations = number(&attributes)?;
+    for (name, l) in &attribute_locations {
+        if *l >= MAX_VERTEX_ATTRIBUTES {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "`{}` is @location({}) but the vertex data of a pipeline only goes up to @location({})",
+                    name,
+                    l,
+                    MAX_VERTEX_ATTRIBUTES - 1
+                ),
+            ));
+        }
+    }
+    let mut varyings = Vec::new();
+    for p in vertex
+        .params
+        .iter()
+        .filter(has_location)
+        .filter(|p| is_out(p))
+    {
+        let input = fragment.params.iter().find(|f| f.name == p.name);
+        let location = match (
+            p.annotation("location"),
+            input.and_then(|f| f.annotation("location")),
+        ) {
+            (Some(a), Some(b)) if a != b => {
+                return Err(syn::Error::new(
+                    input.unwrap().name.span(),
+                    format!(
+                        "`{}` is @location({}) but the out of the vertex shader is @location({})",
+                        p.name, b, a
+                    ),
+                ))
+            }
+            (a, b) => a.or(b),
+        };
+        varyings.push((p, location));
+    }
+    let located = |numbered: Vec<(Ident, u32)>, stage: Stage, is_out: bool| {
+        numbered.into_iter().map(move |(name, location)| Location {
+            name,
+            stage,
+            is_out,
+            location,
+        })
+    };
+    let mut locations: Vec<Location> = located(attribute_locations, Stage::Vertex, false).collect();
+    for (name, location) in number(&varyings)? {
+        let is_read = fragment
+            .params
+            .iter()
+            .any(|f| f.name == name && !is_out(f) && has_location(&f));
+        if is_read {
+            locations.extend(located(
+                vec![(name.clone(), location)],
+                Stage::Fragment,
+                false,
+            ));
+        }
+        locations.extend(located(vec![(name, location)], Stage::Vertex, true));
+    }
+    let targets: Vec<(&Parameters, Option<u32>)> = fragment
+        .params
+        .iter()
+        .filter(has_location)
+        .filter(|p| is_out(p))
+        .map(|p| (p, p.annotation("location")))
+        .collect();
+    let target_locations = number(&targets)?;
+    for (name, l) in &target_locations {
+        if *l >= targets.len() as u32 {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "`{}` is @location({}) but there are only {} color attachments, which are numbered from 0 without gaps",
+                    name,
+                    l,
+                    targets.len()
+                ),
+            ));
+        }
     }
+    locations.extend(located(target_locations, Stage::Fragment, true));
+    Ok(locations)
 }
 
 // Contains the parameters of one or more shaders which make up a pipeline context
-// Will be created for the user at `context`
+// Will be created for the user at `context`, or declared as the type `context` with `vis`
 struct Context {
+    vis: syn::Visibility,
     context: Ident,
     ins: Vec<Parameters>,
     outs: Vec<Parameters>,
+    consts: Vec<Parameters>,
+    // The outs of the fragment shader, in the order of their color attachments
+    targets: Vec<Parameters>,
+    // The group and binding of each parameter in a bind group
+    bindings: HashMap<Ident, (u32, u32)>,
+    // The stages whose shaders declare each parameter in a bind group
+    visibility: HashMap<Ident, Vec<Stage>>,
+    locations: Vec<Location>,
+    // The stage of each shader, if it is known from how many there are
+    stages: Vec<Option<Stage>>,
+}
+
+// A shader on its own is a compute shader unless its builtins say which stage it is for,
+// or it has the vertex data, ins or outs of a graphics shader
+fn single_stage(s: &Shader) -> Option<Stage> {
+    if let Some(b) = s
+        .builtins
+        .iter()
+        .find_map(|p| find_builtin(&p.name.to_string()))
+    {
+        return Some(b.stage);
+    }
+    if s.params
+        .iter()
+        .any(|p| p.quals.contains(&format_ident!("vertex")))
+    {
+        return Some(Stage::Vertex);
+    }
+    let has_location = |p: &Parameters| {
+        p.group.is_none()
+            && !p
+                .quals
+                .iter()
+                .any(|q| q == "push" || q == "const" || q == "shared")
+    };
+    if s.params.iter().any(has_location) {
+        None
+    } else {
+        Some(Stage::Compute)
+    }
 }
 
 impl Parse for Context {
     fn parse(input: ParseStream) -> Result<Self> {
+        let vis = input.parse::<syn::Visibility>()?;
         let context = input.parse::<Ident>()?;
         input.parse::<Token![=]>()?;
         let shaders = Punctuated::<Shader, Token![,]>::parse_separated_nonempty(input)?;
 
+        let stages = match shaders.len() {
+            1 => vec![single_stage(&shaders[0])],
+            2 => vec![Some(Stage::Vertex), Some(Stage::Fragment)],
+            n => vec![None; n],
+        };
+        let mut visibility: HashMap<Ident, Vec<Stage>> = HashMap::new();
+        for (s, &stage) in shaders.iter().zip(&stages) {
+            for p in s.params.iter().filter(|p| p.group.is_some()) {
+                let stages = visibility.entry(p.name.clone()).or_default();
+                match stage {
+                    Some(stage) => stages.push(stage),
+                    None => *stages = vec![Stage::Vertex, Stage::Fragment, Stage::Compute],
+                }
+            }
+            for p in &s.builtins {
+                check_builtin(p, stage)?;
+            }
+            check_shared(&s.params, stage)?;
+            check_workgroup_size(s, stage)?;
+            if let Some(p) = s.params.iter().find(|p| is_image(&p.glsl_type)) {
+                if stage != Some(Stage::Compute) {
+                    return Err(syn::Error::new(
+                        p.name.span(),
+                        format!(
+                            "`{}` is an image so it can only be used in a compute shader",
+                            p.name
+                        ),
+                    ));
+                }
+            }
+        }
+
         let mut ins = Vec::new();
         let mut outs = Vec::new();
+        let mut consts = Vec::new();
+
+        let bindings = bind_group_numbers(&shaders.iter().collect::<Vec<_>>())?;
+        let locations = locations(&shaders.iter().collect::<Vec<_>>())?;
+        let targets = if shaders.len() == 2 {
+            let location = |p: &Parameters| {
+                locations
+                    .iter()
+                    .find(|l| l.name == p.name && l.stage == Stage::Fragment && l.is_out)
+                    .unwrap()
+                    .location
+            };
+            let mut targets: Vec<Parameters> = shaders
+                .last()
+                .unwrap()
+                .params
+                .iter()
+                .filter(|p| {
+                    p.group.is_none()
+                        && p.quals.contains(&format_ident!("out"))
+                        && !p.quals.contains(&format_ident!("in"))
+                })
+                .cloned()
+                .collect();
+            targets.sort_by_key(location);
+            targets
+        } else {
+            Vec::new()
+        };
 
-        shaders.into_iter().for_each(|s| {
-            s.params.into_iter().for_each(|p| {
-                if p.quals.contains(&format_ident!("in")) {
+        for s in shaders.into_iter() {
+            for p in s.params.into_iter() {
+                if p.quals.contains(&format_ident!("const")) {
+                    if !consts.contains(&p) {
+                        consts.push(p);
+                    }
+                } else if p.quals.contains(&format_ident!("in")) || is_image(&p.glsl_type) {
+                    if let Some(out) = outs.iter().find(|o| **o == p) {
+                        check_varying(out, &p)?;
+                    }
                     if !outs.contains(&p) && !ins.contains(&p) {
                         ins.push(p);
                     }
@@ -278,24 +1316,54 @@ impl Parse for Context {
                         outs.push(p);
                     }
                 }
-            })
-        });
+            }
+        }
 
         Ok(Context {
+            vis,
             context,
             ins: ins.into_iter().collect(),
             outs: outs.into_iter().collect(),
+            consts,
+            targets,
+            bindings,
+            visibility,
+            locations,
+            stages,
         })
     }
 }
 
+// The Rust type of each component of a scalar, vector or matrix
+fn scalar_type(ty: &GLSLType) -> syn::Ident {
+    match ty {
+        GLSLType::Bool | GLSLType::BVec(_) | GLSLType::ArrayBool | GLSLType::ArrayBVec(_) => {
+            format_ident!("bool")
+        }
+        GLSLType::Int | GLSLType::IVec(_) | GLSLType::ArrayInt | GLSLType::ArrayIVec(_) => {
+            format_ident!("i32")
+        }
+        GLSLType::Uint | GLSLType::UVec(_) | GLSLType::ArrayUint | GLSLType::ArrayUVec(_) => {
+            format_ident!("u32")
+        }
+        GLSLType::Double
+        | GLSLType::DVec(_)
+        | GLSLType::DMat(_, _)
+        | GLSLType::ArrayDouble
+        | GLSLType::ArrayDVec(_) => format_ident!("f64"),
+        _ => format_ident!("f32"),
+    }
+}
+
+// Square matrices are cgmath matrices
 fn create_mat_type(
     data_type: &mut syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
-    // maybe other args with other mat types
+    scalar_type: syn::Ident,
+    n: &GLSLDimension,
 ) {
     let mut inner_type = syn::punctuated::Punctuated::new();
     inner_type.push(syn::PathSegment {
-        ident: format_ident!("f32"),
+        ident: scalar_type,
         arguments: syn::PathArguments::None,
     });
     let mut mat_type = syn::punctuated::Punctuated::new();
@@ -312,7 +1380,7 @@ fn create_mat_type(
         arguments: syn::PathArguments::None,
     });
     data_type.push(syn::PathSegment {
-        ident: format_ident!("Matrix4"),
+        ident: format_ident!("Matrix{}", <&str>::from(n)),
         arguments: syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
             args: mat_type,
             colon2_token: None,
@@ -400,10 +1468,71 @@ fn create_vec_array_type(
     });
 }
 
+// Nested arrays of a scalar with the innermost length first, so `[[f32; ROWS]; COLS]` for a matrix
+fn create_array_type(scalar_type: syn::Ident, lengths: &[&GLSLDimension]) -> syn::Type {
+    let mut scalar_path = syn::punctuated::Punctuated::new();
+    scalar_path.push(syn::PathSegment {
+        ident: scalar_type,
+        arguments: syn::PathArguments::None,
+    });
+    let scalar = syn::Type::Path(syn::TypePath {
+        qself: None,
+        path: syn::Path {
+            leading_colon: None,
+            segments: scalar_path,
+        },
+    });
+    lengths.iter().fold(scalar, |elem, n| {
+        syn::Type::Array(syn::TypeArray {
+            bracket_token: syn::token::Bracket(proc_macro2::Span::call_site()),
+            elem: Box::new(elem),
+            semi_token: Token!(;)(proc_macro2::Span::call_site()),
+            len: syn::Expr::Lit(syn::ExprLit {
+                attrs: Vec::new(),
+                lit: syn::Lit::Int(syn::LitInt::new(
+                    (*n).into(),
+                    proc_macro2::Span::call_site(),
+                )),
+            }),
+        })
+    })
+}
+
+// A storage buffer that is only `in` is read only, so it can't be bound where the shader writes to it
+fn storage_access(qualifiers: &[Ident]) -> Option<bool> {
+    if qualifiers.contains(&format_ident!("buffer")) {
+        Some(
+            qualifiers.contains(&format_ident!("in"))
+                && !qualifiers.contains(&format_ident!("out")),
+        )
+    } else {
+        None
+    }
+}
+
+// `storage` is whether a storage buffer is read only, uniforms have none
 fn create_buffer_type(
     data_type: &mut syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
     generic: syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
-    is_buffer: bool,
+    storage: Option<bool>,
+) {
+    create_buffer_type_of(
+        data_type,
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            path: syn::Path {
+                leading_colon: None,
+                segments: generic,
+            },
+        }),
+        storage,
+    )
+}
+
+fn create_buffer_type_of(
+    data_type: &mut syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
+    generic: syn::Type,
+    storage: Option<bool>,
 ) {
     let mut buffer_binding_type_path = syn::punctuated::Punctuated::new();
     buffer_binding_type_path.push(syn::PathSegment {
@@ -414,7 +1543,7 @@ fn create_buffer_type(
         ident: format_ident!("BufferBindingType"),
         arguments: syn::PathArguments::None,
     });
-    if is_buffer {
+    if storage.is_some() {
         buffer_binding_type_path.push(syn::PathSegment {
             ident: format_ident!("Storage"),
             arguments: syn::PathArguments::None,
@@ -426,7 +1555,7 @@ fn create_buffer_type(
         });
     }
     let mut buffer_binding_type_field = syn::punctuated::Punctuated::new();
-    if is_buffer {
+    if let Some(read_only) = storage {
         buffer_binding_type_field.push(syn::FieldValue {
             attrs: Vec::new(),
             member: syn::Member::Named(format_ident!("read_only")),
@@ -434,7 +1563,7 @@ fn create_buffer_type(
             expr: syn::Expr::Lit(syn::ExprLit {
                 attrs: Vec::new(),
                 lit: syn::Lit::Bool(syn::LitBool {
-                    value: false,
+                    value: read_only,
                     span: proc_macro2::Span::call_site(),
                 }),
             }),
@@ -455,13 +1584,7 @@ fn create_buffer_type(
             rest: None,
         },
     )));
-    generic_args.push(syn::GenericArgument::Type(syn::Type::Path(syn::TypePath {
-        qself: None,
-        path: syn::Path {
-            leading_colon: None,
-            segments: generic,
-        },
-    })));
+    generic_args.push(syn::GenericArgument::Type(generic));
 
     data_type.push(syn::PathSegment {
         ident: format_ident!("pipeline"),
@@ -697,6 +1820,32 @@ fn create_texture_type(
     });
 }
 
+// pipeline::bind::StorageTextureData<{ wgpu::StorageTextureAccess::WriteOnly }, { wgpu::TextureViewDimension::D2 }, [u8; 4]>
+fn create_storage_texture_type(
+    data_type: &mut syn::punctuated::Punctuated<syn::PathSegment, syn::token::Colon2>,
+    ty: &GLSLType,
+    view_dimension: syn::Ident,
+    qualifiers: &[Ident],
+) {
+    let access = match (
+        qualifiers.contains(&format_ident!("in")),
+        qualifiers.contains(&format_ident!("out")),
+    ) {
+        (true, false) => format_ident!("ReadOnly"),
+        (false, true) => format_ident!("WriteOnly"),
+        _ => format_ident!("ReadWrite"),
+    };
+    let texel: syn::Type = syn::parse_str(image_format(ty, qualifiers).1).unwrap();
+    let path: syn::Path = syn::parse_quote! {
+        pipeline::bind::StorageTextureData<
+            { wgpu::StorageTextureAccess::#access },
+            { wgpu::TextureViewDimension::#view_dimension },
+            #texel
+        >
+    };
+    data_type.extend(path.segments);
+}
+
 fn create_base_type(ty: &GLSLType, qualifiers: &Vec<Ident>) -> syn::GenericArgument {
     let mut data_type = syn::punctuated::Punctuated::new();
     match ty {
@@ -704,61 +1853,64 @@ fn create_base_type(ty: &GLSLType, qualifiers: &Vec<Ident>) -> syn::GenericArgum
             //Vec<f32>
             let mut generic_type = syn::punctuated::Punctuated::new();
             create_type(&mut generic_type, format_ident!("f32"));
-            create_buffer_type(
-                &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
-            );
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
         }
         GLSLType::Vec(dim) | GLSLType::ArrayVec(dim) => {
             //Vec<[f32; dim]>
             let mut generic_type = syn::punctuated::Punctuated::new();
             create_vec_array_type(&mut generic_type, format_ident!("f32"), dim);
-            create_buffer_type(
-                &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
-            );
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
+        }
+        GLSLType::Bool | GLSLType::Double => {
+            //bool or f64
+            let mut generic_type = syn::punctuated::Punctuated::new();
+            create_type(&mut generic_type, scalar_type(ty));
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
+        }
+        GLSLType::BVec(dim)
+        | GLSLType::IVec(dim)
+        | GLSLType::UVec(dim)
+        | GLSLType::DVec(dim)
+        | GLSLType::ArrayBVec(dim)
+        | GLSLType::ArrayIVec(dim)
+        | GLSLType::ArrayUVec(dim)
+        | GLSLType::ArrayDVec(dim) => {
+            //Vec<[i32; dim]>
+            let mut generic_type = syn::punctuated::Punctuated::new();
+            create_vec_array_type(&mut generic_type, scalar_type(ty), dim);
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
         }
-        GLSLType::Mat(GLSLDimension::Four, GLSLDimension::Four) => {
+        GLSLType::Mat(cols, rows) | GLSLType::DMat(cols, rows) if cols == rows => {
             //cgmath::Matrix4<f32>
             let mut generic_type = syn::punctuated::Punctuated::new();
-            create_mat_type(&mut generic_type);
-            create_buffer_type(
+            create_mat_type(&mut generic_type, scalar_type(ty), cols);
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
+        }
+        GLSLType::Mat(cols, rows) | GLSLType::DMat(cols, rows) => {
+            //[[f32; rows]; cols]
+            create_buffer_type_of(
                 &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
+                create_array_type(scalar_type(ty), &[rows, cols]),
+                storage_access(qualifiers),
             );
         }
-        GLSLType::Int => {
+        GLSLType::Int | GLSLType::ArrayInt => {
             //Vec<i32>
             let mut generic_type = syn::punctuated::Punctuated::new();
             create_vec_type(&mut generic_type, format_ident!("i32"));
-            create_buffer_type(
-                &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
-            );
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
         }
         GLSLType::Uint | GLSLType::ArrayUint => {
             //todo what is the difference between this data type and ArratUint
             // Vec<u32>
             let mut generic_type = syn::punctuated::Punctuated::new();
             create_vec_type(&mut generic_type, format_ident!("u32"));
-            create_buffer_type(
-                &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
-            );
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
         }
-        GLSLType::ArrayFloat => {
+        GLSLType::ArrayBool | GLSLType::ArrayFloat | GLSLType::ArrayDouble => {
             let mut generic_type = syn::punctuated::Punctuated::new();
-            create_vec_type(&mut generic_type, format_ident!("f32"));
-            create_buffer_type(
-                &mut data_type,
-                generic_type,
-                qualifiers.contains(&format_ident!("buffer")),
-            );
+            create_vec_type(&mut generic_type, scalar_type(ty));
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
         }
         GLSLType::Sampler => {
             create_sampler_type(&mut data_type, qualifiers);
@@ -776,7 +1928,24 @@ fn create_base_type(ty: &GLSLType, qualifiers: &Vec<Ident>) -> syn::GenericArgum
         GLSLType::TextureCube => {
             create_texture_type(&mut data_type, format_ident!("Cube"), qualifiers);
         }
-        _ => panic!("Unsupported type: {:?}", ty),
+        GLSLType::Image2D | GLSLType::UImage2D => {
+            create_storage_texture_type(&mut data_type, ty, format_ident!("D2"), qualifiers);
+        }
+        GLSLType::Image3D => {
+            create_storage_texture_type(&mut data_type, ty, format_ident!("D3"), qualifiers);
+        }
+        GLSLType::Struct(name) => {
+            // MyStruct
+            let mut generic_type = syn::punctuated::Punctuated::new();
+            create_type(&mut generic_type, name.clone());
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
+        }
+        GLSLType::ArrayStruct(name) => {
+            // Vec<MyStruct>
+            let mut generic_type = syn::punctuated::Punctuated::new();
+            create_vec_type(&mut generic_type, name.clone());
+            create_buffer_type(&mut data_type, generic_type, storage_access(qualifiers));
+        }
     }
 
     syn::GenericArgument::Type(syn::Type::Path(syn::TypePath {
@@ -788,6 +1957,42 @@ fn create_base_type(ty: &GLSLType, qualifiers: &Vec<Ident>) -> syn::GenericArgum
     }))
 }
 
+// Push constants hold a single value, so unlike uniforms their vectors and integers are not `Vec`s
+fn create_push_type(ty: &GLSLType, qualifiers: &Vec<Ident>) -> syn::Type {
+    let mut data_type = syn::punctuated::Punctuated::new();
+    match ty {
+        GLSLType::Int | GLSLType::Uint => {
+            //i32
+            let mut generic_type = syn::punctuated::Punctuated::new();
+            create_type(&mut generic_type, scalar_type(ty));
+            create_buffer_type(&mut data_type, generic_type, None);
+        }
+        GLSLType::BVec(dim)
+        | GLSLType::IVec(dim)
+        | GLSLType::UVec(dim)
+        | GLSLType::Vec(dim)
+        | GLSLType::DVec(dim) => {
+            //[f32; dim]
+            create_buffer_type_of(
+                &mut data_type,
+                create_array_type(scalar_type(ty), &[dim]),
+                None,
+            );
+        }
+        _ => match create_base_type(ty, qualifiers) {
+            syn::GenericArgument::Type(ty) => return ty,
+            _ => unreachable!(),
+        },
+    }
+    syn::Type::Path(syn::TypePath {
+        qself: None,
+        path: syn::Path {
+            leading_colon: None,
+            segments: data_type,
+        },
+    })
+}
+
 fn create_vertex(ty: &GLSLType, quals: &Vec<Ident>) -> syn::Type {
     let mut bind_ty = syn::punctuated::Punctuated::new();
     bind_ty.push(create_base_type(ty, quals));
@@ -857,6 +2062,7 @@ fn create_bindgroup(ty: Vec<(&GLSLType, &Vec<Ident>)>) -> syn::Type {
 enum ParamType {
     Vertex { num: u32, param: Parameters },
     Group { num: u32, param: Vec<Parameters> },
+    Push { num: u32, param: Parameters },
 }
 
 impl ParamType {
@@ -864,6 +2070,7 @@ impl ParamType {
         match self {
             ParamType::Vertex { num: _, param } => vec![param.clone()],
             ParamType::Group { num: _, param } => param.clone(),
+            ParamType::Push { num: _, param } => vec![param.clone()],
         }
     }
 
@@ -871,6 +2078,7 @@ impl ParamType {
         match self {
             ParamType::Vertex { num: _, param: _ } => unreachable!(),
             ParamType::Group { num: _, param } => param,
+            ParamType::Push { num: _, param: _ } => unreachable!(),
         }
     }
 
@@ -878,31 +2086,42 @@ impl ParamType {
         match self {
             ParamType::Vertex { num, param: _ } => *num,
             ParamType::Group { num, param: _ } => *num,
+            ParamType::Push { num, param: _ } => *num,
         }
     }
 }
 
 //todo This should create a new group if there is no group name.
 // Only create a vertex when there is the vertex qualifier
-fn process_params(params: Vec<Parameters>) -> Vec<ParamType> {
+// Push constants are numbered in the order they are declared which is also their order in the block
+// Groups are set at the index the shaders declare them and their data is in the order of their bindings
+fn process_params(
+    params: Vec<Parameters>,
+    bindings: &HashMap<Ident, (u32, u32)>,
+) -> Vec<ParamType> {
     let mut res = Vec::new();
-    let mut group_map: HashMap<Ident, ParamType> = HashMap::new();
+    // A Vec instead of a map so the Context is generated the same way every time
+    let mut groups: Vec<(Ident, ParamType)> = Vec::new();
     let mut num_vertex = 0;
-    let mut num_groups = 0;
+    let mut num_push = 0;
     params.into_iter().for_each(|p| match p.group.clone() {
-        Some(g) if group_map.contains_key(&g) => {
-            group_map.get_mut(&g).unwrap().get_params_mut().push(p)
+        _ if p.quals.contains(&format_ident!("push")) => {
+            res.push(ParamType::Push {
+                num: num_push,
+                param: p,
+            });
+            num_push += 1
         }
-        Some(g) => {
-            group_map.insert(
+        Some(g) => match groups.iter_mut().find(|(name, _)| *name == g) {
+            Some((_, group)) => group.get_params_mut().push(p),
+            None => groups.push((
                 g,
                 ParamType::Group {
-                    num: num_groups,
+                    num: bindings[&p.name].0,
                     param: vec![p],
                 },
-            );
-            num_groups += 1
-        }
+            )),
+        },
         None => {
             res.push(ParamType::Vertex {
                 num: num_vertex,
@@ -911,7 +2130,11 @@ fn process_params(params: Vec<Parameters>) -> Vec<ParamType> {
             num_vertex += 1
         }
     });
-    res.append(&mut group_map.into_iter().map(|(_, p)| p).collect());
+    for (_, group) in groups.iter_mut() {
+        group.get_params_mut().sort_by_key(|p| bindings[&p.name].1);
+    }
+    groups.sort_by_key(|(_, group)| group.get_num());
+    res.append(&mut groups.into_iter().map(|(_, p)| p).collect());
     res
 }
 
@@ -968,6 +2191,23 @@ fn unbound() -> syn::Type {
     })
 }
 
+// The data that is bound to a parameter of the Context
+fn param_data_type(param: &ParamType) -> syn::Type {
+    match param {
+        ParamType::Vertex { param, .. } => create_vertex(&param.glsl_type, &param.quals),
+        ParamType::Group { param, .. } => {
+            create_bindgroup(param.iter().map(|p| (&p.glsl_type, &p.quals)).collect())
+        }
+        ParamType::Push { param, .. } => create_push_type(&param.glsl_type, &param.quals),
+    }
+}
+
+// The name of a parameter at the type level, `in1` is `(pipeline::Char<'i'>, pipeline::Char<'n'>, pipeline::Char<'1'>)`
+fn name_marker(name: &Ident) -> proc_macro2::TokenStream {
+    let chars: Vec<char> = name.to_string().chars().collect();
+    quote! { (#(pipeline::Char<#chars>,)*) }
+}
+
 // Implementation 3
 pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
     // Parse the input tokens into a syntax tree
@@ -978,12 +2218,11 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
 
     let out_vec = shader_params.outs;
 
-    let input_params = process_params(input_vec);
-
-    let mut rng = rand::thread_rng();
+    let input_params = process_params(input_vec, &shader_params.bindings);
 
-    let n1: u8 = rng.gen();
-    let context = format_ident!("Context{}", n1);
+    let vis = shader_params.vis;
+    // The Context is named by the caller so two Contexts in one scope can't be given the same type by accident
+    let context = shader_params.context;
 
     let mut all_expanded = Vec::new();
 
@@ -997,14 +2236,52 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
         .collect();
     let init: Vec<syn::Type> = iter::repeat(unbound()).take(input_params.len()).collect();
     let run: Vec<syn::Type> = iter::repeat(bound()).take(input_params.len()).collect();
-    let ctxloc = shader_params.context;
+    let const_names: Vec<&Ident> = shader_params.consts.iter().map(|p| &p.name).collect();
+    // Constants are usually named like Rust consts, which a parameter of the same name would try to match against,
+    // and can be named like keywords, so the arguments keep the name behind a prefix
+    let const_args: Vec<Ident> = const_names
+        .iter()
+        .map(|n| format_ident!("const_{}", n))
+        .collect();
+    let const_types: Vec<Ident> = shader_params
+        .consts
+        .iter()
+        .map(|p| scalar_type(&p.glsl_type))
+        .collect();
+    // One color attachment for each out of the fragment shader, which the render pass is created with
+    let target_args: Vec<Ident> = shader_params
+        .targets
+        .iter()
+        .map(|p| format_ident!("target_{}", p.name))
+        .collect();
+    // A Context with a program is only created by the program, which it is compiled through instead
+    let has_program = matches!(
+        shader_params.stages.as_slice(),
+        [Some(Stage::Compute)] | [Some(Stage::Vertex), Some(Stage::Fragment)]
+    );
+    let receiver = if has_program {
+        quote! {}
+    } else {
+        quote! { &self, }
+    };
+    // The values of the constants are typed by the parameters they are for
+    let constants = if const_names.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #[allow(non_snake_case)]
+            #vis fn constants(#receiver #(#const_args: #const_types),*) -> Vec<(&'static str, pipeline::shared::ConstValue)> {
+                vec![#((stringify!(#const_names), #const_args.into())),*]
+            }
+        }
+    };
 
     // For setting up pipeline
     let mut bind_group_types: Vec<(u32, syn::Type)> = input_params
         .clone()
         .into_iter()
         .filter_map(|a| match a {
-            ParamType::Vertex { .. } => None,
+            ParamType::Vertex { .. } | ParamType::Push { .. } => None,
             ParamType::Group { num, param } => Some((
                 num,
                 create_bindgroup(param.iter().map(|p| (&p.glsl_type, &p.quals)).collect()),
@@ -1013,73 +2290,400 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
         .collect();
     bind_group_types.sort_by(|(a, _), (b, _)| a.cmp(b));
 
+    let group_numbers: Vec<u32> = bind_group_types.iter().map(|(n, _)| *n).collect();
     let sorted_bind_group_types: Vec<syn::Type> =
         bind_group_types.into_iter().map(|(_, x)| x).collect();
 
+    let visibility = &shader_params.visibility;
+    let mut bindings: Vec<(&Ident, &(u32, u32))> = shader_params.bindings.iter().collect();
+    bindings.sort_by_key(|(_, numbers)| **numbers);
+    let binding_descriptions: Vec<proc_macro2::TokenStream> = bindings
+        .into_iter()
+        .map(|(name, (group, binding))| {
+            let visibility = visibility[name]
+                .iter()
+                .map(|stage| format_ident!("{}", format!("{:?}", stage)));
+            let name = name.to_string();
+            quote! {
+                pipeline::shared::BindingDescription {
+                    name: #name,
+                    group: #group,
+                    binding: #binding,
+                    visibility: &[#(pipeline::builtins::Stage::#visibility),*],
+                }
+            }
+        })
+        .collect();
+    let location_descriptions: Vec<proc_macro2::TokenStream> = shader_params
+        .locations
+        .iter()
+        .map(|l| {
+            let name = l.name.to_string();
+            let stage = format_ident!("{}", format!("{:?}", l.stage));
+            let (is_out, location) = (l.is_out, l.location);
+            quote! {
+                pipeline::shared::LocationDescription {
+                    name: #name,
+                    stage: pipeline::builtins::Stage::#stage,
+                    is_out: #is_out,
+                    location: #location,
+                }
+            }
+        })
+        .collect();
+
+    // The types of every push constant so the offset of each one in the block can be found
+    let push_types: Vec<syn::Type> = input_params
+        .iter()
+        .filter_map(|a| match a {
+            ParamType::Push { param, .. } => Some(create_push_type(&param.glsl_type, &param.quals)),
+            _ => None,
+        })
+        .collect();
+
+    let num_params = input_params.len();
+    // The outs of the bind groups with their slot and entry, whose data is kept so that it can be piped into the next program
+    let outs: Vec<(usize, usize, Parameters)> = input_params
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| match a {
+            ParamType::Group { param, .. } => param
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.quals.contains(&format_ident!("out")))
+                .map(|(k, p)| (i, k, p.clone()))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+    let out_fields: Vec<Ident> = outs
+        .iter()
+        .map(|(_, _, p)| format_ident!("out_{}", p.name))
+        .collect();
+    let out_types: Vec<syn::GenericArgument> = outs
+        .iter()
+        .map(|(_, _, p)| create_base_type(&p.glsl_type, &p.quals))
+        .collect();
+    // The state of the parameter of each out, which decides whether its entry is kept
+    let out_variables: Vec<&syn::Type> =
+        outs.iter().map(|(slot, _, _)| &variables[*slot]).collect();
+    // The vertex parameters stepped per vertex and per instance, whose shortest buffers limit a draw
+    let vertex_slots = |per_vertex: bool| -> Vec<usize> {
+        input_params
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| match a {
+                ParamType::Vertex { param, .. } => {
+                    param.quals.iter().any(|q| q == "vertex") == per_vertex
+                }
+                _ => false,
+            })
+            .map(|(i, _)| i)
+            .collect()
+    };
+    let instance_slots = vertex_slots(false);
+    let vertex_slots = vertex_slots(true);
+    let num_vertex_slots = vertex_slots.len();
+    let num_instance_slots = instance_slots.len();
+
+    // Keeps the case of the Context, `AB` and `Ab` are different types
+    let layout = format_ident!("{}_LAYOUT", context);
+    // The layout and the constants of the shaders, which the program is compiled with when there is one
+    let context_items = quote! {
+        #vis fn get_layout(#receiver device: &wgpu::Device) -> pipeline::shared::ContextLayout {
+            pipeline::shared::ContextLayout {
+                bind_group_layouts: vec![#(#sorted_bind_group_types::get_layout(device, #layout.visibility(#group_numbers)),)*],
+                description: &#layout,
+            }
+        }
+        #constants
+    };
+    let context_items_unprogrammed = if has_program {
+        quote! {}
+    } else {
+        context_items.clone()
+    };
+
+    // The program is compiled on its own, and binding to a pass starts from it with the pipeline set
+    let program = format_ident!("{}Program", context);
+    // A pass is only bound on and run through a Context once it is created by the program,
+    // a render pass with an attachment for each out of the fragment shader
+    let pass = format_ident!("{}Pass", context);
+    let program_type = match shader_params.stages.as_slice() {
+        [Some(Stage::Compute)] => quote! {
+            #[allow(dead_code)]
+            #vis struct #program {
+                program: pipeline::wgpu_compute_header::ComputeProgram,
+            }
+
+            #[allow(dead_code)]
+            impl #program {
+                #vis async fn compile(compute: &pipeline::wgpu_compute_header::ComputeShader, device: &wgpu::Device, args: pipeline::wgpu_compute_header::ComputeCompileArgs) -> Result<Self, pipeline::shared::ShaderCompileError> {
+                    Ok(#program {
+                        program: pipeline::wgpu_compute_header::compile(compute, device, Self::get_layout(device), args).await?,
+                    })
+                }
+
+                #context_items
+
+                #vis fn compute_pass<'a>(&self, encoder: &'a mut wgpu::CommandEncoder) -> #pass<'a> {
+                    #pass {
+                        pass: encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None }),
+                        local_size: self.program.local_size,
+                        dispatch_group: self.program.dispatch_group,
+                    }
+                }
+
+                // Takes over the pass of another program, which can't be run on once this program has it
+                #vis fn continue_pass<'a, P: pipeline::ProgramPass<'a, Pass = wgpu::ComputePass<'a>>>(&self, cpass: P) -> #pass<'a> {
+                    #pass {
+                        pass: cpass.into_pass(),
+                        local_size: self.program.local_size,
+                        dispatch_group: self.program.dispatch_group,
+                    }
+                }
+
+                #vis fn begin<'a>(&'a self, cpass: &mut #pass<'a>) -> #context<'a, #pass<'a>, #(#init),*> {
+                    cpass.pass.set_pipeline(&self.program.pipeline);
+                    #context::new()
+                }
+            }
+
+            impl<'a> pipeline::ProgramPass<'a> for #pass<'a> {
+                type Pass = wgpu::ComputePass<'a>;
+                fn into_pass(self) -> Self::Pass {
+                    self.pass
+                }
+            }
+
+            #vis struct #pass<'a> {
+                pass: wgpu::ComputePass<'a>,
+                // The size of each workgroup of the program, which a dispatch is divided into
+                local_size: [u32; 3],
+                // The group after those of the Context, which the size of a dispatch is bound in
+                dispatch_group: u32,
+            }
+
+            impl<'a> pipeline::RuntimePass<'a> for #pass<'a> {
+                fn set_bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup, offsets: &[wgpu::DynamicOffset]) {
+                    pipeline::RuntimePass::set_bind_group(&mut self.pass, index, bind_group, offsets)
+                }
+
+                fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+                    pipeline::RuntimePass::set_vertex_buffer(&mut self.pass, slot, buffer_slice)
+                }
+
+                fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+                    pipeline::RuntimePass::set_push_constants(&mut self.pass, offset, data)
+                }
+            }
+        },
+        [Some(Stage::Vertex), Some(Stage::Fragment)] => quote! {
+            #[allow(dead_code)]
+            #vis struct #program {
+                program: pipeline::wgpu_graphics_header::GraphicsProgram,
+            }
+
+            #[allow(dead_code)]
+            impl #program {
+                #vis async fn compile(vec_buffer: &mut [wgpu::VertexAttribute; 32], device: &wgpu::Device, vertex: &pipeline::wgpu_graphics_header::GraphicsShader, fragment: &pipeline::wgpu_graphics_header::GraphicsShader, args: pipeline::wgpu_graphics_header::GraphicsCompileArgs) -> Result<Self, pipeline::shared::ShaderCompileError> {
+                    Ok(#program {
+                        program: pipeline::wgpu_graphics_header::graphics_compile(vec_buffer, device, Self::get_layout(device), vertex, fragment, args).await?,
+                    })
+                }
+
+                #context_items
+
+                #[allow(non_snake_case)]
+                #vis fn render_pass<'a>(&self, encoder: &'a mut wgpu::CommandEncoder, #(#target_args: wgpu::RenderPassColorAttachmentDescriptor<'a>,)* depth_stencil_attachment: Option<wgpu::RenderPassDepthStencilAttachmentDescriptor<'a>>) -> #pass<'a> {
+                    #pass {
+                        pass: encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[#(#target_args),*],
+                            depth_stencil_attachment,
+                        }),
+                    }
+                }
+
+                // Takes over the pass of another program, whose attachments have to match the outs of this program
+                #vis fn continue_pass<'a, P: pipeline::ProgramPass<'a, Pass = wgpu::RenderPass<'a>>>(&self, rpass: P) -> #pass<'a> {
+                    #pass {
+                        pass: rpass.into_pass(),
+                    }
+                }
+
+                #vis fn begin<'a>(&'a self, rpass: &mut #pass<'a>) -> #context<'a, #pass<'a>, #(#init),*> {
+                    rpass.pass.set_pipeline(&self.program.pipeline);
+                    #context::new()
+                }
+            }
+
+            impl<'a> pipeline::ProgramPass<'a> for #pass<'a> {
+                type Pass = wgpu::RenderPass<'a>;
+                fn into_pass(self) -> Self::Pass {
+                    self.pass
+                }
+            }
+
+            #vis struct #pass<'a> {
+                pass: wgpu::RenderPass<'a>,
+            }
+
+            impl<'a> pipeline::RuntimePass<'a> for #pass<'a> {
+                fn set_bind_group(&mut self, index: u32, bind_group: &'a wgpu::BindGroup, offsets: &[wgpu::DynamicOffset]) {
+                    pipeline::RuntimePass::set_bind_group(&mut self.pass, index, bind_group, offsets)
+                }
+
+                fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+                    pipeline::RuntimePass::set_vertex_buffer(&mut self.pass, slot, buffer_slice)
+                }
+
+                fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+                    pipeline::RuntimePass::set_push_constants(&mut self.pass, offset, data)
+                }
+            }
+        },
+        _ => quote! {},
+    };
+
+    // Running the program needs every parameter to be bound on a pass created by the program,
+    // which is the only way to run it
+    let run_methods = match shader_params.stages.as_slice() {
+        [Some(Stage::Compute)] => quote! {
+            #[allow(dead_code)]
+            impl <'a> #context<'a, #pass<'a>, #(#run),*> {
+                // The shader is given the size so the invocations of the last workgroups that are past it do nothing
+                #vis fn dispatch(&self, cpass: &mut #pass<'a>, size: &'a pipeline::wgpu_compute_header::DispatchSize) {
+                    cpass.pass.set_bind_group(cpass.dispatch_group, &size.bind_group, &[]);
+                    let [x, y, z] = pipeline::wgpu_compute_header::workgroups(cpass.local_size, size.size);
+                    cpass.pass.dispatch(x, y, z)
+                }
+            }
+        },
+        [Some(Stage::Vertex), Some(Stage::Fragment)] => quote! {
+            #[allow(dead_code)]
+            impl <'a> #context<'a, #pass<'a>, #(#run),*> {
+                // The length of the shortest buffer bound per vertex, if any are
+                #vis fn vertex_count(&self) -> Option<u32> {
+                    let lengths: [Option<u32>; #num_vertex_slots] = [#(self.lengths[#vertex_slots]),*];
+                    lengths.iter().flatten().copied().min()
+                }
+                // The length of the shortest buffer bound per instance, if any are
+                #vis fn instance_count(&self) -> Option<u32> {
+                    let lengths: [Option<u32>; #num_instance_slots] = [#(self.lengths[#instance_slots]),*];
+                    lengths.iter().flatten().copied().min()
+                }
+                #vis fn draw(&self, rpass: &mut #pass<'a>, num_verts: u32, num_instances: u32) -> Result<(), pipeline::wgpu_graphics_header::DrawError> {
+                    pipeline::wgpu_graphics_header::check_draw_counts(num_verts, num_instances, self.vertex_count(), self.instance_count())?;
+                    rpass.pass.draw(0..num_verts, 0..num_instances);
+                    Ok(())
+                }
+                #vis fn draw_indexed(&self, rpass: &mut #pass<'a>, indices: &'a pipeline::bind::Indices, num_instances: u32) -> Result<(), pipeline::wgpu_graphics_header::DrawError> {
+                    let num_verts = indices.max_index.map_or(0, |i| i + 1);
+                    pipeline::wgpu_graphics_header::check_draw_counts(num_verts, num_instances, self.vertex_count(), self.instance_count())?;
+                    rpass.pass.set_index_buffer(indices.buffer.slice(..), indices.format);
+                    rpass.pass.draw_indexed(0..indices.len, 0, 0..num_instances);
+                    Ok(())
+                }
+            }
+        },
+        _ => quote! {},
+    };
+
+    // Once it has run, the data bound to each out is bound to the parameter of the same name in the next Context,
+    // on the pass the next program continued, one at a time or all of them with `pipe_into`
+    let pipe_into = if outs.is_empty() {
+        quote! {}
+    } else {
+        let out_names: Vec<proc_macro2::TokenStream> =
+            outs.iter().map(|(_, _, p)| name_marker(&p.name)).collect();
+        let pipe_names: Vec<Ident> = outs
+            .iter()
+            .map(|(_, _, p)| format_ident!("pipe_{}", p.name))
+            .collect();
+        let nexts: Vec<Ident> = (0..=outs.len()).map(|n| format_ident!("N{}", n)).collect();
+        let first = &nexts[0];
+        let last = &nexts[outs.len()];
+        let inputs = &nexts[..outs.len()];
+        let outputs = &nexts[1..];
+        quote! {
+            #[allow(dead_code)]
+            impl <'a,  T : pipeline :: RuntimePass<'a>> #context<'a, T, #(#run),*> {
+                #(
+                    #vis fn #pipe_names<U: pipeline::RuntimePass<'a>, N: pipeline::PipeIn<'a, U, #out_names, #out_types>>(&self, rpass: &mut U, next: N) -> N::Output {
+                        next.pipe_in(rpass, pipeline::bind::Piped::from_source(self.#out_fields))
+                    }
+                )*
+                #vis fn pipe_into<U: pipeline::RuntimePass<'a>, #(#nexts),*>(&self, rpass: &mut U, next: #first) -> #last
+                where #(#inputs: pipeline::PipeIn<'a, U, #out_names, #out_types, Output = #outputs>,)*
+                {
+                    #(let next = next.pipe_in(rpass, pipeline::bind::Piped::from_source(self.#out_fields));)*
+                    next
+                }
+            }
+        }
+    };
+
     all_expanded.push(quote! {
-        struct #context<'a,  T : pipeline :: RuntimePass<'a>, #(#variables: pipeline::AbstractBind),*> {
+        // The numbers the shaders are declared with, which are also where this Context sets its data
+        #[allow(non_upper_case_globals)]
+        const #layout: pipeline::shared::LayoutDescription = pipeline::shared::LayoutDescription {
+            bindings: &[#(#binding_descriptions),*],
+            locations: &[#(#location_descriptions),*],
+        };
+
+        #vis struct #context<'a,  T : pipeline :: RuntimePass<'a>, #(#variables: pipeline::AbstractBind),*> {
             phantom: std::marker::PhantomData<&'a T>,
             #(#fields: #variables,)*
+            // How many elements the buffer bound to each vertex parameter has
+            lengths: [Option<u32>; #num_params],
+            // The entry bound to each out, which is kept once its parameter is Bound
+            #(#out_fields: <#out_variables as pipeline::Keep<'a>>::Kept,)*
         }
 
+        #[allow(dead_code)]
         impl <'a,  T : pipeline :: RuntimePass<'a>> #context<'a, T, #(#init),*> {
+            // Binding starts from the program compiled for this Context, so other modules can't create one
             fn new() -> Self {
                 #context {
                     phantom: std::marker::PhantomData,
                     #(#fields: pipeline::Unbound {},)*
+                    lengths: [None; #num_params],
+                    #(#out_fields: (),)*
                 }
             }
-            fn get_layout(&self, device : &wgpu::Device) -> Vec<wgpu::BindGroupLayout> {
-                vec![#(#sorted_bind_group_types::get_layout(device),)*]
-            }
+            #context_items_unprogrammed
         }
 
-        impl <'a,  T : pipeline :: RuntimePass<'a>> #context<'a, T, #(#run),*> {
-            fn runnable<P, B>(&self, f: P) -> B where P: FnOnce() -> B{
-                f()
-            }
-        }
+        #run_methods
+
+        #pipe_into
+
+        #program_type
 
-        let #ctxloc = #context::new();
     });
 
     let bound = bound();
     let unbound = unbound();
 
-    let mut curr = -1;
-    let index_numbers: Vec<syn::Expr> = iter::repeat_with(|| {
-        curr += 1;
-        syn::Expr::Lit(syn::ExprLit {
-            attrs: Vec::new(),
-            lit: syn::Lit::Int(syn::LitInt::new(
-                &curr.to_string(),
-                proc_macro2::Span::call_site(),
-            )),
-        })
-    })
-    .take(input_params.len())
-    .collect();
-
     for i in 0..input_params.len() {
         let current_thing = input_params[i].clone();
-        let trait_name = format_ident!("BindField{}{}", i + 1, n1);
-
-        let bind_name = format_ident!(
-            "{}",
-            current_thing
-                .get_params()
-                .iter()
-                .fold("set".to_string(), |acc, p| format!("{}_{}", acc, p.name))
+        let trait_name = format_ident!("{}Field{}", context, i + 1);
+
+        let names = current_thing
+            .get_params()
+            .iter()
+            .fold(String::new(), |acc, p| format!("{}_{}", acc, p.name));
+        let bind_name = format_ident!("set{}", names);
+        let rebind_name = format_ident!("rebind{}", names);
+        let unset_name = format_ident!("unset{}", names);
+
+        let index = syn::LitInt::new(
+            &current_thing.get_num().to_string(),
+            proc_macro2::Span::call_site(),
         );
 
-        let index = index_numbers.get(current_thing.get_num() as usize).unwrap();
-
-        let data_type = match current_thing.clone() {
-            ParamType::Vertex { param, .. } => create_vertex(&param.glsl_type, &param.quals),
-            ParamType::Group { param, .. } => {
-                create_bindgroup(param.iter().map(|p| (&p.glsl_type, &p.quals)).collect())
-            }
-        };
+        let data_type = param_data_type(&current_thing);
 
         let mut type_params = variables.clone();
         type_params.remove(i);
@@ -1128,14 +2732,115 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
             })
             .collect();
 
+        let mut bound_impl = trait_params.clone();
+        bound_impl.insert(i, bound.clone());
+
         let mut restricted_impl = restricted_trait.clone();
         restricted_impl.insert(i, unbound.clone());
         let mut restricted_type = restricted_trait.clone();
         restricted_type.insert(i, bound.clone());
 
+        // Setting a vertex buffer records its length so that draws can be checked against it
+        // The data of each out of a group is kept until it is unset
+        let out_values = |value: &dyn Fn(usize) -> proc_macro2::TokenStream| {
+            let values = outs
+                .iter()
+                .zip(&out_fields)
+                .map(|((slot, entry, _), field)| {
+                    if *slot == i {
+                        value(*entry)
+                    } else {
+                        quote! { self.#field }
+                    }
+                });
+            quote! { #(#out_fields: #values,)* }
+        };
+        let set_outs = out_values(&|entry| {
+            let piped = format_ident!("piped_{}", entry);
+            quote! { data.#piped() }
+        });
+        let unset_outs = out_values(&|_| quote! { () });
+        let piped_outs = out_values(&|_| quote! { data.source() });
+
+        let (lengths, unset_lengths) = match current_thing {
+            ParamType::Vertex { .. } => (
+                quote! {{
+                    let mut lengths = self.lengths;
+                    lengths[#i] = Some(data.len());
+                    lengths
+                }},
+                quote! {{
+                    let mut lengths = self.lengths;
+                    lengths[#i] = None;
+                    lengths
+                }},
+            ),
+            _ => (quote! { self.lengths }, quote! { self.lengths }),
+        };
+
+        let bind_data = match current_thing {
+            ParamType::Vertex { .. } => quote! {
+                rpass.set_vertex_buffer(#index as u32, data.get_buffer().slice(..));
+            },
+            ParamType::Group { .. } => quote! {
+                rpass.set_bind_group(#index as u32, data.get_bind_group(#layout.visibility(#index as u32)), &[]);
+            },
+            ParamType::Push { .. } => quote! {
+                let offsets = pipeline::shared::push_constant_offsets(&[#(<#push_types>::GTYPE),*]);
+                data.set_push_constant(rpass, offsets[#index]);
+            },
+        };
+
+        // A bound parameter can be given new data or made unbound again without the rest of the Context changing
+        all_expanded.push(quote! {
+            #[allow(dead_code)]
+            impl<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* > #context<'a, T, #(#bound_impl),*> {
+                #vis fn #rebind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#bound_impl),*> {
+                    #bind_data
+                    #context {
+                        lengths: #lengths,
+                        #set_outs
+                        ..self
+                    }
+                }
+                #vis fn #unset_name(self) -> #context<'a, T, #(#impl_params),*> {
+                    #context {
+                        phantom: std::marker::PhantomData,
+                        #(#fields : #impl_params::new(),)*
+                        lengths: #unset_lengths,
+                        #unset_outs
+                    }
+                }
+            }
+        });
+
+        // Binding a parameter that is alone in its group by its name, for piping the out of another program into it
+        if let ParamType::Group { param, .. } = &current_thing {
+            if let [p] = param.as_slice() {
+                let name = name_marker(&p.name);
+                let param_type = create_base_type(&p.glsl_type, &p.quals);
+                all_expanded.push(quote! {
+                    impl<'a,  T : pipeline :: RuntimePass<'a>, S, #(#trait_params: pipeline::AbstractBind,)* > pipeline::PipeIn<'a, T, #name, S> for #context<'a, T, #(#impl_params),*>
+                    where S: pipeline::bind::PipeInto<#param_type>
+                    {
+                        type Output = #context<'a, T, #(#type_params),*>;
+                        fn pipe_in(self, rpass: &mut T, data: pipeline::bind::Piped<'a, S>) -> Self::Output {
+                            rpass.set_bind_group(#index as u32, data.pipe_as::<#param_type>().get_bind_group(#layout.visibility(#index as u32)), &[]);
+                            #context {
+                                phantom: std::marker::PhantomData,
+                                #(#fields : #type_params::new(),)*
+                                lengths: self.lengths,
+                                #piped_outs
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
         match current_thing {
             ParamType::Vertex{..} => all_expanded.push(quote! {
-                trait #trait_name<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* >{
+                #vis trait #trait_name<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* >{
                     fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>;
                 }
 
@@ -1144,7 +2849,9 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
                         rpass.set_vertex_buffer(#index as u32, data.get_buffer().slice(..));
                         #context {
                             phantom: std::marker::PhantomData,
-                            #(#fields : #type_params::new()),*
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
                         }
                     }
                 }
@@ -1153,36 +2860,71 @@ pub fn sub_module_generic_bindings(input: TokenStream) -> TokenStream {
                         rpass.set_vertex_buffer(#index as u32, data.get_buffer().slice(..));
                         #context {
                             phantom: std::marker::PhantomData,
-                            #(#fields : #type_params::new()),*
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
                         }
                     }
                 }
             }),
-            ParamType::Group{..} =>all_expanded.push(quote! {
-            trait #trait_name<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* >{
-                fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>;
-            }
+            ParamType::Group{..} => all_expanded.push(quote! {
+                #vis trait #trait_name<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* >{
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>;
+                }
 
-            impl<'a,  T : pipeline :: RuntimePass<'a>, #(#restricted_abstract: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#restricted_trait,)*> for &#context<'a, T, #(#restricted_impl),*> {
-                fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#restricted_type),*>{
-                    rpass.set_bind_group(#index as u32, data.get_bind_group(), &[]);
-                    #context {
-                        phantom: std::marker::PhantomData,
-                        #(#fields : #type_params::new()),*
+                impl<'a,  T : pipeline :: RuntimePass<'a>, #(#restricted_abstract: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#restricted_trait,)*> for &#context<'a, T, #(#restricted_impl),*> {
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#restricted_type),*>{
+                        rpass.set_bind_group(#index as u32, data.get_bind_group(#layout.visibility(#index as u32)), &[]);
+                        #context {
+                            phantom: std::marker::PhantomData,
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
+                        }
                     }
                 }
-            }
-            impl<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#trait_params,)*> for #context<'a, T, #(#impl_params),*> {
-                fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>{
-                    rpass.set_bind_group(#index as u32, data.get_bind_group(), &[]);
-                    #context {
-                        phantom: std::marker::PhantomData,
-                        #(#fields : #type_params::new()),*
+                impl<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#trait_params,)*> for #context<'a, T, #(#impl_params),*> {
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>{
+                        rpass.set_bind_group(#index as u32, data.get_bind_group(#layout.visibility(#index as u32)), &[]);
+                        #context {
+                            phantom: std::marker::PhantomData,
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
+                        }
                     }
                 }
-            }
+            }),
+            ParamType::Push{..} => all_expanded.push(quote! {
+                #vis trait #trait_name<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* >{
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>;
+                }
 
-        }),
+                impl<'a,  T : pipeline :: RuntimePass<'a>, #(#restricted_abstract: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#restricted_trait,)*> for &#context<'a, T, #(#restricted_impl),*> {
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#restricted_type),*>{
+                        let offsets = pipeline::shared::push_constant_offsets(&[#(<#push_types>::GTYPE),*]);
+                        data.set_push_constant(rpass, offsets[#index]);
+                        #context {
+                            phantom: std::marker::PhantomData,
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
+                        }
+                    }
+                }
+                impl<'a,  T : pipeline :: RuntimePass<'a>, #(#trait_params: pipeline::AbstractBind,)* > #trait_name<'a, T, #(#trait_params,)*> for #context<'a, T, #(#impl_params),*> {
+                    fn #bind_name(self, rpass: &mut T, data : &'a #data_type) -> #context<'a, T, #(#type_params),*>{
+                        let offsets = pipeline::shared::push_constant_offsets(&[#(<#push_types>::GTYPE),*]);
+                        data.set_push_constant(rpass, offsets[#index]);
+                        #context {
+                            phantom: std::marker::PhantomData,
+                            #(#fields : #type_params::new()),*,
+                            lengths: #lengths,
+                            #set_outs
+                        }
+                    }
+                }
+            }),
         }
     }
 
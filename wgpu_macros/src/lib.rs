@@ -10,7 +10,33 @@ pub fn create_get_view_func(input: TokenStream) -> TokenStream {
 
 mod generic_bindings;
 
+// The same table of builtins that pipeline uses
+use wgpu_common::builtins;
+
+mod numbering;
+
+mod shader_body;
+
 #[proc_macro]
 pub fn generic_bindings(input: TokenStream) -> TokenStream {
     crate::generic_bindings::sub_module_generic_bindings(input)
 }
+
+mod shader_file;
+
+#[proc_macro]
+pub fn shader_file(input: TokenStream) -> TokenStream {
+    crate::shader_file::sub_module_shader_file(input)
+}
+
+#[proc_macro]
+pub fn shader_source(input: TokenStream) -> TokenStream {
+    crate::shader_file::sub_module_shader_source(input)
+}
+
+mod wgpu_struct;
+
+#[proc_macro_derive(WgpuStruct)]
+pub fn derive_wgpu_struct(input: TokenStream) -> TokenStream {
+    crate::wgpu_struct::sub_module_wgpu_struct(input)
+}
@@ -0,0 +1,44 @@
+// How groups, bindings and locations are numbered, from `@group(n) @binding(m)` and `@location(n)` annotations
+// or else in the order the parameters are declared
+
+// Each number that is given is kept and the rest take the lowest numbers that are left, in order
+pub fn assign_numbers(given: &[Option<u32>]) -> Vec<u32> {
+    let mut next = 0;
+    given
+        .iter()
+        .map(|n| match n {
+            Some(n) => *n,
+            None => {
+                while given.contains(&Some(next)) {
+                    next += 1;
+                }
+                next += 1;
+                next - 1
+            }
+        })
+        .collect()
+}
+
+// The group and binding of each parameter from its group label, `@group` and `@binding`, in the order they are declared
+// Every parameter with the same label is in the same group, which any of them can give
+pub fn assign_bindings(params: &[(&str, Option<u32>, Option<u32>)]) -> Vec<(u32, u32)> {
+    let mut labels: Vec<(&str, Option<u32>)> = Vec::new();
+    for (label, group, _) in params {
+        match labels.iter_mut().find(|(l, _)| l == label) {
+            Some((_, g)) => *g = g.or(*group),
+            None => labels.push((label, *group)),
+        }
+    }
+    let groups = assign_numbers(&labels.iter().map(|(_, g)| *g).collect::<Vec<_>>());
+    let mut numbers = vec![(0, 0); params.len()];
+    for ((label, _), group) in labels.iter().zip(groups) {
+        let members: Vec<usize> = (0..params.len())
+            .filter(|i| params[*i].0 == *label)
+            .collect();
+        let bindings = assign_numbers(&members.iter().map(|i| params[*i].2).collect::<Vec<_>>());
+        for (i, binding) in members.into_iter().zip(bindings) {
+            numbers[i] = (group, binding);
+        }
+    }
+    numbers
+}
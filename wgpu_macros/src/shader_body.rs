@@ -0,0 +1,509 @@
+use proc_macro2::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+use syn::{Error, Ident, LitStr, Result};
+
+use std::collections::HashSet;
+
+use crate::builtins::DISPATCH_SIZE;
+
+// Words that the body can use without them being declared
+const KEYWORDS: &[&str] = &[
+    // GLSL
+    "if",
+    "else",
+    "for",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "default",
+    "return",
+    "break",
+    "continue",
+    "discard",
+    "true",
+    "false",
+    "struct",
+    "const",
+    "in",
+    "out",
+    "inout",
+    "uniform",
+    "buffer",
+    "shared",
+    "layout",
+    "flat",
+    "smooth",
+    "noperspective",
+    "centroid",
+    "highp",
+    "mediump",
+    "lowp",
+    "precision",
+    // WGSL
+    "fn",
+    "var",
+    "let",
+    "loop",
+    "continuing",
+];
+
+// Types that the body can declare locals of or construct
+const TYPES: &[&str] = &[
+    // GLSL
+    "void",
+    "bool",
+    "int",
+    "uint",
+    "float",
+    "double",
+    "bvec2",
+    "bvec3",
+    "bvec4",
+    "ivec2",
+    "ivec3",
+    "ivec4",
+    "uvec2",
+    "uvec3",
+    "uvec4",
+    "vec2",
+    "vec3",
+    "vec4",
+    "dvec2",
+    "dvec3",
+    "dvec4",
+    "mat2",
+    "mat3",
+    "mat4",
+    "mat2x2",
+    "mat2x3",
+    "mat2x4",
+    "mat3x2",
+    "mat3x3",
+    "mat3x4",
+    "mat4x2",
+    "mat4x3",
+    "mat4x4",
+    "dmat2",
+    "dmat3",
+    "dmat4",
+    "sampler",
+    "samplerShadow",
+    "texture2D",
+    "texture2DArray",
+    "textureCube",
+    "sampler2D",
+    "sampler2DShadow",
+    "sampler2DArrayShadow",
+    "samplerCube",
+    // WGSL
+    "i32",
+    "u32",
+    "f32",
+    "array",
+    "ptr",
+];
+
+// The parts of a parameter that matter when checking how the body uses it
+pub struct BodyParam<'a> {
+    pub name: &'a Ident,
+    pub is_out: bool,
+    // Shared arrays can be written without being out
+    pub is_shared: bool,
+}
+
+// A body written as a string is tokenized so it can be checked like any other body.
+// Of the preprocessor lines only the name of each `#define` is kept so the body can use it.
+// If what is left is not valid Rust tokens, like with a `'`, the body is not checked.
+pub fn string_body(body: &LitStr) -> Option<TokenStream> {
+    let code: Vec<String> = body
+        .value()
+        .lines()
+        .map(|l| {
+            let words: Vec<&str> = l.split_whitespace().collect();
+            match words.as_slice() {
+                ["#define", name, ..] => format!("#define {}", name.split('(').next().unwrap()),
+                [w, ..] if w.starts_with('#') => String::new(),
+                _ => l.to_string(),
+            }
+        })
+        .collect();
+    let tokens = code.join("\n").parse::<TokenStream>().ok()?;
+    Some(respan(tokens, body.span()))
+}
+
+// Errors in a string body point at the string
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|t| match t {
+            TokenTree::Group(g) => {
+                let mut group = Group::new(g.delimiter(), respan(g.stream(), span));
+                group.set_span(span);
+                TokenTree::Group(group)
+            }
+            mut t => {
+                t.set_span(span);
+                t
+            }
+        })
+        .collect()
+}
+
+// Check that the body only reads what is declared, only writes to outs and writes to every out
+// Without `check_declared` names that aren't declared in the body are allowed, as they can come from a snippet
+pub fn validate_body(params: &[BodyParam], body: TokenStream, check_declared: bool) -> Result<()> {
+    let tokens: Vec<TokenTree> = body.into_iter().collect();
+    if let Some(size) = find_ident(
+        &tokens,
+        &[
+            "local_size_x",
+            "local_size_y",
+            "local_size_z",
+            "workgroup_size",
+        ],
+    ) {
+        return Err(Error::new(
+            size.span(),
+            "The workgroup size is declared before the parameters with `workgroup_size(x, y, z);`",
+        ));
+    }
+    // The invocations past the size of the dispatch can't return early when the rest of their workgroup waits at a barrier
+    if let Some(p) = params.iter().find(|p| p.is_shared) {
+        if find_ident(&tokens, &[DISPATCH_SIZE]).is_none() {
+            return Err(Error::new(
+                p.name.span(),
+                format!(
+                    "`{}` is shared so the body has to check gl_GlobalInvocationID against `{}` itself",
+                    p.name, DISPATCH_SIZE
+                ),
+            ));
+        }
+    }
+    let is_wgsl = is_wgsl(&tokens);
+
+    // Names can be used before the point they are declared at in their scope, like functions in the global scope
+    let mut globals = HashSet::new();
+    collect_declarations(&tokens, is_wgsl, false, &mut globals);
+
+    let mut assigned = HashSet::new();
+    check_uses(
+        &tokens,
+        params,
+        is_wgsl,
+        check_declared,
+        &globals,
+        &mut assigned,
+    )?;
+
+    for p in params {
+        if p.is_out && !assigned.contains(&p.name.to_string()) {
+            return Err(Error::new(
+                p.name.span(),
+                format!("`{}` is declared out but the body never assigns it", p.name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// WGSL functions start with `fn` where GLSL functions start with a type
+pub fn is_wgsl(tokens: &[TokenTree]) -> bool {
+    tokens.iter().any(|t| is_ident(t, "fn"))
+}
+
+// The first identifier in the body that is one of `names`
+fn find_ident(tokens: &[TokenTree], names: &[&str]) -> Option<Ident> {
+    tokens.iter().find_map(|t| match t {
+        TokenTree::Ident(i) if names.iter().any(|n| i == n) => Some(i.clone()),
+        TokenTree::Group(g) => find_ident(&g.stream().into_iter().collect::<Vec<_>>(), names),
+        _ => None,
+    })
+}
+
+fn is_ident(t: &TokenTree, name: &str) -> bool {
+    match t {
+        TokenTree::Ident(i) => i == name,
+        _ => false,
+    }
+}
+
+fn is_punct(t: &TokenTree, c: char) -> bool {
+    match t {
+        TokenTree::Punct(p) => p.as_char() == c,
+        _ => false,
+    }
+}
+
+fn is_group(t: Option<&TokenTree>, delimiter: Delimiter) -> bool {
+    match t {
+        Some(TokenTree::Group(g)) => g.delimiter() == delimiter,
+        _ => false,
+    }
+}
+
+fn group_tokens(t: &TokenTree) -> Option<Vec<TokenTree>> {
+    match t {
+        TokenTree::Group(g) => Some(g.stream().into_iter().collect()),
+        _ => None,
+    }
+}
+
+// The operator starting at the front of `tokens` like `=`, `+=` or `==`
+fn operator(tokens: &[TokenTree]) -> String {
+    let mut op = String::new();
+    for t in tokens {
+        match t {
+            TokenTree::Punct(p) => {
+                op.push(p.as_char());
+                if p.spacing() == Spacing::Alone {
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    op
+}
+
+// The names declared in one scope. Blocks are scopes of their own, so they are never looked into,
+// but the parentheses of a block's statements like `for (int i = 0; ...)` are part of it
+fn collect_declarations(
+    tokens: &[TokenTree],
+    is_wgsl: bool,
+    nested: bool,
+    locals: &mut HashSet<String>,
+) {
+    for (idx, t) in tokens.iter().enumerate() {
+        match t {
+            TokenTree::Group(g) if nested && g.delimiter() != Delimiter::Brace => {
+                collect_declarations(&group_tokens(t).unwrap(), is_wgsl, nested, locals);
+            }
+            TokenTree::Ident(i) => {
+                let first = i.to_string();
+                match tokens.get(idx + 1) {
+                    // `var x`, `let x`, `fn f` and `struct S`
+                    Some(TokenTree::Ident(n))
+                        if is_wgsl
+                            && ["var", "let", "const", "fn", "struct"]
+                                .contains(&first.as_str()) =>
+                    {
+                        locals.insert(n.to_string());
+                    }
+                    // `float x`, `float f(...)` and `struct S`
+                    Some(TokenTree::Ident(n))
+                        if !is_wgsl
+                            && !["return", "else", "case", "do"].contains(&first.as_str()) =>
+                    {
+                        locals.insert(n.to_string());
+                        // `float x = 0, y;`
+                        let mut j = idx + 2;
+                        while j < tokens.len() && !is_punct(&tokens[j], ';') {
+                            if let (true, Some(TokenTree::Ident(m))) =
+                                (is_punct(&tokens[j], ','), tokens.get(j + 1))
+                            {
+                                let ends = match tokens.get(j + 2) {
+                                    None => true,
+                                    Some(a) => {
+                                        is_punct(a, '=')
+                                            || is_punct(a, ';')
+                                            || is_punct(a, ',')
+                                            || is_group(Some(a), Delimiter::Bracket)
+                                    }
+                                };
+                                if ends {
+                                    locals.insert(m.to_string());
+                                }
+                            }
+                            j += 1;
+                        }
+                    }
+                    // WGSL function parameters `x : u32`
+                    Some(TokenTree::Punct(p))
+                        if is_wgsl && p.as_char() == ':' && p.spacing() == Spacing::Alone =>
+                    {
+                        locals.insert(first);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Whether the identifier at `idx` is the target of an assignment or increment
+fn is_written(tokens: &[TokenTree], idx: usize) -> bool {
+    // Skip past any indexing and member accesses to find what is done with the value
+    let mut j = idx + 1;
+    loop {
+        match tokens.get(j) {
+            Some(t) if is_group(Some(t), Delimiter::Bracket) => j += 1,
+            Some(t) if is_punct(t, '.') => j += 2,
+            _ => break,
+        }
+    }
+    let op = if j < tokens.len() {
+        operator(&tokens[j..])
+    } else {
+        String::new()
+    };
+    let compound = [
+        "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    ];
+    let pre_increment = idx >= 2
+        && match (&tokens[idx - 2], &tokens[idx - 1]) {
+            (TokenTree::Punct(a), TokenTree::Punct(b)) => {
+                a.spacing() == Spacing::Joint
+                    && a.as_char() == b.as_char()
+                    && (a.as_char() == '+' || a.as_char() == '-')
+            }
+            _ => false,
+        };
+    pre_increment
+        || compound.iter().any(|c| op.starts_with(c))
+        || (op.starts_with('=') && !op.starts_with("=="))
+}
+
+// The image or memory that a call like `imageStore(img, p, value)` or `atomicAdd(x, 1)` writes to
+fn written_argument(name: &str, args: Option<&TokenTree>) -> Option<Ident> {
+    if name != "imageStore"
+        && name != "textureStore"
+        && (!name.starts_with("atomic") || name == "atomicLoad")
+        && !name.starts_with("imageAtomic")
+    {
+        return None;
+    }
+    let args = group_tokens(args?)?;
+    // WGSL takes a pointer to the atomic
+    let first = if args.first().is_some_and(|t| is_punct(t, '&')) {
+        args.get(1)
+    } else {
+        args.first()
+    };
+    match first {
+        Some(TokenTree::Ident(i)) => Some(i.clone()),
+        _ => None,
+    }
+}
+
+// The names declared by a block and by the parentheses before it, like the parameters of a function
+fn block_scope(
+    tokens: &[TokenTree],
+    idx: usize,
+    is_wgsl: bool,
+    locals: &HashSet<String>,
+) -> HashSet<String> {
+    let mut scope = locals.clone();
+    if let Some(params) = tokens[..idx]
+        .iter()
+        .rev()
+        .take_while(|t| !is_punct(t, ';') && !is_group(Some(t), Delimiter::Brace))
+        .find(|t| is_group(Some(t), Delimiter::Parenthesis))
+    {
+        collect_declarations(&group_tokens(params).unwrap(), is_wgsl, true, &mut scope);
+    }
+    collect_declarations(
+        &group_tokens(&tokens[idx]).unwrap(),
+        is_wgsl,
+        true,
+        &mut scope,
+    );
+    scope
+}
+
+fn check_uses(
+    tokens: &[TokenTree],
+    params: &[BodyParam],
+    is_wgsl: bool,
+    check_declared: bool,
+    locals: &HashSet<String>,
+    assigned: &mut HashSet<String>,
+) -> Result<()> {
+    for (idx, t) in tokens.iter().enumerate() {
+        match t {
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                let scope = block_scope(tokens, idx, is_wgsl, locals);
+                check_uses(
+                    &group_tokens(t).unwrap(),
+                    params,
+                    is_wgsl,
+                    check_declared,
+                    &scope,
+                    assigned,
+                )?;
+            }
+            // The arguments of `layout(...)` are not expressions
+            TokenTree::Group(_) if idx == 0 || !is_ident(&tokens[idx - 1], "layout") => {
+                // The parameters of a function and the start of a `for` declare names
+                let inner = group_tokens(t).unwrap();
+                let mut scope = locals.clone();
+                collect_declarations(&inner, is_wgsl, true, &mut scope);
+                check_uses(&inner, params, is_wgsl, check_declared, &scope, assigned)?;
+            }
+            TokenTree::Ident(i) => {
+                let name = i.to_string();
+                // Members and swizzles
+                if idx > 0 && is_punct(&tokens[idx - 1], '.') {
+                    continue;
+                }
+                // A local of the same name hides the parameter
+                if locals.contains(&name) {
+                    continue;
+                }
+                if let Some(written) = written_argument(&name, tokens.get(idx + 1)) {
+                    let local = locals.contains(&written.to_string());
+                    if let Some(p) = params.iter().find(|p| *p.name == written && !local) {
+                        if !p.is_out && !p.is_shared {
+                            return Err(Error::new(
+                                written.span(),
+                                format!(
+                                    "`{}` is not declared out so it can not be written to",
+                                    written
+                                ),
+                            ));
+                        }
+                        assigned.insert(written.to_string());
+                    }
+                }
+                match params.iter().find(|p| *p.name == name) {
+                    Some(p) => {
+                        if is_written(tokens, idx) {
+                            if !p.is_out && !p.is_shared {
+                                return Err(Error::new(
+                                    i.span(),
+                                    format!(
+                                        "`{}` is not declared out so it can not be written to",
+                                        name
+                                    ),
+                                ));
+                            }
+                            assigned.insert(name);
+                        }
+                    }
+                    None => {
+                        // Calls to functions and constructors, and the types of declarations like structs from `WgpuStruct`
+                        if !check_declared
+                            || is_group(tokens.get(idx + 1), Delimiter::Parenthesis)
+                            || matches!(tokens.get(idx + 1), Some(TokenTree::Ident(_)))
+                            || name.starts_with("gl_")
+                            || name == DISPATCH_SIZE
+                            || KEYWORDS.contains(&name.as_str())
+                            || TYPES.contains(&name.as_str())
+                        {
+                            continue;
+                        }
+                        return Err(Error::new(
+                            i.span(),
+                            format!(
+                                "`{}` is not a parameter or local variable of this shader",
+                                name
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{parse_macro_input, Error, Ident, LitStr, Token};
+
+use std::path::PathBuf;
+
+use crate::generic_bindings::Header;
+
+// `name = "path/to/shader.comp"` where the path is relative to the crate's Cargo.toml
+struct ShaderFile {
+    name: Ident,
+    path: LitStr,
+}
+
+impl Parse for ShaderFile {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse::<Ident>()?;
+        input.parse::<Token![=]>()?;
+        let path = input.parse::<LitStr>()?;
+        Ok(ShaderFile { name, path })
+    }
+}
+
+fn manifest_path(path: &str) -> PathBuf {
+    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(path)
+}
+
+// Replace each `#include NAME` line of a body with `NAME!()`, a snippet declared with `shader_snippet!`
+// Used by `munch_body!` as `concat!` only takes literals and macro calls
+pub fn sub_module_shader_source(input: TokenStream) -> TokenStream {
+    let body = parse_macro_input!(input as LitStr);
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    for line in body.value().split_inclusive('\n') {
+        let name = match line.trim().strip_prefix("#include") {
+            Some(rest) => rest.trim(),
+            None => {
+                text.push_str(line);
+                continue;
+            }
+        };
+        if syn::parse_str::<Ident>(name).is_err() {
+            return Error::new(
+                body.span(),
+                format!(
+                    "Expected `#include NAME` of a snippet declared with `shader_snippet!` but found `{}`",
+                    line.trim()
+                ),
+            )
+            .to_compile_error()
+            .into();
+        }
+        let snippet = Ident::new(name, body.span());
+        parts.push(quote! {#text});
+        parts.push(quote! {#snippet!()});
+        text.clear();
+    }
+    parts.push(quote! {#text});
+    TokenStream::from(quote! {concat!(#(#parts),*)})
+}
+
+// Splits a file into its parameters, which come first like `[group1 [buffer in] uint[]] indices;`, and the body after them
+// The parameters are parsed like in `my_shader!`, one `;` at a time, and the body starts where they stop parsing.
+// A part of the body before its first `;` usually isn't whole tokens yet, like an open `{`, so those are read on to the next `;`.
+// Returns the line and the error if the part after the parameters starts like one but isn't
+fn split_header(contents: &str) -> std::result::Result<(&str, &str), (usize, Error)> {
+    let mut end = 0;
+    for (n, _) in contents.match_indices(';') {
+        let tokens = match contents[..=n].parse::<proc_macro2::TokenStream>() {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        if syn::parse2::<Header>(tokens).is_err() {
+            break;
+        }
+        end = n + 1;
+    }
+
+    // Skip the comments between the parameters and the body
+    let mut start = end;
+    loop {
+        let rest = contents[start..].trim_start();
+        start = contents.len() - rest.len();
+        match rest.strip_prefix("//") {
+            Some(comment) => start += 2 + comment.find('\n').unwrap_or(comment.len()),
+            None => break,
+        }
+    }
+    let rest = &contents[start..];
+    if rest.starts_with('[')
+        || rest.starts_with('@')
+        || rest.starts_with("use ")
+        || rest.starts_with("workgroup_size")
+    {
+        let item = &rest[..rest.find(';').map_or(rest.len(), |n| n + 1)];
+        let line = contents[..start].matches('\n').count() + 1;
+        let err = match syn::parse_str::<Header>(item) {
+            Ok(_) => Error::new(Span::call_site(), "Expected a parameter"),
+            Err(e) => e,
+        };
+        return Err((line, err));
+    }
+    Ok(contents.split_at(end))
+}
+
+// Like `my_shader!` but the parameters and body are read from a file
+// The body is kept as a string so the preprocessor, comments and `++` aren't mangled by the Rust tokenizer
+pub fn sub_module_shader_file(input: TokenStream) -> TokenStream {
+    let ShaderFile { name, path } = parse_macro_input!(input as ShaderFile);
+
+    let full_path = manifest_path(&path.value());
+    let contents = match std::fs::read_to_string(&full_path) {
+        Ok(c) => c,
+        Err(e) => {
+            return syn::Error::new(
+                path.span(),
+                format!("Could not read {}: {}", full_path.display(), e),
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+
+    let (header, body) = match split_header(&contents) {
+        Ok(parts) => parts,
+        Err((line, e)) => {
+            return syn::Error::new(
+                path.span(),
+                format!(
+                    "The parameter at line {} of {} is not valid: {}",
+                    line,
+                    full_path.display(),
+                    e
+                ),
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+    // It was parsed to find where the body starts so it is whole tokens
+    let params = header.parse::<proc_macro2::TokenStream>().unwrap();
+    // Pad the body so that the lines of errors in the body are the lines of the file
+    let body = LitStr::new(
+        &format!("{}{}", "\n".repeat(header.matches('\n').count()), body),
+        path.span(),
+    );
+    let full_path = full_path.to_string_lossy().to_string();
+
+    TokenStream::from(quote! {
+        // Rebuild when the file changes
+        const _: &[u8] = include_bytes!(#full_path);
+
+        eager::eager_macro_rules! { $eager_1
+            #[macro_export]
+            macro_rules! #name{
+                ()=>{#params {#body}};
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_header;
+
+    #[test]
+    fn splits_at_the_body() {
+        let contents = "[group1 [buffer loop in] uint[]] indices;\n//[[buffer out] uint[]] result;\n\n#define N 4\nvoid main() {\n    indices[0] = N;\n}\n";
+        let (header, body) = split_header(contents).unwrap();
+        assert_eq!(
+            header,
+            "[group1 [buffer loop in] uint[]] indices;\n//[[buffer out] uint[]] result;"
+        );
+        assert!(body.trim_start().starts_with("#define N 4"));
+    }
+
+    #[test]
+    fn malformed_header() {
+        let contents = "[group1 [buffer loop in] uint[]] indices;\n[group2 [buffer in out] uint[]];\n\nvoid main() {}\n";
+        let (line, _) = split_header(contents).unwrap_err();
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn unclosed_header() {
+        let contents =
+            "workgroup_size(64);\n[group1 [buffer loop in] uint[] indices;\n\nvoid main() {}\n";
+        let (line, _) = split_header(contents).unwrap_err();
+        assert_eq!(line, 2);
+    }
+}
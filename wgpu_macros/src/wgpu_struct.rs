@@ -0,0 +1,59 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+// Implements `pipeline::bind::WgpuStruct` by describing each named field with its `WgpuField` type
+pub fn sub_module_wgpu_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => {
+                return syn::Error::new_spanned(
+                    &input.ident,
+                    "WgpuStruct can only be derived for structs with named fields",
+                )
+                .to_compile_error()
+                .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(
+                &input.ident,
+                "WgpuStruct can only be derived for structs",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &input.generics,
+            "WgpuStruct can not be derived for generic structs",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_strings = field_names.iter().map(|f| f.to_string());
+    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let idx = 0..field_names.len();
+
+    TokenStream::from(quote! {
+        impl pipeline::bind::WgpuStruct for #name {
+            const DESCRIPTION: pipeline::shared::StructDescription =
+                pipeline::shared::StructDescription {
+                    name: stringify!(#name),
+                    fields: &[#((#field_strings, <#field_types as pipeline::bind::WgpuField>::GTYPE)),*],
+                };
+
+            fn write_fields(&self, layout: pipeline::shared::BlockLayout, out: &mut [u8]) {
+                let offsets = <Self as pipeline::bind::WgpuStruct>::DESCRIPTION.offsets(layout);
+                #(pipeline::bind::WgpuField::write(&self.#field_names, layout, &mut out[offsets[#idx]..]);)*
+            }
+        }
+    })
+}